@@ -0,0 +1,177 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rustnut::assembler::*;
+use rustnut::program::*;
+use rustnut::runtime::*;
+
+// note: ループカウンタを 0 から n まで増加させる
+fn count_loop(n: u32) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let loop_i = asm.add_func(2, 1);
+
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(n))
+        .push(Opcode::Invoke, Operand::Index(loop_i))
+        .push(Opcode::Exit, Operand::None);
+
+    let func = asm.func(loop_i);
+    let loop_label = func.new_label();
+    let end_label = func.new_label();
+
+    func.push(Opcode::BPush, Operand::Byte(0))
+        .push(Opcode::Store, Operand::Short(1));
+    func.set_label(loop_label);
+    func.push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IOrd, Operand::None)
+        .push(Opcode::IfNot, Operand::Jump(end_label))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::BPush, Operand::Byte(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Store, Operand::Short(1))
+        .push(Opcode::Goto, Operand::Jump(loop_label));
+    func.set_label(end_label);
+    func.push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+// note: 戻り値が無いため fib(n) の呼び出し木を辿るのみ
+fn recursive_fib(n: u32) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let fib_i = asm.add_func(1, 1);
+
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(n))
+        .push(Opcode::Invoke, Operand::Index(fib_i))
+        .push(Opcode::Exit, Operand::None);
+
+    let func = asm.func(fib_i);
+    let rec_label = func.new_label();
+
+    func.push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::BPush, Operand::Byte(2))
+        .push(Opcode::IOrd, Operand::None)
+        .push(Opcode::IfNot, Operand::Jump(rec_label))
+        .push(Opcode::Ret, Operand::None);
+    func.set_label(rec_label);
+    func.push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::BPush, Operand::Byte(1))
+        .push(Opcode::ISub, Operand::None)
+        .push(Opcode::Invoke, Operand::Index(fib_i))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::BPush, Operand::Byte(2))
+        .push(Opcode::ISub, Operand::None)
+        .push(Opcode::Invoke, Operand::Index(fib_i))
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+// note: 長さ len の int 配列を埋めてから総和を求める
+fn array_fill_sum(len: u64) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    // note: 変数 0-1 = 配列, 2-3 = インデックス, 4 = 総和
+    let sum_i = asm.add_func(5, 0);
+
+    asm.func(main_i)
+        .push(Opcode::Invoke, Operand::Index(sum_i))
+        .push(Opcode::Exit, Operand::None);
+
+    let func = asm.func(sum_i);
+    let fill_label = func.new_label();
+    let sum_begin_label = func.new_label();
+    let sum_label = func.new_label();
+    let end_label = func.new_label();
+
+    func.push(Opcode::IAPush, Operand::Index(len as usize))
+        .push(Opcode::Store2, Operand::Short(0))
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::Store2, Operand::Short(2));
+    func.set_label(fill_label);
+    func.push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::LPush, Operand::Long(len))
+        .push(Opcode::LOrd, Operand::None)
+        .push(Opcode::IfNot, Operand::Jump(sum_begin_label))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::BPush, Operand::Byte(3))
+        .push(Opcode::IAStore, Operand::None)
+        .push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::LPush, Operand::Long(1))
+        .push(Opcode::LAdd, Operand::None)
+        .push(Opcode::Store2, Operand::Short(2))
+        .push(Opcode::Goto, Operand::Jump(fill_label));
+    func.set_label(sum_begin_label);
+    func.push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::Store2, Operand::Short(2));
+    func.set_label(sum_label);
+    func.push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::LPush, Operand::Long(len))
+        .push(Opcode::LOrd, Operand::None)
+        .push(Opcode::IfNot, Operand::Jump(end_label))
+        .push(Opcode::Load, Operand::Short(4))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::IALoad, Operand::None)
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Store, Operand::Short(4))
+        .push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::LPush, Operand::Long(1))
+        .push(Opcode::LAdd, Operand::None)
+        .push(Opcode::Store2, Operand::Short(2))
+        .push(Opcode::Goto, Operand::Jump(sum_label));
+    func.set_label(end_label);
+    func.push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Drop, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+// note: ヘッダの検証とプール要素の読み込みは Program::load で済ませ, 計測対象を Interpreter::run のみとする
+fn launch(program: &Program, config: &InterpreterConfig) -> RunResult {
+    let result = unsafe {
+        Interpreter::launch_program(program, config, None)
+    };
+
+    match result.exit_status {
+        ExitStatus::Success => (),
+        _ => panic!("benchmark program exited with {}", result.exit_status),
+    }
+
+    return result;
+}
+
+fn bench_interpreter(c: &mut Criterion) {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+
+    let programs = vec![
+        ("count_loop", 100000, count_loop(100000)),
+        ("recursive_fib", 20, recursive_fib(20)),
+        ("array_fill_sum", 4096, array_fill_sum(4096)),
+    ];
+
+    let mut group = c.benchmark_group("interpreter");
+
+    for (name, param, bytes) in programs {
+        let program = match Program::load(bytes) {
+            Ok(v) => v,
+            Err(e) => panic!("failed to load benchmark program: {}", e),
+        };
+
+        // note: 1 回あたりの実行命令数をスループットとして設定 (結果は命令数/秒で表示される)
+        let inst_count = launch(&program, &config).inst_count;
+        group.throughput(Throughput::Elements(inst_count as u64));
+        group.bench_with_input(BenchmarkId::new(name, param), &program, |b, program| b.iter(|| launch(program, &config)));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_interpreter);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+#![no_main]
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+
+use rustnut::runtime::*;
+#[cfg(feature = "safe-interpreter")]
+use rustnut::safe_runtime::*;
+
+use crate::common::*;
+
+// note: 先頭の 2 バイトを呼び出す関数のプールインデックスと int 引数の個数とする
+fn launch_call(data: &[u8]) {
+    let (pool_i, arg_len, bytes) = match data {
+        [pool_i, arg_len, bytes @ ..] => (*pool_i as usize, *arg_len as usize % 8, with_header(bytes)),
+        _ => return,
+    };
+
+    let args = (0..arg_len).map(|v| Value::Int(v as u32)).collect::<Vec<Value>>();
+    let config = config();
+
+    #[cfg(feature = "safe-interpreter")]
+    SafeInterpreter::launch_call(bytes.clone(), &config, pool_i, &args);
+
+    unsafe {
+        Interpreter::launch_call(bytes, &config, pool_i, &args);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    redirect_stdin();
+    launch_call(data);
+});
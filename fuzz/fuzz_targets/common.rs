@@ -0,0 +1,34 @@
+#![allow(dead_code)]
+
+use std::sync::Once;
+
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+static INIT: Once = Once::new();
+
+// note: call 0x00 で標準入力を待たないよう /dev/null に置き換える
+pub fn redirect_stdin() {
+    INIT.call_once(|| unsafe {
+        let null_fd = libc::open("/dev/null\0".as_ptr() as *const libc::c_char, libc::O_RDONLY);
+        libc::dup2(null_fd, 0);
+    });
+}
+
+// note: 大きな配列の確保でメモリを使い切らないようヒープの上限を小さくする
+pub fn config() -> InterpreterConfig {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+    config.max_heap_size = 64 * 1024;
+    return config;
+}
+
+// note: マジックナンバーとバージョンを補ってプール・命令列の解釈まで到達させる
+pub fn with_header(data: &[u8]) -> Vec<u8> {
+    let mut bytes = MAGIC_NUMBER.to_vec();
+    bytes.resize(HeaderItem::ChesVersion.get_bytecode_range().begin, 0);
+    bytes.push(CURRENT_CHES_VERSION.0 as u8);
+    bytes.extend_from_slice(data);
+    return bytes;
+}
@@ -0,0 +1,28 @@
+#![no_main]
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+
+use rustnut::runtime::*;
+#[cfg(feature = "safe-interpreter")]
+use rustnut::safe_runtime::*;
+
+use crate::common::*;
+
+fn launch(bytes: Vec<u8>) {
+    let config = config();
+
+    #[cfg(feature = "safe-interpreter")]
+    SafeInterpreter::launch(bytes.clone(), &config);
+
+    unsafe {
+        Interpreter::launch(bytes, &config);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    redirect_stdin();
+    launch(data.to_vec());
+    launch(with_header(data));
+});
@@ -0,0 +1,41 @@
+#![no_main]
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+
+use rustnut::program::*;
+use rustnut::runtime::*;
+#[cfg(feature = "safe-interpreter")]
+use rustnut::safe_runtime::*;
+
+use crate::common::*;
+
+// note: 先頭のバイトをモジュール数とし, 残りを等分してそれぞれのモジュールとする
+fn split_modules(data: &[u8]) -> Vec<Vec<u8>> {
+    let (module_len, data) = match data {
+        [module_len, data @ ..] => (*module_len as usize % 4 + 1, data),
+        _ => return Vec::new(),
+    };
+
+    let chunk_size = data.len() / module_len + 1;
+    return data.chunks(chunk_size).map(|v| with_header(v)).collect();
+}
+
+fuzz_target!(|data: &[u8]| {
+    redirect_stdin();
+
+    let program = match Program::link(split_modules(data)) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+
+    let config = config();
+
+    #[cfg(feature = "safe-interpreter")]
+    SafeInterpreter::launch_program(&program, &config, None);
+
+    unsafe {
+        Interpreter::launch_program(&program, &config, None);
+    }
+});
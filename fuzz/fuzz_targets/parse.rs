@@ -0,0 +1,37 @@
+#![no_main]
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+
+use rustnut::bytecode::*;
+
+use crate::common::*;
+
+// note: 各セクションの読み込みは範囲外のアドレスや長さに対して None を返す (パニックしない)
+fn parse(bytes: &[u8]) {
+    DebugInfo::read(bytes);
+    LinkInfo::read(bytes);
+    ConstPool::read(bytes);
+
+    for const_i in 0..16 {
+        ConstPool::read_entry(bytes, const_i);
+    }
+}
+
+// note: switch 系命令のオペランドとして先頭の 4 バイトをキーとする
+fn parse_switch(data: &[u8]) {
+    let (key, operand) = match data {
+        [a, b, c, d, operand @ ..] => (u32::from_ne_bytes([*a, *b, *c, *d]), operand),
+        _ => return,
+    };
+
+    SwitchTable::read_table(operand, key);
+    SwitchTable::read_lookup(operand, key);
+}
+
+fuzz_target!(|data: &[u8]| {
+    parse(data);
+    parse(&with_header(data));
+    parse_switch(data);
+});
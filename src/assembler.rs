@@ -0,0 +1,562 @@
+use std::mem::size_of;
+
+use crate::bytecode::*;
+use crate::runtime::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Label(usize);
+
+pub enum Operand {
+    None,
+    Byte(u8),
+    Short(u16),
+    Int(u32),
+    Long(u64),
+    Index(usize),
+    // note: 現在位置からの i16 相対オフセット (goto_w / if_w / ifnot_w では i32) として出力される
+    // note: goto / if / ifnot のオフセットが i16 に収まらない場合は i32 の命令に置き換えられる
+    Jump(Label),
+    // note: tableswitch 用 (キーが low 以上 low + ジャンプ先数未満であれば対応するジャンプ先, それ以外は default)
+    Table { default: Label, low: i32, targets: Vec<Label> },
+    // note: lookupswitch 用のキーとジャンプ先 (キーは昇順に並べ替えて出力され, 重複するとパニックする)
+    Lookup { default: Label, targets: Vec<(i32, Label)> },
+}
+
+impl Operand {
+    pub fn len(&self) -> usize {
+        return match self {
+            Operand::None => 0,
+            Operand::Byte(_) => size_of::<u8>(),
+            Operand::Short(_) => size_of::<u16>(),
+            Operand::Int(_) => size_of::<u32>(),
+            Operand::Long(_) => size_of::<u64>(),
+            Operand::Index(_) => size_of::<usize>(),
+            Operand::Jump(_) => size_of::<i16>(),
+            Operand::Table { targets, .. } => size_of::<i32>() + size_of::<i32>() + size_of::<u16>() + targets.len() * size_of::<i32>(),
+            Operand::Lookup { targets, .. } => size_of::<i32>() + size_of::<u16>() + targets.len() * SwitchTable::LOOKUP_ENTRY_SIZE,
+        };
+    }
+}
+
+pub struct Inst {
+    pub opcode: Opcode,
+    pub operand: Operand,
+}
+
+impl Inst {
+    pub fn new(opcode: Opcode, operand: Operand) -> Inst {
+        return Inst {
+            opcode: opcode,
+            operand: operand,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return Inst::encoded_len(self.opcode, &self.operand);
+    }
+
+    fn encoded_len(opcode: Opcode, operand: &Operand) -> usize {
+        return match operand {
+            Operand::Jump(_) if Inst::is_wide_jump(opcode) => size_of::<u8>() + size_of::<i32>(),
+            _ => size_of::<u8>() + operand.len(),
+        };
+    }
+
+    fn is_wide_jump(opcode: Opcode) -> bool {
+        return [Opcode::GotoW, Opcode::IfW, Opcode::IfNotW].contains(&opcode);
+    }
+
+    fn wide_jump_opcode(opcode: Opcode) -> Option<Opcode> {
+        return match opcode {
+            Opcode::Goto => Some(Opcode::GotoW),
+            Opcode::If => Some(Opcode::IfW),
+            Opcode::IfNot => Some(Opcode::IfNotW),
+            _ => None,
+        };
+    }
+}
+
+pub struct FuncDef {
+    pub var_len: u16,
+    pub arg_len: u8,
+    // note: デバッグ情報に出力する関数名
+    pub name: Option<String>,
+    // note: プール要素に出力する名前とシグネチャ
+    pub symbol: Option<FuncSymbol>,
+    insts: Vec<Inst>,
+    // note: ラベルごとの命令インデックス
+    labels: Vec<Option<usize>>,
+    // note: 命令インデックスごとのソース位置 (ファイルインデックス, 行, 列)
+    locations: Vec<(usize, u16, u32, u32)>,
+    // note: 例外ハンドラ (開始ラベル, 終了ラベル, ハンドララベル, 例外コード)
+    handlers: Vec<(Label, Label, Label, u32)>,
+}
+
+impl FuncDef {
+    pub fn new(var_len: u16, arg_len: u8) -> FuncDef {
+        return FuncDef {
+            var_len: var_len,
+            arg_len: arg_len,
+            name: None,
+            symbol: None,
+            insts: Vec::new(),
+            labels: Vec::new(),
+            locations: Vec::new(),
+            handlers: Vec::new(),
+        };
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        return Label(self.labels.len() - 1);
+    }
+
+    // note: 次にプッシュされる命令の位置にラベルを設定
+    pub fn set_label(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.insts.len());
+    }
+
+    // note: 次にプッシュされる命令以降のソース位置を設定
+    pub fn set_location(&mut self, file_i: u16, line: u32, column: u32) -> &mut FuncDef {
+        self.locations.push((self.insts.len(), file_i, line, column));
+        return self;
+    }
+
+    pub fn set_symbol(&mut self, name: &str, signature: &str) -> &mut FuncDef {
+        self.symbol = Some(FuncSymbol {
+            name: name.to_string(),
+            signature: signature.to_string(),
+        });
+
+        return self;
+    }
+
+    // note: begin から end の直前までの命令で送出された例外を handler で捕捉 (先に追加したハンドラが優先)
+    pub fn add_handler(&mut self, begin: Label, end: Label, handler: Label, code: u32) -> &mut FuncDef {
+        self.handlers.push((begin, end, handler, code));
+        return self;
+    }
+
+    pub fn push(&mut self, opcode: Opcode, operand: Operand) -> &mut FuncDef {
+        self.insts.push(Inst::new(opcode, operand));
+        return self;
+    }
+
+    pub fn len(&self) -> usize {
+        return *self.inst_offsets().last().unwrap();
+    }
+
+    fn offsets_of(&self, opcodes: &[Opcode]) -> Vec<usize> {
+        let mut offsets = Vec::<usize>::new();
+        let mut offset = 0usize;
+
+        for (each_opcode, each_inst) in opcodes.iter().zip(&self.insts) {
+            offsets.push(offset);
+            offset += Inst::encoded_len(*each_opcode, &each_inst.operand);
+        }
+
+        // note: 末尾ラベル用に関数の終端位置を追加
+        offsets.push(offset);
+        return offsets;
+    }
+
+    // note: 出力する命令 (オフセットが i16 に収まらないジャンプ命令は i32 の命令に置き換える)
+    // note: 置き換えで命令長が伸びると他のジャンプのオフセットも伸びうるため変化しなくなるまで繰り返す
+    fn encoded_opcodes(&self) -> Vec<Opcode> {
+        let mut opcodes = self.insts.iter().map(|v| v.opcode).collect::<Vec<Opcode>>();
+
+        loop {
+            let offsets = self.offsets_of(&opcodes);
+            let mut is_changed = false;
+
+            for (inst_i, each_inst) in self.insts.iter().enumerate() {
+                if let (Operand::Jump(label), Some(wide_opcode)) = (&each_inst.operand, Inst::wide_jump_opcode(opcodes[inst_i])) {
+                    let offset = offsets[self.label_inst_i(*label)] as isize - offsets[inst_i + 1] as isize;
+
+                    if offset < i16::MIN as isize || offset > i16::MAX as isize {
+                        opcodes[inst_i] = wide_opcode;
+                        is_changed = true;
+                    }
+                }
+            }
+
+            if !is_changed {
+                return opcodes;
+            }
+        }
+    }
+
+    fn inst_offsets(&self) -> Vec<usize> {
+        return self.offsets_of(&self.encoded_opcodes());
+    }
+
+    fn label_inst_i(&self, label: Label) -> usize {
+        return match self.labels[label.0] {
+            Some(v) => v,
+            None => panic!("label {} is not set", label.0),
+        };
+    }
+
+    // note: プール要素のハンドラ表 (アドレスは関数の開始アドレスを加算した値)
+    fn exception_handlers(&self, start_addr: usize) -> Vec<ExceptionHandler> {
+        let offsets = self.inst_offsets();
+
+        return self.handlers.iter().map(|(begin, end, handler, code)| ExceptionHandler {
+            begin_addr: start_addr + offsets[self.label_inst_i(*begin)],
+            end_addr: start_addr + offsets[self.label_inst_i(*end)],
+            handler_addr: start_addr + offsets[self.label_inst_i(*handler)],
+            code: *code,
+        }).collect();
+    }
+
+    // note: 指定した命令のオペランド (プールインデックスや定数インデックス) の関数内オフセット
+    fn operand_offsets(&self, opcode: Opcode) -> Vec<usize> {
+        let offsets = self.inst_offsets();
+        return self.insts.iter().enumerate().filter(|(_, v)| v.opcode == opcode).map(|(i, _)| offsets[i] + size_of::<u8>()).collect();
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let opcodes = self.encoded_opcodes();
+        let offsets = self.offsets_of(&opcodes);
+        let mut bytes = Vec::<u8>::new();
+
+        for (inst_i, each_inst) in self.insts.iter().enumerate() {
+            bytes.push(opcodes[inst_i].into());
+
+            // note: ジャンプ先は次の命令の位置からの相対オフセット
+            let relative_offset = |label: Label| -> isize {
+                return offsets[self.label_inst_i(label)] as isize - offsets[inst_i + 1] as isize;
+            };
+
+            let jump_offset = |label: Label| -> [u8; 2] {
+                let offset = relative_offset(label);
+
+                if offset < i16::MIN as isize || offset > i16::MAX as isize {
+                    panic!("jump offset {} is out of range", offset);
+                }
+
+                return (offset as i16).to_ne_bytes();
+            };
+
+            let wide_jump_offset = |label: Label| -> [u8; 4] {
+                let offset = relative_offset(label);
+
+                if offset < i32::MIN as isize || offset > i32::MAX as isize {
+                    panic!("jump offset {} is out of range", offset);
+                }
+
+                return (offset as i32).to_ne_bytes();
+            };
+
+            match &each_inst.operand {
+                Operand::None => (),
+                Operand::Byte(v) => bytes.push(*v),
+                Operand::Short(v) => bytes.extend_from_slice(&v.to_ne_bytes()),
+                Operand::Int(v) => bytes.extend_from_slice(&v.to_ne_bytes()),
+                Operand::Long(v) => bytes.extend_from_slice(&v.to_ne_bytes()),
+                Operand::Index(v) => bytes.extend_from_slice(&v.to_ne_bytes()),
+                Operand::Jump(label) if Inst::is_wide_jump(opcodes[inst_i]) => bytes.extend_from_slice(&wide_jump_offset(*label)),
+                Operand::Jump(label) => bytes.extend_from_slice(&jump_offset(*label)),
+                Operand::Table { default, low, targets } => {
+                    if targets.len() > u16::MAX as usize {
+                        panic!("{} switch targets exceed the limit of {}", targets.len(), u16::MAX);
+                    }
+
+                    bytes.extend_from_slice(&wide_jump_offset(*default));
+                    bytes.extend_from_slice(&low.to_ne_bytes());
+                    bytes.extend_from_slice(&(targets.len() as u16).to_ne_bytes());
+
+                    for each_target in targets {
+                        bytes.extend_from_slice(&wide_jump_offset(*each_target));
+                    }
+                },
+                Operand::Lookup { default, targets } => {
+                    if targets.len() > u16::MAX as usize {
+                        panic!("{} switch targets exceed the limit of {}", targets.len(), u16::MAX);
+                    }
+
+                    // note: 実行時に二分探索できるようキーの昇順に並べる
+                    let mut sorted_targets = targets.clone();
+                    sorted_targets.sort_by_key(|v| v.0);
+
+                    if let Some(v) = sorted_targets.windows(2).find(|v| v[0].0 == v[1].0) {
+                        panic!("switch key {} is duplicated", v[0].0);
+                    }
+
+                    bytes.extend_from_slice(&wide_jump_offset(*default));
+                    bytes.extend_from_slice(&(sorted_targets.len() as u16).to_ne_bytes());
+
+                    for (each_key, each_target) in sorted_targets {
+                        bytes.extend_from_slice(&each_key.to_ne_bytes());
+                        bytes.extend_from_slice(&wide_jump_offset(each_target));
+                    }
+                },
+            }
+        }
+
+        return bytes;
+    }
+}
+
+pub struct Assembler {
+    code_name: [u8; 8],
+    funcs: Vec<FuncDef>,
+    // note: デバッグ情報に出力するソースファイル名
+    files: Vec<String>,
+    // note: リンク情報に出力するシンボル名とプールインデックス
+    exports: Vec<(String, usize)>,
+    imports: Vec<(String, usize)>,
+    // note: 定数プールに出力する定数 (インデックスは追加順)
+    constants: Vec<Constant>,
+}
+
+impl Assembler {
+    pub fn new() -> Assembler {
+        return Assembler {
+            code_name: [0u8; 8],
+            funcs: Vec::new(),
+            files: Vec::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+            constants: Vec::new(),
+        };
+    }
+
+    // note: 追加したソースファイルのインデックスを返す
+    pub fn add_file(&mut self, file_name: &str) -> u16 {
+        self.files.push(file_name.to_string());
+        return (self.files.len() - 1) as u16;
+    }
+
+    pub fn set_code_name(&mut self, code_name: &str) {
+        let name_bytes = code_name.as_bytes();
+        let len = name_bytes.len().min(self.code_name.len());
+
+        self.code_name = [0u8; 8];
+        self.code_name[..len].copy_from_slice(&name_bytes[..len]);
+    }
+
+    // note: 追加した関数のプールインデックスを返す (インデックス 0 がエントリポイント)
+    pub fn add_func(&mut self, var_len: u16, arg_len: u8) -> usize {
+        self.funcs.push(FuncDef::new(var_len, arg_len));
+        return self.funcs.len() - 1;
+    }
+
+    // note: 他のモジュールがエクスポートする関数のプールインデックスを返す (リンク時に解決される)
+    pub fn add_import(&mut self, name: &str) -> usize {
+        let pool_i = self.add_func(0, 0);
+        self.imports.push((name.to_string(), pool_i));
+        return pool_i;
+    }
+
+    pub fn add_export(&mut self, pool_i: usize, name: &str) {
+        self.exports.push((name.to_string(), pool_i));
+    }
+
+    // note: 定数インデックスを返す (同じ種別と内容の定数は共有する)
+    pub fn add_constant(&mut self, constant: Constant) -> u16 {
+        if let Some(const_i) = self.constants.iter().position(|v| *v == constant) {
+            return const_i as u16;
+        }
+
+        if self.constants.len() >= MAX_CONSTANT_LEN {
+            panic!("constant count exceeds {}", MAX_CONSTANT_LEN);
+        }
+
+        self.constants.push(constant);
+        return (self.constants.len() - 1) as u16;
+    }
+
+    // note: baconst のオペランド
+    pub fn add_bytes(&mut self, value: &[u8]) -> u16 {
+        return self.add_constant(Constant::Bytes(value.to_vec()));
+    }
+
+    pub fn add_string(&mut self, value: &str) -> u16 {
+        return self.add_bytes(value.as_bytes());
+    }
+
+    // note: ldc のオペランド
+    pub fn add_int(&mut self, value: u32) -> u16 {
+        return self.add_constant(Constant::Int(value));
+    }
+
+    // note: ldc2 のオペランド
+    pub fn add_long(&mut self, value: u64) -> u16 {
+        return self.add_constant(Constant::Long(value));
+    }
+
+    pub fn add_double(&mut self, value: f64) -> u16 {
+        return self.add_long(value.to_bits());
+    }
+
+    // note: new のオペランド (getfield / setfield のオペランドは宣言順のフィールドインデックス)
+    pub fn add_struct(&mut self, fields: &[FieldType]) -> u16 {
+        return self.add_constant(Constant::Struct(fields.to_vec()));
+    }
+
+    pub fn func(&mut self, pool_i: usize) -> &mut FuncDef {
+        return &mut self.funcs[pool_i];
+    }
+
+    pub fn assemble(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; *HEADER_SIZE];
+
+        let magic_number_range = HeaderItem::MagicNumber.get_bytecode_range();
+        bytes[magic_number_range.begin..magic_number_range.begin + magic_number_range.len].copy_from_slice(MAGIC_NUMBER);
+
+        let code_name_range = HeaderItem::CodeName.get_bytecode_range();
+        bytes[code_name_range.begin..code_name_range.begin + code_name_range.len].copy_from_slice(&self.code_name);
+
+        let version_range = HeaderItem::ChesVersion.get_bytecode_range();
+        let (major, minor, patch) = *CURRENT_CHES_VERSION;
+        bytes[version_range.begin..version_range.begin + version_range.len].copy_from_slice(&[major as u8, minor as u8, patch as u8]);
+
+        // note: プール要素のアドレス表 -> プール要素 (開始アドレス, 変数長, 引数長, ハンドラ表) -> 定数プール -> デバッグ情報 -> リンク情報 -> 各関数のコード
+        // note: プール要素・定数プール・デバッグ情報・リンク情報の長さはアドレスに依存しないため仮のアドレスで出力して長さを求める
+        // note: 文字列や要素数が出力できる上限を超えた場合はパニック
+        let encode = |result: EncodeResult<Vec<u8>>| -> Vec<u8> {
+            return match result {
+                Ok(v) => v,
+                Err(e) => panic!("{}", e),
+            };
+        };
+
+        let pool_entry_sizes = self.pool_funcs(0).iter().map(|v| encode(v.encode()).len()).collect::<Vec<usize>>();
+        let entry_table_begin = bytes.len() + self.funcs.len() * size_of::<usize>();
+        let const_pool_begin = entry_table_begin + pool_entry_sizes.iter().sum::<usize>();
+        let debug_info_begin = const_pool_begin + self.const_pool().map_or(0, |v| v.encode(0).len());
+        let link_info_begin = debug_info_begin + self.debug_info(0).map_or(0, |v| encode(v.encode()).len());
+        let code_begin = link_info_begin + self.link_info(0).map_or(0, |v| encode(v.encode()).len());
+
+        let mut entry_addr = entry_table_begin;
+
+        for each_size in &pool_entry_sizes {
+            bytes.extend_from_slice(&entry_addr.to_ne_bytes());
+            entry_addr += each_size;
+        }
+
+        for each_func in self.pool_funcs(code_begin) {
+            bytes.append(&mut encode(each_func.encode()));
+        }
+
+        if let Some(const_pool) = self.const_pool() {
+            let addr_range = HeaderItem::ConstPoolAddr.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&const_pool_begin.to_ne_bytes());
+            bytes.append(&mut const_pool.encode(const_pool_begin));
+        }
+
+        if let Some(debug_info) = self.debug_info(code_begin) {
+            let addr_range = HeaderItem::DebugInfoAddr.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&debug_info_begin.to_ne_bytes());
+            bytes.append(&mut encode(debug_info.encode()));
+        }
+
+        if let Some(link_info) = self.link_info(code_begin) {
+            let addr_range = HeaderItem::LinkInfoAddr.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&link_info_begin.to_ne_bytes());
+            bytes.append(&mut encode(link_info.encode()));
+        }
+
+        for each_func in &self.funcs {
+            bytes.append(&mut each_func.encode());
+        }
+
+        // note: エントリポイントからのリターン先 (バイトコード末尾)
+        bytes.push(Opcode::Exit.into());
+        return bytes;
+    }
+
+    fn pool_funcs(&self, code_begin: usize) -> Vec<PoolFunc> {
+        let mut funcs = Vec::<PoolFunc>::new();
+        let mut start_addr = code_begin;
+
+        for (pool_i, each_func) in self.funcs.iter().enumerate() {
+            let is_imported = self.imports.iter().any(|v| v.1 == pool_i);
+
+            funcs.push(PoolFunc {
+                pool_i: pool_i,
+                // note: 未解決のインポートは呼び出せないようバイトコード外のアドレスとする
+                start_addr: if is_imported { usize::MAX } else { start_addr },
+                var_len: each_func.var_len,
+                arg_len: each_func.arg_len,
+                handlers: each_func.exception_handlers(start_addr),
+                symbol: each_func.symbol.clone(),
+            });
+
+            start_addr += each_func.len();
+        }
+
+        return funcs;
+    }
+
+    // note: 定数がなければ None
+    fn const_pool(&self) -> Option<ConstPool> {
+        if self.constants.len() == 0 {
+            return None;
+        }
+
+        let mut const_pool = ConstPool::new();
+        const_pool.constants = self.constants.clone();
+        return Some(const_pool);
+    }
+
+    // note: インポートとエクスポートのいずれもなければ None
+    fn link_info(&self, code_begin: usize) -> Option<LinkInfo> {
+        if self.imports.len() == 0 && self.exports.len() == 0 {
+            return None;
+        }
+
+        let mut link_info = LinkInfo::new();
+        link_info.code_begin = code_begin;
+        link_info.exports = self.exports.clone();
+        link_info.imports = self.imports.clone();
+
+        let mut start_addr = code_begin;
+
+        for each_func in &self.funcs {
+            for each_opcode in &[Opcode::Invoke, Opcode::TailInvoke] {
+                link_info.relocations.extend(each_func.operand_offsets(*each_opcode).iter().map(|v| start_addr + v));
+            }
+
+            for each_opcode in &[Opcode::BAConst, Opcode::Ldc, Opcode::Ldc2, Opcode::New] {
+                link_info.const_relocations.extend(each_func.operand_offsets(*each_opcode).iter().map(|v| start_addr + v));
+            }
+
+            start_addr += each_func.len();
+        }
+
+        link_info.code_end = start_addr;
+        return Some(link_info);
+    }
+
+    // note: ファイル・関数名・ソース位置のいずれも設定されていなければ None
+    fn debug_info(&self, code_begin: usize) -> Option<DebugInfo> {
+        if self.files.len() == 0 && self.funcs.iter().all(|v| v.name.is_none() && v.locations.len() == 0) {
+            return None;
+        }
+
+        let mut debug_info = DebugInfo::new();
+        debug_info.files = self.files.clone();
+        debug_info.func_names = self.funcs.iter().map(|v| v.name.clone().unwrap_or_default()).collect();
+
+        let mut start_addr = code_begin;
+
+        for each_func in &self.funcs {
+            let offsets = each_func.inst_offsets();
+
+            for (inst_i, file_i, line, column) in &each_func.locations {
+                debug_info.lines.push(LineEntry {
+                    addr: start_addr + offsets[*inst_i],
+                    file_i: *file_i,
+                    line: *line,
+                    column: *column,
+                });
+            }
+
+            start_addr += each_func.len();
+        }
+
+        return Some(debug_info);
+    }
+}
@@ -1,6 +1,12 @@
+use std::fmt::{Formatter, Display};
+use std::mem::size_of;
+
+use crate::runtime::BacktraceFrame;
+
 pub const HEADER_SIZE: &'static usize = &128;
 
-pub const CURRENT_CHES_VERSION: &'static (usize, usize, usize) = &(1, 0, 0);
+// spec: 2.0.0 からプール要素にハンドラ表と種別を持つ (1.x のバイトコードとは互換性がない)
+pub const CURRENT_CHES_VERSION: &'static (usize, usize, usize) = &(2, 0, 0);
 pub const MAGIC_NUMBER: &'static [u8; 8] = &[0x43u8, 0x48u8, 0x45u8, 0x53u8, 0x43u8, 0x43u8, 0x42u8, 0x43u8];
 
 pub struct BytecodeRange {
@@ -47,6 +53,10 @@ impl Bytecode {
         return self.bytes;
     }
 
+    pub fn as_slice(&self) -> &[u8] {
+        return &self.bytes;
+    }
+
     pub fn bytes_to_string(bytes: &Vec<u8>) -> String {
         return bytes.iter().map(|v| {
             let s = format!("{:0x}", v);
@@ -74,6 +84,12 @@ pub enum HeaderItem {
     MagicNumber,
     CodeName,
     ChesVersion,
+    // note: デバッグ情報セクションのアドレス (0 であればデバッグ情報なし)
+    DebugInfoAddr,
+    // note: リンク情報セクションのアドレス (0 であればリンク情報なし)
+    LinkInfoAddr,
+    // note: 定数プールセクションのアドレス (0 であれば定数なし)
+    ConstPoolAddr,
 }
 
 impl HeaderItem {
@@ -82,8 +98,794 @@ impl HeaderItem {
             HeaderItem::MagicNumber => (0, 8),
             HeaderItem::CodeName => (8, 8),
             HeaderItem::ChesVersion => (16, 3),
+            HeaderItem::DebugInfoAddr => (24, 8),
+            HeaderItem::LinkInfoAddr => (32, 8),
+            HeaderItem::ConstPoolAddr => (40, 8),
         };
 
         return BytecodeRange::new(begin, len);
     }
 }
+
+// spec: 文字列長や要素数を u16 で出力するセクションでは 0xffff を超える長さを出力できない
+pub const MAX_ENCODED_LEN: usize = u16::MAX as usize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncodeError {
+    // note: 上限を超えた文字列のバイト長
+    StringTooLong(usize),
+    // note: 上限を超えた要素数
+    TooManyEntries(usize),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            EncodeError::StringTooLong(len) => write!(f, "string of {} bytes exceeds the limit of {}", len, MAX_ENCODED_LEN),
+            EncodeError::TooManyEntries(len) => write!(f, "{} entries exceed the limit of {}", len, MAX_ENCODED_LEN),
+        };
+    }
+}
+
+pub type EncodeResult<T> = Result<T, EncodeError>;
+
+// note: 任意の例外コードを捕捉するハンドラの例外コード
+pub const CATCH_ALL_CODE: u32 = u32::MAX;
+
+#[derive(Clone)]
+pub struct ExceptionHandler {
+    // note: 捕捉する範囲 (終了アドレスを含まない)
+    pub begin_addr: usize,
+    pub end_addr: usize,
+    pub handler_addr: usize,
+    pub code: u32,
+}
+
+impl ExceptionHandler {
+    pub fn is_matched(&self, pc: usize, code: u32) -> bool {
+        return self.begin_addr <= pc && pc < self.end_addr && (self.code == code || self.code == CATCH_ALL_CODE);
+    }
+}
+
+// note: プール要素の種別
+pub const POOL_ENTRY_PLAIN: u8 = 0x00;
+pub const POOL_ENTRY_NAMED: u8 = 0x01;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuncSymbol {
+    pub name: String,
+    // note: 書式はコンパイラに委ねる (例: 引数と戻り値の型を並べた `(ii)i`)
+    pub signature: String,
+}
+
+// spec: プール要素 = 開始アドレス (usize), 変数長 (u16), 引数長 (u8), ハンドラ数 (u16)
+// spec:   -> [開始アドレス (usize), 終了アドレス (usize), ハンドラアドレス (usize), 例外コード (u32)] * ハンドラ数 (優先順)
+// spec:   -> 種別 (u8; 0x00 = 名前なし, 0x01 = 名前付き)
+// spec:   -> 名前付きの場合は [名前長 (u16), UTF-8 の名前, シグネチャ長 (u16), UTF-8 のシグネチャ]
+// spec: 未知の種別の要素は名前なしとして扱う
+#[derive(Clone)]
+pub struct PoolFunc {
+    pub pool_i: usize,
+    pub start_addr: usize,
+    pub var_len: u16,
+    pub arg_len: u8,
+    pub handlers: Vec<ExceptionHandler>,
+    pub symbol: Option<FuncSymbol>,
+}
+
+impl PoolFunc {
+    // note: プール要素のバイト列 (プールインデックスは含まない)
+    pub fn encode(&self) -> EncodeResult<Vec<u8>> {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(&self.start_addr.to_ne_bytes());
+        bytes.extend_from_slice(&self.var_len.to_ne_bytes());
+        bytes.push(self.arg_len);
+        Bytecode::write_len(&mut bytes, self.handlers.len())?;
+
+        for each_handler in &self.handlers {
+            bytes.extend_from_slice(&each_handler.begin_addr.to_ne_bytes());
+            bytes.extend_from_slice(&each_handler.end_addr.to_ne_bytes());
+            bytes.extend_from_slice(&each_handler.handler_addr.to_ne_bytes());
+            bytes.extend_from_slice(&each_handler.code.to_ne_bytes());
+        }
+
+        match &self.symbol {
+            Some(symbol) => {
+                bytes.push(POOL_ENTRY_NAMED);
+
+                for each_string in &[&symbol.name, &symbol.signature] {
+                    Bytecode::write_string(&mut bytes, each_string)?;
+                }
+            },
+            None => bytes.push(POOL_ENTRY_PLAIN),
+        }
+
+        return Ok(bytes);
+    }
+
+    // note: デバッグ情報の関数名を優先し, なければプール要素の名前
+    pub fn name<'a>(&'a self, debug_info: Option<&'a DebugInfo>) -> Option<&'a str> {
+        return debug_info.and_then(|v| v.func_name(self.pool_i)).or_else(|| self.symbol.as_ref().map(|v| v.name.as_str()));
+    }
+}
+
+impl Bytecode {
+    pub(crate) fn read_usize(bytes: &[u8], index: usize) -> Option<usize> {
+        let mut buf = [0u8; size_of::<usize>()];
+        buf.copy_from_slice(bytes.get(index..index.checked_add(size_of::<usize>())?)?);
+        return Some(usize::from_ne_bytes(buf));
+    }
+
+    // note: index から読み込んで index を読み込んだバイト数だけ進める (範囲外の場合は None)
+    pub(crate) fn read_bytes<'a>(bytes: &'a [u8], index: &mut usize, len: usize) -> Option<&'a [u8]> {
+        let value = bytes.get(*index..index.checked_add(len)?)?;
+        *index += len;
+        return Some(value);
+    }
+
+    pub(crate) fn read_u16(bytes: &[u8], index: &mut usize) -> Option<u16> {
+        let value = Bytecode::read_bytes(bytes, index, size_of::<u16>())?;
+        return Some(u16::from_ne_bytes([value[0], value[1]]));
+    }
+
+    pub(crate) fn read_u32(bytes: &[u8], index: &mut usize) -> Option<u32> {
+        let value = Bytecode::read_bytes(bytes, index, size_of::<u32>())?;
+        return Some(u32::from_ne_bytes([value[0], value[1], value[2], value[3]]));
+    }
+
+    // spec: セクションの形式はメジャーバージョンごとに異なるため, メジャーバージョンが異なるバイトコードは読み込まない
+    pub fn is_supported_version(bytes: &[u8]) -> bool {
+        let version_range = HeaderItem::ChesVersion.get_bytecode_range();
+        return bytes.get(version_range.begin) == Some(&(CURRENT_CHES_VERSION.0 as u8));
+    }
+
+    // note: 要素数を u16 で出力
+    pub(crate) fn write_len(bytes: &mut Vec<u8>, len: usize) -> EncodeResult<()> {
+        if len > MAX_ENCODED_LEN {
+            return Err(EncodeError::TooManyEntries(len));
+        }
+
+        bytes.extend_from_slice(&(len as u16).to_ne_bytes());
+        return Ok(());
+    }
+
+    // note: バイト長 (u16) と UTF-8 の文字列を出力 (切り詰めると文字の途中で切れうるため上限を超えればエラー)
+    pub(crate) fn write_string(bytes: &mut Vec<u8>, value: &str) -> EncodeResult<()> {
+        if value.len() > MAX_ENCODED_LEN {
+            return Err(EncodeError::StringTooLong(value.len()));
+        }
+
+        bytes.extend_from_slice(&(value.len() as u16).to_ne_bytes());
+        bytes.extend_from_slice(value.as_bytes());
+        return Ok(());
+    }
+
+    // note: プール要素のアドレス表は最初のプール要素の手前で終わるものとして関数を列挙
+    pub fn read_pool_funcs(bytes: &[u8]) -> Vec<PoolFunc> {
+        let mut funcs = Vec::<PoolFunc>::new();
+        let mut table_end = bytes.len();
+        let mut pool_i = 0usize;
+
+        while *HEADER_SIZE + (pool_i + 1) * size_of::<usize>() <= table_end {
+            let entry_addr = match Bytecode::read_usize(bytes, *HEADER_SIZE + pool_i * size_of::<usize>()) {
+                Some(v) => v,
+                None => break,
+            };
+
+            if entry_addr < table_end {
+                table_end = entry_addr;
+            }
+
+            let start_addr = Bytecode::read_usize(bytes, entry_addr);
+            let var_len = entry_addr.checked_add(size_of::<usize>()).and_then(|i| bytes.get(i..i.checked_add(size_of::<u16>())?));
+            let arg_len = entry_addr.checked_add(size_of::<usize>() + size_of::<u16>()).and_then(|i| bytes.get(i));
+
+            if let (Some(start_addr), Some(var_len), Some(arg_len)) = (start_addr, var_len, arg_len) {
+                let mut index = entry_addr + size_of::<usize>() + size_of::<u16>() + size_of::<u8>();
+                let (handlers, symbol) = match Bytecode::read_handlers(bytes, &mut index) {
+                    Ok(v) => (v, Bytecode::read_symbol(bytes, &mut index)),
+                    Err(v) => (v, None),
+                };
+
+                funcs.push(PoolFunc {
+                    pool_i: pool_i,
+                    start_addr: start_addr,
+                    var_len: u16::from_ne_bytes([var_len[0], var_len[1]]),
+                    arg_len: *arg_len,
+                    handlers: handlers,
+                    symbol: symbol,
+                });
+            }
+
+            pool_i += 1;
+        }
+
+        return funcs;
+    }
+
+    // note: ハンドラ表が不正な場合は読み込めたハンドラまでを Err で返す
+    fn read_handlers(bytes: &[u8], index: &mut usize) -> Result<Vec<ExceptionHandler>, Vec<ExceptionHandler>> {
+        let mut handlers = Vec::<ExceptionHandler>::new();
+
+        let count = match Bytecode::read_u16(bytes, index) {
+            Some(v) => v,
+            None => return Err(handlers),
+        };
+
+        for _ in 0..count {
+            let addrs = (Bytecode::read_usize(bytes, *index), Bytecode::read_usize(bytes, *index + size_of::<usize>()), Bytecode::read_usize(bytes, *index + size_of::<usize>() * 2));
+            *index += size_of::<usize>() * 3;
+
+            match (addrs, Bytecode::read_u32(bytes, index)) {
+                ((Some(begin_addr), Some(end_addr), Some(handler_addr)), Some(code)) => handlers.push(ExceptionHandler {
+                    begin_addr: begin_addr,
+                    end_addr: end_addr,
+                    handler_addr: handler_addr,
+                    code: code,
+                }),
+                _ => return Err(handlers),
+            }
+        }
+
+        return Ok(handlers);
+    }
+
+    // note: 名前付きでない場合や名前が不正な場合は None
+    fn read_symbol(bytes: &[u8], index: &mut usize) -> Option<FuncSymbol> {
+        if Bytecode::read_bytes(bytes, index, 1)?[0] != POOL_ENTRY_NAMED {
+            return None;
+        }
+
+        let mut read_string = || -> Option<String> {
+            let len = Bytecode::read_u16(bytes, index)? as usize;
+            return String::from_utf8(Bytecode::read_bytes(bytes, index, len)?.to_vec()).ok();
+        };
+
+        let name = read_string()?;
+        let signature = read_string()?;
+
+        return Some(FuncSymbol {
+            name: name,
+            signature: signature,
+        });
+    }
+
+    // note: 開始アドレスが pc 以前で最も近い関数
+    pub fn find_pool_func(funcs: &[PoolFunc], pc: usize) -> Option<&PoolFunc> {
+        return funcs.iter().filter(|v| v.start_addr <= pc).max_by_key(|v| v.start_addr);
+    }
+
+    // note: プール要素の名前で関数を検索 (同名の場合はプールインデックスが最小のもの)
+    pub fn find_pool_func_by_name<'a>(funcs: &'a Vec<PoolFunc>, name: &str) -> Option<&'a PoolFunc> {
+        return funcs.iter().find(|v| v.symbol.as_ref().map_or(false, |v| v.name == name));
+    }
+}
+
+pub struct LineEntry {
+    pub addr: usize,
+    pub file_i: u16,
+    pub line: u32,
+    pub column: u32,
+}
+
+pub struct SourceLocation<'a> {
+    pub file: &'a str,
+    pub line: u32,
+    pub column: u32,
+}
+
+// spec: デバッグ情報セクション (値はすべてネイティブエンディアン)
+// spec: ファイル数 (u16) -> [名前長 (u16), UTF-8 の名前] * ファイル数
+// spec: 関数名数 (u16) -> [名前長 (u16), UTF-8 の名前] * 関数名数 (プールインデックス順)
+// spec: 行情報数 (u32) -> [命令アドレス (usize), ファイルインデックス (u16), 行 (u32), 列 (u32)] * 行情報数 (アドレス昇順)
+pub struct DebugInfo {
+    pub files: Vec<String>,
+    pub func_names: Vec<String>,
+    pub lines: Vec<LineEntry>,
+}
+
+impl DebugInfo {
+    pub fn new() -> DebugInfo {
+        return DebugInfo {
+            files: Vec::new(),
+            func_names: Vec::new(),
+            lines: Vec::new(),
+        };
+    }
+
+    fn read_strings(bytes: &[u8], index: &mut usize) -> Option<Vec<String>> {
+        let count = Bytecode::read_u16(bytes, index)?;
+        let mut strings = Vec::<String>::new();
+
+        for _ in 0..count {
+            let len = Bytecode::read_u16(bytes, index)? as usize;
+            strings.push(String::from_utf8_lossy(Bytecode::read_bytes(bytes, index, len)?).to_string());
+        }
+
+        return Some(strings);
+    }
+
+    // note: デバッグ情報がない場合や不正な場合は None
+    pub fn read(bytes: &[u8]) -> Option<DebugInfo> {
+        let addr_range = HeaderItem::DebugInfoAddr.get_bytecode_range();
+        let mut index = Bytecode::read_usize(bytes, addr_range.begin)?;
+
+        if index == 0 {
+            return None;
+        }
+
+        let files = DebugInfo::read_strings(bytes, &mut index)?;
+        let func_names = DebugInfo::read_strings(bytes, &mut index)?;
+        let line_count = Bytecode::read_u32(bytes, &mut index)?;
+        let mut lines = Vec::<LineEntry>::new();
+
+        for _ in 0..line_count {
+            let addr = Bytecode::read_usize(bytes, index)?;
+            index += size_of::<usize>();
+
+            lines.push(LineEntry {
+                addr: addr,
+                file_i: Bytecode::read_u16(bytes, &mut index)?,
+                line: Bytecode::read_u32(bytes, &mut index)?,
+                column: Bytecode::read_u32(bytes, &mut index)?,
+            });
+        }
+
+        return Some(DebugInfo {
+            files: files,
+            func_names: func_names,
+            lines: lines,
+        });
+    }
+
+    pub fn encode(&self) -> EncodeResult<Vec<u8>> {
+        let mut bytes = Vec::<u8>::new();
+
+        for each_strings in &[&self.files, &self.func_names] {
+            Bytecode::write_len(&mut bytes, each_strings.len())?;
+
+            for each_string in each_strings.iter() {
+                Bytecode::write_string(&mut bytes, each_string)?;
+            }
+        }
+
+        bytes.extend_from_slice(&(self.lines.len() as u32).to_ne_bytes());
+
+        for each_line in &self.lines {
+            bytes.extend_from_slice(&each_line.addr.to_ne_bytes());
+            bytes.extend_from_slice(&each_line.file_i.to_ne_bytes());
+            bytes.extend_from_slice(&each_line.line.to_ne_bytes());
+            bytes.extend_from_slice(&each_line.column.to_ne_bytes());
+        }
+
+        return Ok(bytes);
+    }
+
+    // note: 命令アドレスが pc 以前で最も近い行情報
+    pub fn location(&self, pc: usize) -> Option<SourceLocation<'_>> {
+        let line_i = self.lines.partition_point(|v| v.addr <= pc).checked_sub(1)?;
+        let line = &self.lines[line_i];
+
+        return Some(SourceLocation {
+            file: self.files.get(line.file_i as usize)?,
+            line: line.line,
+            column: line.column,
+        });
+    }
+
+    pub fn func_name(&self, pool_i: usize) -> Option<&str> {
+        return self.func_names.get(pool_i).map(|v| v.as_str()).filter(|v| v.len() != 0);
+    }
+
+    // note: バイトコードから関数とデバッグ情報を読み込んで命令位置の文字列を得る
+    pub fn describe_bytecode_pc(bytes: &[u8], pc: usize) -> String {
+        return DebugInfo::describe_pc(DebugInfo::read(bytes).as_ref(), &Bytecode::read_pool_funcs(bytes), pc);
+    }
+
+    // note: 関数名とソース位置が得られる場合は付加した命令位置の文字列
+    pub fn describe_pc(debug_info: Option<&DebugInfo>, funcs: &Vec<PoolFunc>, pc: usize) -> String {
+        return BacktraceFrame::new(funcs, debug_info, pc).to_string();
+    }
+}
+
+// spec: リンク情報セクション (値はすべてネイティブエンディアン)
+// spec: コード開始アドレス (usize) -> コード終了アドレス (usize)
+// spec: エクスポート数 (u16) -> [名前長 (u16), UTF-8 の名前, プールインデックス (usize)] * エクスポート数
+// spec: インポート数 (u16) -> [名前長 (u16), UTF-8 の名前, プールインデックス (usize)] * インポート数
+// spec: 再配置数 (u32) -> [invoke 命令のオペランドのアドレス (usize)] * 再配置数
+// spec: 定数の再配置数 (u32) -> [定数インデックス (u16) を持つ命令のオペランドのアドレス (usize)] * 定数の再配置数
+pub struct LinkInfo {
+    // note: 各関数のコードを含む範囲 (終了アドレスを含まない)
+    pub code_begin: usize,
+    pub code_end: usize,
+    pub exports: Vec<(String, usize)>,
+    // note: 他のモジュールのエクスポートで置き換えるプールインデックス
+    pub imports: Vec<(String, usize)>,
+    // note: プールインデックスを書き換える位置
+    pub relocations: Vec<usize>,
+    // note: 定数インデックスを書き換える位置
+    pub const_relocations: Vec<usize>,
+}
+
+impl LinkInfo {
+    pub fn new() -> LinkInfo {
+        return LinkInfo {
+            code_begin: 0,
+            code_end: 0,
+            exports: Vec::new(),
+            imports: Vec::new(),
+            relocations: Vec::new(),
+            const_relocations: Vec::new(),
+        };
+    }
+
+    fn read_symbols(bytes: &[u8], index: &mut usize) -> Option<Vec<(String, usize)>> {
+        let count = Bytecode::read_u16(bytes, index)?;
+        let mut symbols = Vec::<(String, usize)>::new();
+
+        for _ in 0..count {
+            let len = Bytecode::read_u16(bytes, index)? as usize;
+            let name = String::from_utf8_lossy(Bytecode::read_bytes(bytes, index, len)?).to_string();
+            let pool_i = Bytecode::read_usize(bytes, *index)?;
+            *index += size_of::<usize>();
+            symbols.push((name, pool_i));
+        }
+
+        return Some(symbols);
+    }
+
+    // note: リンク情報がない場合や不正な場合は None
+    pub fn read(bytes: &[u8]) -> Option<LinkInfo> {
+        let addr_range = HeaderItem::LinkInfoAddr.get_bytecode_range();
+        let mut index = Bytecode::read_usize(bytes, addr_range.begin)?;
+
+        if index == 0 {
+            return None;
+        }
+
+        let code_begin = Bytecode::read_usize(bytes, index)?;
+        let code_end = Bytecode::read_usize(bytes, index.checked_add(size_of::<usize>())?)?;
+        index += size_of::<usize>() * 2;
+
+        let exports = LinkInfo::read_symbols(bytes, &mut index)?;
+        let imports = LinkInfo::read_symbols(bytes, &mut index)?;
+        let relocations = LinkInfo::read_addrs(bytes, &mut index)?;
+        let const_relocations = LinkInfo::read_addrs(bytes, &mut index)?;
+
+        return Some(LinkInfo {
+            code_begin: code_begin,
+            code_end: code_end,
+            exports: exports,
+            imports: imports,
+            relocations: relocations,
+            const_relocations: const_relocations,
+        });
+    }
+
+    fn read_addrs(bytes: &[u8], index: &mut usize) -> Option<Vec<usize>> {
+        let count = Bytecode::read_u32(bytes, index)?;
+        let mut addrs = Vec::<usize>::new();
+
+        for _ in 0..count {
+            addrs.push(Bytecode::read_usize(bytes, *index)?);
+            *index += size_of::<usize>();
+        }
+
+        return Some(addrs);
+    }
+
+    pub fn encode(&self) -> EncodeResult<Vec<u8>> {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(&self.code_begin.to_ne_bytes());
+        bytes.extend_from_slice(&self.code_end.to_ne_bytes());
+
+        for each_symbols in &[&self.exports, &self.imports] {
+            Bytecode::write_len(&mut bytes, each_symbols.len())?;
+
+            for (name, pool_i) in each_symbols.iter() {
+                Bytecode::write_string(&mut bytes, name)?;
+                bytes.extend_from_slice(&pool_i.to_ne_bytes());
+            }
+        }
+
+        for each_addrs in &[&self.relocations, &self.const_relocations] {
+            bytes.extend_from_slice(&(each_addrs.len() as u32).to_ne_bytes());
+
+            for each_addr in each_addrs.iter() {
+                bytes.extend_from_slice(&each_addr.to_ne_bytes());
+            }
+        }
+
+        return Ok(bytes);
+    }
+
+    pub fn is_in_code(&self, addr: usize) -> bool {
+        return self.code_begin <= addr && addr <= self.code_end;
+    }
+
+    pub fn import_name(&self, pool_i: usize) -> Option<&str> {
+        return self.imports.iter().find(|v| v.1 == pool_i).map(|v| v.0.as_str());
+    }
+}
+
+// spec: 定数インデックスは u16 のオペランドで指定するため 0xffff は常に範囲外となる
+pub const MAX_CONSTANT_LEN: usize = u16::MAX as usize;
+
+// spec: mapush のオペランド (u16) = 次元数 (下位 8 ビット) | 最内の配列の要素のバイトサイズ (上位 8 ビット)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrayShape {
+    pub dims: u8,
+    // note: 1 / 2 / 4 / 8 バイト (最内以外の次元は参照配列となる)
+    pub elem_size: u8,
+}
+
+impl ArrayShape {
+    pub fn new(dims: u8, elem_size: u8) -> ArrayShape {
+        return ArrayShape {
+            dims: dims,
+            elem_size: elem_size,
+        };
+    }
+
+    pub fn from_operand(v: u16) -> ArrayShape {
+        return ArrayShape::new(v as u8, (v >> 8) as u8);
+    }
+
+    pub fn to_operand(&self) -> u16 {
+        return self.dims as u16 | (self.elem_size as u16) << 8;
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return self.dims != 0 && [1, 2, 4, 8].contains(&self.elem_size);
+    }
+}
+
+// spec: tableswitch のオペランド = デフォルトのオフセット (i32) | 最小のキー (i32) | オフセット数 (u16) | オフセット (i32) * オフセット数
+// spec: lookupswitch のオペランド = デフォルトのオフセット (i32) | キー数 (u16) | (キー (i32) | オフセット (i32)) * キー数
+// spec: lookupswitch のキーは重複なく昇順に並べる (実行時には検証せず, 並んでいない場合にどのオフセットが選ばれるかは未規定)
+// note: いずれのオフセットも命令の終端からの相対位置で, ポップした int 値を符号付きのキーとして扱う
+pub struct SwitchTable;
+
+impl SwitchTable {
+    pub const LOOKUP_ENTRY_SIZE: usize = size_of::<u32>() + size_of::<i32>();
+
+    // note: オペランドのバイト長とキーに対応するオフセットを返す (オペランドが途中で終わる場合は None)
+    pub fn read_table(operand: &[u8], key: u32) -> Option<(usize, i32)> {
+        let mut index = 0usize;
+        let default_offset = Bytecode::read_u32(operand, &mut index)? as i32;
+        let low = Bytecode::read_u32(operand, &mut index)? as i32;
+        let offset_len = Bytecode::read_u16(operand, &mut index)? as usize;
+        let operand_len = index + offset_len * size_of::<i32>();
+
+        if operand.len() < operand_len {
+            return None;
+        }
+
+        let offset_i = key as i32 as i64 - low as i64;
+
+        if offset_i < 0 || offset_i >= offset_len as i64 {
+            return Some((operand_len, default_offset));
+        }
+
+        index += offset_i as usize * size_of::<i32>();
+        return Some((operand_len, Bytecode::read_u32(operand, &mut index)? as i32));
+    }
+
+    // note: キーを二分探索するため実行時間はキー数の対数となる
+    pub fn read_lookup(operand: &[u8], key: u32) -> Option<(usize, i32)> {
+        let mut index = 0usize;
+        let default_offset = Bytecode::read_u32(operand, &mut index)? as i32;
+        let key_len = Bytecode::read_u16(operand, &mut index)? as usize;
+        let entries_begin = index;
+        let operand_len = entries_begin + key_len * SwitchTable::LOOKUP_ENTRY_SIZE;
+
+        if operand.len() < operand_len {
+            return None;
+        }
+
+        let (mut low, mut high) = (0usize, key_len);
+
+        while low < high {
+            let mid = (low + high) / 2;
+            let mut entry_i = entries_begin + mid * SwitchTable::LOOKUP_ENTRY_SIZE;
+            let entry_key = Bytecode::read_u32(operand, &mut entry_i)? as i32;
+
+            if entry_key == key as i32 {
+                return Some((operand_len, Bytecode::read_u32(operand, &mut entry_i)? as i32));
+            } else if entry_key < key as i32 {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        return Some((operand_len, default_offset));
+    }
+}
+
+// note: 定数の種別
+pub const CONSTANT_BYTES: u8 = 0x00;
+pub const CONSTANT_INT: u8 = 0x01;
+pub const CONSTANT_LONG: u8 = 0x02;
+pub const CONSTANT_STRUCT: u8 = 0x03;
+
+// spec: 構造体のフィールドの型 (int = 4 バイト, long = 8 バイト, ref = 配列もしくはオブジェクトへの参照)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldType {
+    Int,
+    Long,
+    Ref,
+}
+
+impl FieldType {
+    pub fn from_byte(v: u8) -> Option<FieldType> {
+        return match v {
+            0x00 => Some(FieldType::Int),
+            0x01 => Some(FieldType::Long),
+            0x02 => Some(FieldType::Ref),
+            _ => None,
+        };
+    }
+
+    pub fn to_byte(&self) -> u8 {
+        return match self {
+            FieldType::Int => 0x00,
+            FieldType::Long => 0x01,
+            FieldType::Ref => 0x02,
+        };
+    }
+
+    pub fn size(&self) -> usize {
+        return match self {
+            FieldType::Int => size_of::<u32>(),
+            FieldType::Long => size_of::<u64>(),
+            FieldType::Ref => size_of::<usize>(),
+        };
+    }
+
+    // note: フィールドはパディングなしで宣言順に配置する
+    pub fn offset(fields: &[FieldType], field_i: usize) -> usize {
+        return fields[..field_i].iter().map(|v| v.size()).sum();
+    }
+}
+
+// note: 構造体の定数から求めた各フィールドの型とデータ領域内のオフセット (インタプリタは定数インデックスごとに一度だけ求める)
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructLayout {
+    pub fields: Vec<FieldType>,
+    pub offsets: Vec<usize>,
+    // note: データ領域のバイトサイズ
+    pub size: usize,
+}
+
+impl StructLayout {
+    pub fn new(fields: Vec<FieldType>) -> StructLayout {
+        return StructLayout {
+            offsets: (0..fields.len()).map(|i| FieldType::offset(&fields, i)).collect(),
+            size: FieldType::offset(&fields, fields.len()),
+            fields: fields,
+        };
+    }
+
+    // note: フィールドインデックスが範囲外であれば None
+    pub fn field(&self, field_i: usize) -> Option<(FieldType, usize)> {
+        return Some((*self.fields.get(field_i)?, self.offsets[field_i]));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    // note: baconst でバイト配列として複製される文字列などのバイト列
+    Bytes(Vec<u8>),
+    // note: ldc でプッシュされる値
+    Int(u32),
+    // note: ldc2 でプッシュされる値 (f64 はビット表現を格納する)
+    Long(u64),
+    // note: new で確保されるオブジェクトのフィールドの型
+    Struct(Vec<FieldType>),
+}
+
+impl Constant {
+    // note: 種別と長さが一致しなければ None
+    pub fn from_entry(kind: u8, value: &[u8]) -> Option<Constant> {
+        return match kind {
+            CONSTANT_BYTES => Some(Constant::Bytes(value.to_vec())),
+            CONSTANT_INT if value.len() == size_of::<u32>() => Some(Constant::Int(u32::from_ne_bytes([value[0], value[1], value[2], value[3]]))),
+            CONSTANT_LONG if value.len() == size_of::<u64>() => {
+                let mut buf = [0u8; size_of::<u64>()];
+                buf.copy_from_slice(value);
+                Some(Constant::Long(u64::from_ne_bytes(buf)))
+            },
+            CONSTANT_STRUCT => value.iter().map(|v| FieldType::from_byte(*v)).collect::<Option<Vec<FieldType>>>().map(Constant::Struct),
+            _ => None,
+        };
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, value) = match self {
+            Constant::Bytes(v) => (CONSTANT_BYTES, v.clone()),
+            Constant::Int(v) => (CONSTANT_INT, v.to_ne_bytes().to_vec()),
+            Constant::Long(v) => (CONSTANT_LONG, v.to_ne_bytes().to_vec()),
+            Constant::Struct(v) => (CONSTANT_STRUCT, v.iter().map(|v| v.to_byte()).collect()),
+        };
+
+        let mut bytes = vec![kind];
+        bytes.extend_from_slice(&value.len().to_ne_bytes());
+        bytes.extend_from_slice(&value);
+        return bytes;
+    }
+}
+
+// spec: 定数プールセクション (値はすべてネイティブエンディアン)
+// spec: 定数数 (usize) -> [定数のアドレス (usize)] * 定数数
+// spec: 定数 = 種別 (u8; 0x00 = バイト列, 0x01 = int, 0x02 = long, 0x03 = 構造体) -> 長さ (usize) -> 内容
+// spec: 構造体の内容 = [フィールドの型 (u8; 0x00 = int, 0x01 = long, 0x02 = ref)] * フィールド数
+pub struct ConstPool {
+    pub constants: Vec<Constant>,
+}
+
+impl ConstPool {
+    pub fn new() -> ConstPool {
+        return ConstPool {
+            constants: Vec::new(),
+        };
+    }
+
+    // note: 定数インデックスの種別と内容を定数プール全体を読み込まずに得る (不正な場合は None)
+    pub fn read_entry(bytes: &[u8], const_i: usize) -> Option<(u8, &[u8])> {
+        let addr_range = HeaderItem::ConstPoolAddr.get_bytecode_range();
+        let pool_addr = Bytecode::read_usize(bytes, addr_range.begin)?;
+
+        if pool_addr == 0 || const_i >= Bytecode::read_usize(bytes, pool_addr)? {
+            return None;
+        }
+
+        let mut index = Bytecode::read_usize(bytes, const_i.checked_mul(size_of::<usize>())?.checked_add(pool_addr)?.checked_add(size_of::<usize>())?)?;
+        let kind = Bytecode::read_bytes(bytes, &mut index, 1)?[0];
+        let len = Bytecode::read_usize(bytes, index)?;
+        index = index.checked_add(size_of::<usize>())?;
+
+        return Some((kind, Bytecode::read_bytes(bytes, &mut index, len)?));
+    }
+
+    // note: 定数プールがない場合や不正な場合は None
+    pub fn read(bytes: &[u8]) -> Option<ConstPool> {
+        let addr_range = HeaderItem::ConstPoolAddr.get_bytecode_range();
+        let pool_addr = Bytecode::read_usize(bytes, addr_range.begin)?;
+
+        if pool_addr == 0 {
+            return None;
+        }
+
+        let mut constants = Vec::<Constant>::new();
+
+        for const_i in 0..Bytecode::read_usize(bytes, pool_addr)? {
+            let (kind, value) = ConstPool::read_entry(bytes, const_i)?;
+            constants.push(Constant::from_entry(kind, value)?);
+        }
+
+        return Some(ConstPool {
+            constants: constants,
+        });
+    }
+
+    // note: 定数のアドレス表は定数プールの開始アドレスからの位置で出力
+    pub fn encode(&self, pool_addr: usize) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(&self.constants.len().to_ne_bytes());
+
+        let encoded = self.constants.iter().map(|v| v.encode()).collect::<Vec<Vec<u8>>>();
+        let mut entry_addr = pool_addr + size_of::<usize>() * (self.constants.len() + 1);
+
+        for each_encoded in &encoded {
+            bytes.extend_from_slice(&entry_addr.to_ne_bytes());
+            entry_addr += each_encoded.len();
+        }
+
+        for mut each_encoded in encoded {
+            bytes.append(&mut each_encoded);
+        }
+
+        return bytes;
+    }
+}
@@ -0,0 +1,460 @@
+use std::io::{BufRead, Write};
+
+use crate::bytecode::*;
+use crate::debugger::*;
+use crate::program::*;
+use crate::runtime::*;
+
+use serde_json::{json, Value};
+
+// note: 仮想マシンは単一スレッドのみ
+const THREAD_ID: u64 = 1;
+
+// spec: Debug Adapter Protocol (Content-Length ヘッダ付きの JSON メッセージ) を入出力で処理する
+pub struct DapServer<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    seq: u64,
+    control: StepControl,
+    func_breakpoints: Vec<usize>,
+    inst_breakpoints: Vec<usize>,
+    // note: ソースファイルのパスと行に対応する命令アドレス
+    source_breakpoints: Vec<(String, usize)>,
+    is_first_inst: bool,
+    is_disconnected: bool,
+}
+
+impl<R: BufRead, W: Write> DapServer<R, W> {
+    pub fn new(input: R, output: W) -> DapServer<R, W> {
+        return DapServer {
+            input: input,
+            output: output,
+            seq: 0,
+            control: StepControl::new(StepMode::Continue),
+            func_breakpoints: Vec::new(),
+            inst_breakpoints: Vec::new(),
+            source_breakpoints: Vec::new(),
+            is_first_inst: true,
+            is_disconnected: false,
+        };
+    }
+
+    fn read_message(&mut self) -> Option<Value> {
+        let mut content_len = None;
+
+        loop {
+            let mut line = String::new();
+
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => (),
+            }
+
+            let line = line.trim();
+
+            if line.len() == 0 {
+                break;
+            }
+
+            if let Some(len) = line.strip_prefix("Content-Length:") {
+                content_len = len.trim().parse::<usize>().ok();
+            }
+        }
+
+        let mut content = vec![0u8; content_len?];
+
+        if self.input.read_exact(&mut content).is_err() {
+            return None;
+        }
+
+        return serde_json::from_slice(&content).ok();
+    }
+
+    fn write_message(&mut self, mut message: Value) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let content = message.to_string();
+        let _ = write!(self.output, "Content-Length: {}\r\n\r\n{}", content.len(), content);
+        let _ = self.output.flush();
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) {
+        self.write_message(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    fn respond_error(&mut self, request: &Value, message: &str) {
+        self.write_message(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) {
+        self.write_message(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    // note: 関数・命令単位のブレークポイントを合わせて停止判定に反映
+    fn update_breakpoints(&mut self) {
+        let mut breakpoints = Vec::<Breakpoint>::new();
+        breakpoints.extend(self.func_breakpoints.iter().map(|v| Breakpoint::Func(*v)));
+        breakpoints.extend(self.inst_breakpoints.iter().map(|v| Breakpoint::Offset(*v)));
+        breakpoints.extend(self.source_breakpoints.iter().map(|(_, addr)| Breakpoint::Offset(*addr)));
+        self.control.breakpoints = breakpoints;
+    }
+
+    fn parse_num(s: &str) -> Option<usize> {
+        let s = s.trim();
+
+        return if s.starts_with("0x") {
+            usize::from_str_radix(&s[2..], 16).ok()
+        } else {
+            s.parse::<usize>().ok()
+        };
+    }
+
+    fn set_func_breakpoints(&mut self, request: &Value) {
+        let names = request["arguments"]["breakpoints"].as_array().cloned().unwrap_or_default();
+        let mut results = Vec::<Value>::new();
+        self.func_breakpoints.clear();
+
+        // note: 関数名は `func 1` もしくはプールインデックスのみ, または関数の名前で指定
+        for each_name in names.iter().map(|v| v["name"].as_str().unwrap_or("")) {
+            let func = match DapServer::<R, W>::parse_num(each_name.trim_start_matches("func")) {
+                Some(pool_i) => self.control.funcs().iter().find(|f| f.pool_i == pool_i),
+                None => self.control.find_func_by_name(each_name.trim()),
+            };
+
+            results.push(match func {
+                Some(func) => json!({ "verified": true, "instructionReference": format!("0x{:x}", func.start_addr) }),
+                None => json!({ "verified": false, "message": format!("unknown function `{}`", each_name) }),
+            });
+
+            if let Some(pool_i) = func.map(|v| v.pool_i) {
+                self.func_breakpoints.push(pool_i);
+            }
+        }
+
+        self.update_breakpoints();
+        self.respond(request, json!({ "breakpoints": results }));
+    }
+
+    fn set_inst_breakpoints(&mut self, request: &Value) {
+        let breakpoints = request["arguments"]["breakpoints"].as_array().cloned().unwrap_or_default();
+        let mut results = Vec::<Value>::new();
+        self.inst_breakpoints.clear();
+
+        for each_breakpoint in &breakpoints {
+            let reference = each_breakpoint["instructionReference"].as_str().unwrap_or("");
+            let offset = each_breakpoint["offset"].as_i64().unwrap_or(0);
+            let addr = DapServer::<R, W>::parse_num(reference).and_then(|v| (v as i64).checked_add(offset)).filter(|v| *v >= 0);
+
+            results.push(match addr {
+                Some(addr) => {
+                    self.inst_breakpoints.push(addr as usize);
+                    json!({ "verified": true, "instructionReference": format!("0x{:x}", addr) })
+                },
+                None => json!({ "verified": false, "message": format!("invalid instruction reference `{}`", reference) }),
+            });
+        }
+
+        self.update_breakpoints();
+        self.respond(request, json!({ "breakpoints": results }));
+    }
+
+    // note: デバッグ情報のファイル名はソースのパス末尾と一致すれば同一とみなす
+    fn match_source_path(path: &str, file: &str) -> bool {
+        return path == file || path.ends_with(&format!("/{}", file)) || path.ends_with(&format!("\\{}", file));
+    }
+
+    // note: 指定行を含む最初の行情報の命令アドレス
+    fn find_line_addr(&self, path: &str, line: u32) -> Option<usize> {
+        let debug_info = self.control.debug_info()?;
+
+        return debug_info.lines.iter().find(|v| {
+            v.line == line && debug_info.files.get(v.file_i as usize).map_or(false, |file| DapServer::<R, W>::match_source_path(path, file))
+        }).map(|v| v.addr);
+    }
+
+    fn set_source_breakpoints(&mut self, request: &Value) {
+        let path = request["arguments"]["source"]["path"].as_str().unwrap_or("").to_string();
+        let breakpoints = request["arguments"]["breakpoints"].as_array().cloned().unwrap_or_default();
+        let mut results = Vec::<Value>::new();
+        self.source_breakpoints.retain(|(each_path, _)| *each_path != path);
+
+        for each_breakpoint in &breakpoints {
+            let line = each_breakpoint["line"].as_u64().unwrap_or(0) as u32;
+
+            let addr = if self.control.is_loaded() {
+                self.find_line_addr(&path, line)
+            } else {
+                None
+            };
+
+            results.push(match addr {
+                Some(addr) => {
+                    self.source_breakpoints.push((path.clone(), addr));
+                    json!({ "verified": true, "line": line, "instructionReference": format!("0x{:x}", addr) })
+                },
+                None => json!({ "verified": false, "line": line, "message": "no debug info for this line" }),
+            });
+        }
+
+        self.update_breakpoints();
+        self.respond(request, json!({ "breakpoints": results }));
+    }
+
+    // note: 実行前と実行後の双方で受け付ける要求 (処理した場合は true)
+    fn handle_common_request(&mut self, request: &Value) -> bool {
+        match request["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                self.respond(request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsFunctionBreakpoints": true,
+                    "supportsInstructionBreakpoints": true,
+                }));
+            },
+            "setBreakpoints" => self.set_source_breakpoints(request),
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            "threads" => self.respond(request, json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "disconnect" | "terminate" => {
+                self.respond(request, json!({}));
+                self.is_disconnected = true;
+            },
+            _ => return false,
+        }
+
+        return true;
+    }
+
+    // note: launch と configurationDone を受け取るまで要求を処理し, 読み込んだプログラムを返す
+    // note: load は launch で指定されたパスのプログラムを読み込み, 失敗した場合は launch へのエラー応答に含めるメッセージを返す
+    pub fn wait_for_launch<F: FnMut(&str) -> Result<Program, String>>(&mut self, mut load: F) -> Option<Program> {
+        let mut program = None;
+        let mut is_configured = false;
+
+        while !self.is_disconnected {
+            if let (Some(_), true) = (&program, is_configured) {
+                return program;
+            }
+
+            let request = self.read_message()?;
+
+            if self.handle_common_request(&request) {
+                continue;
+            }
+
+            match request["command"].as_str().unwrap_or("") {
+                "launch" => {
+                    let path = request["arguments"]["program"].as_str().unwrap_or("").to_string();
+
+                    match load(&path) {
+                        Ok(v) => {
+                            self.control.load_funcs(v.bytes());
+
+                            if request["arguments"]["stopOnEntry"].as_bool().unwrap_or(false) {
+                                self.control.mode = StepMode::Step;
+                            }
+
+                            program = Some(v);
+                            self.respond(&request, json!({}));
+                            // note: 関数一覧を読み込んだ後にブレークポイントの設定を受け付ける
+                            self.send_event("initialized", json!({}));
+                        },
+                        Err(e) => self.respond_error(&request, &format!("failed to load program `{}`: {}", path, e)),
+                    }
+                },
+                "configurationDone" => {
+                    is_configured = true;
+                    self.respond(&request, json!({}));
+                },
+                "setFunctionBreakpoints" if program.is_some() => self.set_func_breakpoints(&request),
+                "setFunctionBreakpoints" => self.respond_error(&request, "program is not launched"),
+                "setInstructionBreakpoints" => self.set_inst_breakpoints(&request),
+                _ => self.respond_error(&request, "unsupported request"),
+            }
+        }
+
+        return None;
+    }
+
+    // note: 終了を通知して切断されるまで要求を処理
+    pub fn finish(&mut self, result: &RunResult) {
+        if self.is_disconnected {
+            return;
+        }
+
+        if result.output.len() != 0 {
+            self.send_event("output", json!({ "category": "stdout", "output": String::from_utf8_lossy(&result.output) }));
+        }
+
+        self.send_event("exited", json!({ "exitCode": result.exit_status as u32 }));
+        self.send_event("terminated", json!({}));
+
+        while !self.is_disconnected {
+            let request = match self.read_message() {
+                Some(v) => v,
+                None => return,
+            };
+
+            if !self.handle_common_request(&request) {
+                self.respond_error(&request, "program has exited");
+            }
+        }
+    }
+
+    fn stack_trace(&mut self, request: &Value, state: &InterpreterState) {
+        let debug_info = self.control.debug_info();
+
+        let frames = state.frames().iter().enumerate().map(|(i, each_frame)| {
+            let name = match Bytecode::find_pool_func(self.control.funcs(), each_frame.pc) {
+                Some(func) => match func.name(debug_info) {
+                    Some(name) => name.to_string(),
+                    None => format!("func {}", func.pool_i),
+                },
+                None => "<unknown>".to_string(),
+            };
+
+            let mut frame = json!({
+                "id": i,
+                "name": name,
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format!("0x{:x}", each_frame.pc),
+            });
+
+            if let Some(location) = debug_info.and_then(|v| v.location(each_frame.pc)) {
+                frame["source"] = json!({ "name": location.file, "path": location.file });
+                frame["line"] = json!(location.line);
+                frame["column"] = json!(location.column);
+            }
+
+            frame
+        }).collect::<Vec<Value>>();
+
+        let total = frames.len();
+        self.respond(request, json!({ "stackFrames": frames, "totalFrames": total }));
+    }
+
+    fn scopes(&mut self, request: &Value) {
+        // note: 変数参照番号 0 は子を持たないことを表すためフレーム番号に 1 を加える
+        let frame_id = request["arguments"]["frameId"].as_u64().unwrap_or(0);
+        self.respond(request, json!({ "scopes": [{ "name": "Locals", "variablesReference": frame_id + 1, "expensive": false }] }));
+    }
+
+    fn variables(&mut self, request: &Value, state: &InterpreterState) {
+        let frame_i = (request["arguments"]["variablesReference"].as_u64().unwrap_or(0) as usize).wrapping_sub(1);
+        let frames = state.frames();
+
+        let frame = match frames.get(frame_i) {
+            Some(v) => v,
+            None => return self.respond_error(request, "invalid variables reference"),
+        };
+
+        // note: エントリポイントは変数領域を確保しない
+        let var_len = match Bytecode::find_pool_func(self.control.funcs(), frame.pc) {
+            Some(func) if frame_i < state.call_depth => func.var_len as usize,
+            _ => 0,
+        };
+
+        let vars = (0..var_len).map(|var_i| {
+            let value = match state.frame_local(frame.bp, var_i) {
+                Some(v) => format!("{}", v),
+                None => "<unavailable>".to_string(),
+            };
+
+            json!({ "name": format!("var {}", var_i), "value": value, "type": "u32", "variablesReference": 0 })
+        }).collect::<Vec<Value>>();
+
+        self.respond(request, json!({ "variables": vars }));
+    }
+
+    // note: 停止中に受け付ける要求 (実行を再開する場合は動作を返す)
+    fn handle_stopped_request(&mut self, request: &Value, state: &InterpreterState) -> Option<HookAction> {
+        if self.handle_common_request(request) {
+            return if self.is_disconnected { Some(HookAction::Abort) } else { None };
+        }
+
+        match request["command"].as_str().unwrap_or("") {
+            "stackTrace" => self.stack_trace(request, state),
+            "scopes" => self.scopes(request),
+            "variables" => self.variables(request, state),
+            "setFunctionBreakpoints" => self.set_func_breakpoints(request),
+            "setInstructionBreakpoints" => self.set_inst_breakpoints(request),
+            "continue" => {
+                self.control.mode = StepMode::Continue;
+                self.respond(request, json!({ "allThreadsContinued": true }));
+                return Some(HookAction::Continue);
+            },
+            "next" => {
+                self.control.step_over(state);
+                self.respond(request, json!({}));
+                return Some(HookAction::Continue);
+            },
+            "stepIn" => {
+                self.control.mode = StepMode::Step;
+                self.respond(request, json!({}));
+                return Some(HookAction::Continue);
+            },
+            "stepOut" => {
+                self.control.step_out(state);
+                self.respond(request, json!({}));
+                return Some(HookAction::Continue);
+            },
+            _ => self.respond_error(request, "unsupported request"),
+        }
+
+        return None;
+    }
+}
+
+impl<R: BufRead, W: Write> InterpreterHook for DapServer<R, W> {
+    fn on_inst(&mut self, state: &InterpreterState) -> HookAction {
+        self.control.load_funcs(state.bytecode);
+
+        let is_first_inst = self.is_first_inst;
+        self.is_first_inst = false;
+
+        if !self.control.should_stop(state) {
+            return HookAction::Continue;
+        }
+
+        let reason = if self.control.is_at_breakpoint(state) {
+            "breakpoint"
+        } else if is_first_inst {
+            "entry"
+        } else {
+            "step"
+        };
+
+        self.send_event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }));
+
+        loop {
+            // note: 入力が終了した場合は実行を中断
+            let request = match self.read_message() {
+                Some(v) => v,
+                None => {
+                    self.is_disconnected = true;
+                    return HookAction::Abort;
+                },
+            };
+
+            if let Some(action) = self.handle_stopped_request(&request, state) {
+                return action;
+            }
+        }
+    }
+}
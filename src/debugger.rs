@@ -0,0 +1,336 @@
+use std::io::{BufRead, Write};
+use std::mem::size_of;
+
+use crate::bytecode::*;
+use crate::runtime::*;
+
+pub enum Breakpoint {
+    // note: バイトコード上のオフセット
+    Offset(usize),
+    // note: 関数のプールインデックス (関数の開始位置で停止)
+    Func(usize),
+}
+
+pub(crate) enum StepMode {
+    Continue,
+    Step,
+    // note: 呼び出しの深さが指定値以下になるまで実行
+    StepOver(usize),
+    // note: 呼び出しの深さが指定値未満になるまで実行
+    StepOut(usize),
+}
+
+enum Command {
+    Step,
+    Next,
+    Finish,
+    Continue,
+    Break(Breakpoint),
+    // note: 関数名によるブレークポイント (関数の読み込み後に解決)
+    BreakName(String),
+    Delete(usize),
+    Breakpoints,
+    Regs,
+    Locals,
+    Backtrace,
+    Stack,
+    Help,
+    Quit,
+}
+
+impl Command {
+    fn parse_num(s: &str) -> Option<usize> {
+        return if s.starts_with("0x") {
+            usize::from_str_radix(&s[2..], 16).ok()
+        } else {
+            s.parse::<usize>().ok()
+        };
+    }
+
+    fn parse(line: &str) -> Result<Command, String> {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+
+        let cmd = match tokens.as_slice() {
+            ["s"] | ["step"] => Command::Step,
+            ["n"] | ["next"] => Command::Next,
+            ["f"] | ["finish"] => Command::Finish,
+            ["c"] | ["continue"] => Command::Continue,
+            ["b", "func", index] | ["break", "func", index] => match Command::parse_num(index) {
+                Some(v) => Command::Break(Breakpoint::Func(v)),
+                None => Command::BreakName(index.to_string()),
+            },
+            ["b", offset] | ["break", offset] => match Command::parse_num(offset) {
+                Some(v) => Command::Break(Breakpoint::Offset(v)),
+                None => return Err(format!("invalid offset `{}`", offset)),
+            },
+            ["d", index] | ["delete", index] => match Command::parse_num(index) {
+                Some(v) => Command::Delete(v),
+                None => return Err(format!("invalid breakpoint number `{}`", index)),
+            },
+            ["breakpoints"] => Command::Breakpoints,
+            ["r"] | ["regs"] => Command::Regs,
+            ["l"] | ["locals"] => Command::Locals,
+            ["bt"] | ["backtrace"] => Command::Backtrace,
+            ["stack"] => Command::Stack,
+            ["h"] | ["help"] => Command::Help,
+            ["q"] | ["quit"] => Command::Quit,
+            _ => return Err(format!("unknown command `{}`", line.trim())),
+        };
+
+        return Ok(cmd);
+    }
+}
+
+// note: ブレークポイントとステップ実行による停止判定 (DAP サーバと共有)
+pub(crate) struct StepControl {
+    pub(crate) breakpoints: Vec<Breakpoint>,
+    pub(crate) mode: StepMode,
+    // note: 初回の命令でバイトコードから読み込む
+    funcs: Option<Vec<PoolFunc>>,
+    debug_info: Option<DebugInfo>,
+}
+
+impl StepControl {
+    pub(crate) fn new(mode: StepMode) -> StepControl {
+        return StepControl {
+            breakpoints: Vec::new(),
+            mode: mode,
+            funcs: None,
+            debug_info: None,
+        };
+    }
+
+    pub(crate) fn load_funcs(&mut self, bytecode: &[u8]) {
+        if self.funcs.is_none() {
+            self.funcs = Some(Bytecode::read_pool_funcs(bytecode));
+            self.debug_info = DebugInfo::read(bytecode);
+        }
+    }
+
+    pub(crate) fn is_loaded(&self) -> bool {
+        return self.funcs.is_some();
+    }
+
+    pub(crate) fn funcs(&self) -> &Vec<PoolFunc> {
+        return self.funcs.as_ref().unwrap();
+    }
+
+    pub(crate) fn debug_info(&self) -> Option<&DebugInfo> {
+        return self.debug_info.as_ref();
+    }
+
+    // note: デバッグ情報もしくはプール要素の名前で関数を検索
+    pub(crate) fn find_func_by_name(&self, name: &str) -> Option<&PoolFunc> {
+        return self.funcs().iter().find(|v| v.name(self.debug_info()) == Some(name));
+    }
+
+    pub(crate) fn is_at_breakpoint(&self, state: &InterpreterState) -> bool {
+        let funcs = self.funcs();
+
+        return self.breakpoints.iter().any(|v| match v {
+            Breakpoint::Offset(offset) => *offset == state.pc,
+            Breakpoint::Func(pool_i) => funcs.iter().any(|f| f.pool_i == *pool_i && f.start_addr == state.pc),
+        });
+    }
+
+    pub(crate) fn should_stop(&self, state: &InterpreterState) -> bool {
+        if self.is_at_breakpoint(state) {
+            return true;
+        }
+
+        return match self.mode {
+            StepMode::Continue => false,
+            StepMode::Step => true,
+            StepMode::StepOver(depth) => state.call_depth <= depth,
+            StepMode::StepOut(depth) => state.call_depth < depth,
+        };
+    }
+
+    pub(crate) fn step_over(&mut self, state: &InterpreterState) {
+        self.mode = StepMode::StepOver(state.call_depth);
+    }
+
+    pub(crate) fn step_out(&mut self, state: &InterpreterState) {
+        self.mode = if state.call_depth == 0 { StepMode::Continue } else { StepMode::StepOut(state.call_depth) };
+    }
+
+    pub(crate) fn location_to_string(&self, pc: usize) -> String {
+        return DebugInfo::describe_pc(self.debug_info(), self.funcs(), pc);
+    }
+}
+
+pub struct Debugger<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    control: StepControl,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(input: R, output: W) -> Debugger<R, W> {
+        return Debugger {
+            input: input,
+            output: output,
+            // note: 最初の命令で停止してブレークポイントを設定できるようにする
+            control: StepControl::new(StepMode::Step),
+        };
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.control.breakpoints.push(breakpoint);
+    }
+
+    fn print_locals(&mut self, state: &InterpreterState) {
+        let var_len = match Bytecode::find_pool_func(self.control.funcs(), state.pc) {
+            // note: エントリポイントは変数領域を確保しないためオペランドスタックを表示
+            Some(func) if state.call_depth != 0 => func.var_len as usize,
+            _ => (state.sp - state.bp.min(state.sp)).saturating_sub(size_of::<usize>() * 2) / size_of::<u32>(),
+        };
+
+        if var_len == 0 {
+            let _ = writeln!(self.output, "<no locals>");
+        }
+
+        for var_i in 0..var_len {
+            let _ = match state.local(var_i) {
+                Some(v) => writeln!(self.output, "var {} = 0x{:x} ({})", var_i, v, v),
+                None => writeln!(self.output, "var {} = <unavailable>", var_i),
+            };
+        }
+    }
+
+    fn print_backtrace(&mut self, state: &InterpreterState) {
+        for (i, each_frame) in state.frames().iter().enumerate() {
+            let location = self.control.location_to_string(each_frame.pc);
+            let _ = writeln!(self.output, "#{} {} bp 0x{:x}", i, location, each_frame.bp);
+        }
+    }
+
+    fn print_help(&mut self) {
+        let _ = writeln!(self.output, "step (s)              execute one instruction");
+        let _ = writeln!(self.output, "next (n)              execute one instruction stepping over invoke");
+        let _ = writeln!(self.output, "finish (f)            run until the current function returns");
+        let _ = writeln!(self.output, "continue (c)          run until a breakpoint");
+        let _ = writeln!(self.output, "break (b) <offset>    set a breakpoint at a bytecode offset");
+        let _ = writeln!(self.output, "break (b) func <i>    set a breakpoint at a function pool index or name");
+        let _ = writeln!(self.output, "delete (d) <n>        delete a breakpoint");
+        let _ = writeln!(self.output, "breakpoints           list breakpoints");
+        let _ = writeln!(self.output, "regs (r)              print pc, sp, bp and call depth");
+        let _ = writeln!(self.output, "locals (l)            print local variables");
+        let _ = writeln!(self.output, "backtrace (bt)        print the call stack");
+        let _ = writeln!(self.output, "stack                 print the raw stack");
+        let _ = writeln!(self.output, "quit (q)              abort the program");
+    }
+
+    fn run_command(&mut self, cmd: Command, state: &InterpreterState) -> Option<HookAction> {
+        match cmd {
+            Command::Step => self.control.mode = StepMode::Step,
+            Command::Next => self.control.step_over(state),
+            Command::Finish => self.control.step_out(state),
+            Command::Continue => self.control.mode = StepMode::Continue,
+            Command::Break(breakpoint) => {
+                let _ = match &breakpoint {
+                    Breakpoint::Offset(offset) => writeln!(self.output, "breakpoint {} at 0x{:x}", self.control.breakpoints.len(), offset),
+                    Breakpoint::Func(pool_i) => writeln!(self.output, "breakpoint {} at func {}", self.control.breakpoints.len(), pool_i),
+                };
+
+                self.control.breakpoints.push(breakpoint);
+                return None;
+            },
+            Command::BreakName(name) => {
+                match self.control.find_func_by_name(&name).map(|v| v.pool_i) {
+                    Some(pool_i) => {
+                        let _ = writeln!(self.output, "breakpoint {} at func {} ({})", self.control.breakpoints.len(), pool_i, name);
+                        self.control.breakpoints.push(Breakpoint::Func(pool_i));
+                    },
+                    None => {
+                        let _ = writeln!(self.output, "unknown function `{}`", name);
+                    },
+                }
+
+                return None;
+            },
+            Command::Delete(index) => {
+                if index < self.control.breakpoints.len() {
+                    self.control.breakpoints.remove(index);
+                } else {
+                    let _ = writeln!(self.output, "no breakpoint {}", index);
+                }
+
+                return None;
+            },
+            Command::Breakpoints => {
+                for (i, each_breakpoint) in self.control.breakpoints.iter().enumerate() {
+                    let _ = match each_breakpoint {
+                        Breakpoint::Offset(offset) => writeln!(self.output, "{}: 0x{:x}", i, offset),
+                        Breakpoint::Func(pool_i) => writeln!(self.output, "{}: func {}", i, pool_i),
+                    };
+                }
+
+                return None;
+            },
+            Command::Regs => {
+                let _ = writeln!(self.output, "pc 0x{:x} / sp 0x{:x} / bp 0x{:x} / depth {}", state.pc, state.sp, state.bp, state.call_depth);
+                return None;
+            },
+            Command::Locals => {
+                self.print_locals(state);
+                return None;
+            },
+            Command::Backtrace => {
+                self.print_backtrace(state);
+                return None;
+            },
+            Command::Stack => {
+                let stack_str = state.stack.iter().map(|v| format!("{:02x}", v)).collect::<Vec<String>>().join(" ");
+                let _ = writeln!(self.output, "{}", if stack_str.len() == 0 { "<empty>".to_string() } else { stack_str });
+                return None;
+            },
+            Command::Help => {
+                self.print_help();
+                return None;
+            },
+            Command::Quit => return Some(HookAction::Abort),
+        }
+
+        return Some(HookAction::Continue);
+    }
+}
+
+impl<R: BufRead, W: Write> InterpreterHook for Debugger<R, W> {
+    fn on_inst(&mut self, state: &InterpreterState) -> HookAction {
+        self.control.load_funcs(state.bytecode);
+
+        if !self.control.should_stop(state) {
+            return HookAction::Continue;
+        }
+
+        let location = self.control.location_to_string(state.pc);
+        let _ = writeln!(self.output, "stopped at {} {}", location, state.opcode);
+
+        loop {
+            let _ = write!(self.output, "(rustnut) ");
+            let _ = self.output.flush();
+
+            let mut line = String::new();
+
+            // note: 入力が終了した場合は実行を中断
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return HookAction::Abort,
+                Ok(_) => (),
+            }
+
+            if line.trim().len() == 0 {
+                continue;
+            }
+
+            match Command::parse(&line) {
+                Ok(cmd) => if let Some(action) = self.run_command(cmd, state) {
+                    return action;
+                },
+                Err(e) => {
+                    let _ = writeln!(self.output, "{}", e);
+                },
+            }
+        }
+    }
+}
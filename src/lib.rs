@@ -1,25 +1,248 @@
+// note: テストやベンチマークでバイトコードを生成するための補助モジュール
+// note: 不正な入力に対してはエラーを返さずにパニックするため公開 API としては扱わない
+#[doc(hidden)]
+pub mod assembler;
 pub mod bytecode;
+pub mod dap;
+pub mod debugger;
+pub mod linker;
+pub mod program;
 pub mod runtime;
+#[cfg(feature = "safe-interpreter")]
+pub mod safe_runtime;
+pub mod text;
 
+use std::fmt::{Formatter, Display};
+use std::fs;
+use std::io::Read;
+
+use crate::dap::*;
+use crate::debugger::*;
+use crate::program::*;
 use crate::runtime::*;
+#[cfg(feature = "safe-interpreter")]
+use crate::safe_runtime::*;
 
-use rustnutlib::file::*;
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallError {
+    // note: バイトコードが読み込まれていない
+    NotLoaded,
+    UnknownFunction(String),
+    InvalidPoolIndex(usize),
+    // note: 関数の引数長と渡された引数の要素数
+    ArgumentMismatch { pool_i: usize, arg_len: usize, given_len: usize },
+    // note: ret に到達せずに終了した場合の終了ステータスと詳細
+    Exited { status: ExitStatus, fault: Option<Fault> },
+}
 
-pub struct ChesVM {}
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            CallError::NotLoaded => write!(f, "bytecode is not loaded"),
+            CallError::UnknownFunction(name) => write!(f, "function `{}` is not found", name),
+            CallError::InvalidPoolIndex(pool_i) => write!(f, "pool index {} is out of range", pool_i),
+            CallError::ArgumentMismatch { pool_i, arg_len, given_len } => write!(f, "func {} takes {} argument slots but {} were given", pool_i, arg_len, given_len),
+            CallError::Exited { status, fault: Some(fault) } => write!(f, "exited with {}: {}", status, fault),
+            CallError::Exited { status, fault: None } => write!(f, "exited with {}", status),
+        };
+    }
+}
+
+pub type CallResult<T> = Result<T, CallError>;
+
+pub struct ChesVM {
+    config: InterpreterConfig,
+    // note: call で呼び出すために読み込んだプログラム
+    program: Option<Program>,
+}
 
 impl ChesVM {
+    // note: CLI から実行する場合の設定 (命令ごとのトレースを出力する)
+    // note: 組み込んで使用する場合は with_config で設定を指定する
     pub fn new() -> ChesVM {
-        return ChesVM {};
+        let mut config = InterpreterConfig::new();
+        config.is_traced = true;
+
+        return ChesVM {
+            config: config,
+            program: None,
+        };
+    }
+
+    pub fn with_config(config: InterpreterConfig) -> ChesVM {
+        return ChesVM {
+            config: config,
+            program: None,
+        };
+    }
+
+    fn read_files(chesc_file_paths: &[&str]) -> std::io::Result<Vec<Vec<u8>>> {
+        let mut modules = Vec::<Vec<u8>>::new();
+
+        for each_path in chesc_file_paths {
+            match fs::read(each_path) {
+                Ok(v) => modules.push(v),
+                Err(e) => return Err(e),
+            }
+        }
+
+        return Ok(modules);
     }
 
-    pub fn run(&self, chesc_file_path: &str) -> FileResult<ExitStatus> {
-        let file_bytes = match FileMan::read_all_bytes(chesc_file_path) {
+    pub fn run(&self, chesc_file_path: &str) -> std::io::Result<LoadResult<ExitStatus>> {
+        return self.run_modules(&[chesc_file_path]);
+    }
+
+    // note: 先頭のモジュールのエントリポイントから実行
+    // note: 読み込みやリンクに失敗した場合は実行せずに LoadError を返す (終了ステータスは LoadError::exit_status で得られる)
+    pub fn run_modules(&self, chesc_file_paths: &[&str]) -> std::io::Result<LoadResult<ExitStatus>> {
+        return Ok(self.run_module_bytes(ChesVM::read_files(chesc_file_paths)?));
+    }
+
+    // note: メモリ上のバイトコードを実行
+    pub fn run_bytes(&self, bytes: &[u8]) -> LoadResult<ExitStatus> {
+        return self.run_module_bytes(vec![bytes.to_vec()]);
+    }
+
+    // note: 読み込み元の末尾までをバイトコードとして実行
+    pub fn run_reader<R: Read>(&self, mut reader: R) -> std::io::Result<LoadResult<ExitStatus>> {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes)?;
+        return Ok(self.run_module_bytes(vec![bytes]));
+    }
+
+    // note: メモリ上の複数のモジュールをリンクして実行
+    pub fn run_module_bytes(&self, modules: Vec<Vec<u8>>) -> LoadResult<ExitStatus> {
+        let program = Program::link(modules)?;
+        return Ok(self.run_program(&program));
+    }
+
+    // note: 読み込み済みのプログラムをエントリポイントから実行 (実行ごとに新しいインタプリタを使用)
+    pub fn run_program(&self, program: &Program) -> ExitStatus {
+        let result = self.launch_program(program, None);
+
+        // note: トレース無効時は異常終了時の詳細とバックトレースのみを出力
+        if !self.config.is_traced && result.exit_status != ExitStatus::Success {
+            eprintln!("exit status 0x{:0x} ({})", result.exit_status as u32, result.exit_status);
+
+            if let Some(fault) = &result.fault {
+                eprintln!("{}", fault);
+            }
+
+            for (i, each_frame) in result.backtrace.iter().enumerate() {
+                eprintln!("#{} {}", i, each_frame);
+            }
+        }
+
+        return result.exit_status;
+    }
+
+    // note: call で呼び出すモジュールをリンクして保持 (失敗した場合は保持しているプログラムを変更しない)
+    pub fn load(&mut self, chesc_file_paths: &[&str]) -> std::io::Result<LoadResult<()>> {
+        return Ok(self.load_module_bytes(ChesVM::read_files(chesc_file_paths)?));
+    }
+
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> LoadResult<()> {
+        return self.load_module_bytes(vec![bytes.to_vec()]);
+    }
+
+    pub fn load_module_bytes(&mut self, modules: Vec<Vec<u8>>) -> LoadResult<()> {
+        self.program = Some(Program::link(modules)?);
+        return Ok(());
+    }
+
+    pub fn program(&self) -> Option<&Program> {
+        return self.program.as_ref();
+    }
+
+    // note: 読み込んだプログラムの関数を呼び出して戻り値を得る (exit で終了した場合は void)
+    pub fn call<'a, T: Into<FuncRef<'a>>>(&self, func: T, args: &[Value]) -> CallResult<Value> {
+        return match &self.program {
+            Some(v) => self.call_program(v, func, args),
+            None => Err(CallError::NotLoaded),
+        };
+    }
+
+    pub fn call_program<'a, T: Into<FuncRef<'a>>>(&self, program: &Program, func: T, args: &[Value]) -> CallResult<Value> {
+        let func = func.into();
+
+        let pool_func = match (program.find_func(func), func) {
+            (Some(v), _) => v,
+            (None, FuncRef::Name(name)) => return Err(CallError::UnknownFunction(name.to_string())),
+            (None, FuncRef::Index(pool_i)) => return Err(CallError::InvalidPoolIndex(pool_i)),
+        };
+
+        let given_len = args.iter().map(|v| v.slot_len()).sum::<usize>();
+
+        if given_len != pool_func.arg_len as usize {
+            return Err(CallError::ArgumentMismatch { pool_i: pool_func.pool_i, arg_len: pool_func.arg_len as usize, given_len: given_len });
+        }
+
+        let result = self.launch_call(program, pool_func.pool_i, args);
+
+        return match result.exit_status {
+            ExitStatus::Success => Ok(result.ret_value.unwrap_or(Value::Void)),
+            status => Err(CallError::Exited { status: status, fault: result.fault }),
+        };
+    }
+
+    // note: 標準入出力で操作するデバッガ上で実行 (読み込みやリンクに失敗した場合は run と同様に LoadError を返す)
+    pub fn debug(&self, chesc_file_path: &str) -> std::io::Result<LoadResult<ExitStatus>> {
+        let program = match Program::link(ChesVM::read_files(&[chesc_file_path])?) {
             Ok(v) => v,
-            Err(e) => return Err(e),
+            Err(e) => return Ok(Err(e)),
         };
 
+        let stdin = std::io::stdin();
+        let mut debugger = Debugger::new(stdin.lock(), std::io::stdout());
+        return Ok(Ok(self.launch_program(&program, Some(&mut debugger)).exit_status));
+    }
+
+    // note: 標準入出力で Debug Adapter Protocol を処理して実行 (切断された場合は None)
+    pub fn serve_debug_adapter(&self) -> Option<ExitStatus> {
+        let stdin = std::io::stdin();
+        let mut server = DapServer::new(stdin.lock(), std::io::stdout());
+
+        // note: 読み込みやリンクに失敗した場合は launch へのエラー応答で通知して次の launch を待つ
+        let program = server.wait_for_launch(|path| match fs::read(path) {
+            Ok(v) => Program::load(v).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        })?;
+
+        // note: 標準出力はプロトコルに使用するためトレースを無効化して出力をキャプチャする
+        // note: 標準入力もプロトコルに使用するため call 0x00 による入力は受け付けられない
+        let dap_vm = ChesVM::with_config(InterpreterConfig {
+            is_traced: false,
+            is_output_captured: true,
+            max_heap_size: self.config.max_heap_size,
+        });
+
+        let result = dap_vm.launch_program(&program, Some(&mut server));
+        server.finish(&result);
+        return Some(result.exit_status);
+    }
+
+    #[cfg(feature = "safe-interpreter")]
+    fn launch_program(&self, program: &Program, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        return SafeInterpreter::launch_program(program, &self.config, hook);
+    }
+
+    #[cfg(not(feature = "safe-interpreter"))]
+    fn launch_program(&self, program: &Program, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        unsafe {
+            return Interpreter::launch_program(program, &self.config, hook);
+        }
+    }
+
+    #[cfg(feature = "safe-interpreter")]
+    fn launch_call(&self, program: &Program, pool_i: usize, args: &[Value]) -> RunResult {
+        return SafeInterpreter::call_program(program, &self.config, pool_i, args);
+    }
+
+    #[cfg(not(feature = "safe-interpreter"))]
+    fn launch_call(&self, program: &Program, pool_i: usize, args: &[Value]) -> RunResult {
         unsafe {
-            return Ok(Interpreter::launch(file_bytes));
+            return Interpreter::call_program(program, &self.config, pool_i, args);
         }
     }
 }
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::fmt::{Formatter, Display};
+use std::mem::size_of;
+
+use crate::bytecode::*;
+use crate::runtime::Opcode;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkError {
+    // note: ヘッダやリンク情報が不正なモジュールのインデックス
+    InvalidModule(usize),
+    MissingLinkInfo(usize),
+    DuplicateSymbol(String),
+    UndefinedSymbol(String),
+    // note: 連結後の定数の数
+    TooManyConstants(usize),
+    // note: 連結後のソースファイルの数 (デバッグ情報のファイルインデックスは u16)
+    TooManyFiles(usize),
+    // note: 連結後のセクションを出力できない (文字列や要素数が上限を超えた)
+    Encode(EncodeError),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            LinkError::InvalidModule(module_i) => write!(f, "module #{} is invalid", module_i),
+            LinkError::MissingLinkInfo(module_i) => write!(f, "module #{} has no link info", module_i),
+            LinkError::DuplicateSymbol(name) => write!(f, "symbol `{}` is exported more than once", name),
+            LinkError::UndefinedSymbol(name) => write!(f, "symbol `{}` is not exported", name),
+            LinkError::TooManyConstants(len) => write!(f, "{} constants exceed the limit of {}", len, MAX_CONSTANT_LEN),
+            LinkError::TooManyFiles(len) => write!(f, "{} source files exceed the limit of {}", len, MAX_ENCODED_LEN),
+            LinkError::Encode(e) => write!(f, "linked bytecode cannot be encoded: {}", e),
+        };
+    }
+}
+
+pub type LinkResult<T> = Result<T, LinkError>;
+
+struct Module<'a> {
+    bytes: &'a [u8],
+    funcs: Vec<PoolFunc>,
+    link_info: LinkInfo,
+    debug_info: Option<DebugInfo>,
+    const_pool: Option<ConstPool>,
+}
+
+impl<'a> Module<'a> {
+    fn read(bytes: &'a [u8], module_i: usize) -> LinkResult<Module<'a>> {
+        let magic_number_range = HeaderItem::MagicNumber.get_bytecode_range();
+
+        if bytes.len() < *HEADER_SIZE || bytes[magic_number_range.begin..magic_number_range.begin + magic_number_range.len] != MAGIC_NUMBER[..] || !Bytecode::is_supported_version(bytes) {
+            return Err(LinkError::InvalidModule(module_i));
+        }
+
+        let link_info = match LinkInfo::read(bytes) {
+            Some(v) => v,
+            None => return Err(LinkError::MissingLinkInfo(module_i)),
+        };
+
+        let funcs = Bytecode::read_pool_funcs(bytes);
+        let const_pool = ConstPool::read(bytes);
+        let debug_info = DebugInfo::read(bytes);
+        let const_pool_addr_range = HeaderItem::ConstPoolAddr.get_bytecode_range();
+
+        let is_valid = (const_pool.is_some() || Bytecode::read_usize(bytes, const_pool_addr_range.begin) == Some(0))
+            && funcs.iter().enumerate().all(|(i, v)| v.pool_i == i)
+            && link_info.code_begin <= link_info.code_end
+            && link_info.code_end <= bytes.len()
+            && link_info.relocations.iter().all(|v| link_info.code_begin <= *v && v.saturating_add(size_of::<usize>()) <= link_info.code_end)
+            && link_info.const_relocations.iter().all(|v| link_info.code_begin <= *v && v.saturating_add(size_of::<u16>()) <= link_info.code_end)
+            && link_info.imports.iter().chain(link_info.exports.iter()).all(|v| v.1 < funcs.len())
+            // note: インポートを再エクスポートすることはできない
+            && link_info.exports.iter().all(|v| link_info.import_name(v.1).is_none())
+            // note: 行情報のファイルインデックスは連結時にずらすためモジュール内のファイルを指す必要がある
+            && debug_info.as_ref().map_or(true, |v| v.lines.iter().all(|line| (line.file_i as usize) < v.files.len()))
+            && funcs.iter().all(|v| link_info.import_name(v.pool_i).is_some() || (link_info.is_in_code(v.start_addr) && v.handlers.iter().all(|v| link_info.is_in_code(v.begin_addr) && link_info.is_in_code(v.end_addr) && link_info.is_in_code(v.handler_addr))));
+
+        if !is_valid {
+            return Err(LinkError::InvalidModule(module_i));
+        }
+
+        return Ok(Module {
+            bytes: bytes,
+            funcs: funcs,
+            link_info: link_info,
+            debug_info: debug_info,
+            const_pool: const_pool,
+        });
+    }
+}
+
+// note: 複数のモジュールのコードを連結し, プールインデックスを振り直して 1 つのバイトコードを生成する
+pub struct Linker {
+    modules: Vec<Vec<u8>>,
+}
+
+impl Linker {
+    pub fn new() -> Linker {
+        return Linker {
+            modules: Vec::new(),
+        };
+    }
+
+    // note: 最初に追加したモジュールのプールインデックス 0 がエントリポイントとなる
+    pub fn add_module(&mut self, bytes: Vec<u8>) {
+        self.modules.push(bytes);
+    }
+
+    pub fn link(&self) -> LinkResult<Vec<u8>> {
+        // note: リンク情報のない単一のモジュールはそのまま実行できる
+        if self.modules.len() == 1 && LinkInfo::read(&self.modules[0]).is_none() {
+            return Ok(self.modules[0].clone());
+        }
+
+        let mut modules = Vec::<Module>::new();
+
+        for (module_i, each_bytes) in self.modules.iter().enumerate() {
+            modules.push(Module::read(each_bytes, module_i)?);
+        }
+
+        if modules.len() == 0 || modules[0].funcs.len() == 0 || modules[0].link_info.import_name(0).is_some() {
+            return Err(LinkError::InvalidModule(0));
+        }
+
+        // note: インポート以外の関数にモジュール順で新しいプールインデックスを割り当てる
+        let mut pool_maps = Vec::<Vec<Option<usize>>>::new();
+        let mut linked_funcs = Vec::<(usize, usize)>::new();
+
+        for (module_i, each_module) in modules.iter().enumerate() {
+            let mut pool_map = Vec::<Option<usize>>::new();
+
+            for each_func in &each_module.funcs {
+                if each_module.link_info.import_name(each_func.pool_i).is_some() {
+                    pool_map.push(None);
+                } else {
+                    pool_map.push(Some(linked_funcs.len()));
+                    linked_funcs.push((module_i, each_func.pool_i));
+                }
+            }
+
+            pool_maps.push(pool_map);
+        }
+
+        let mut symbols = HashMap::<String, usize>::new();
+
+        for (module_i, each_module) in modules.iter().enumerate() {
+            for (name, pool_i) in &each_module.link_info.exports {
+                if symbols.insert(name.clone(), pool_maps[module_i][*pool_i].unwrap()).is_some() {
+                    return Err(LinkError::DuplicateSymbol(name.clone()));
+                }
+            }
+        }
+
+        for (module_i, each_module) in modules.iter().enumerate() {
+            for (name, pool_i) in &each_module.link_info.imports {
+                match symbols.get(name) {
+                    Some(v) => pool_maps[module_i][*pool_i] = Some(*v),
+                    None => return Err(LinkError::UndefinedSymbol(name.clone())),
+                }
+            }
+        }
+
+        // note: 各モジュールの定数をモジュール順に連結した定数プールにおける開始インデックス
+        let mut const_offsets = Vec::<usize>::new();
+        let mut constants = Vec::<Constant>::new();
+
+        for each_module in &modules {
+            const_offsets.push(constants.len());
+
+            if let Some(const_pool) = &each_module.const_pool {
+                constants.extend(const_pool.constants.iter().cloned());
+            }
+        }
+
+        if constants.len() > MAX_CONSTANT_LEN {
+            return Err(LinkError::TooManyConstants(constants.len()));
+        }
+
+        let file_len = modules.iter().filter_map(|v| v.debug_info.as_ref()).map(|v| v.files.len()).sum::<usize>();
+
+        if file_len > MAX_ENCODED_LEN {
+            return Err(LinkError::TooManyFiles(file_len));
+        }
+
+        // note: 連結後のコードにおける各モジュールのコードの開始位置
+        let mut code_offsets = Vec::<usize>::new();
+        let mut relocated_codes = Vec::<Vec<u8>>::new();
+        let mut code_len = 0usize;
+
+        for (module_i, each_module) in modules.iter().enumerate() {
+            let link_info = &each_module.link_info;
+            let mut code = each_module.bytes[link_info.code_begin..link_info.code_end].to_vec();
+            let const_len = each_module.const_pool.as_ref().map_or(0, |v| v.constants.len());
+
+            for each_addr in &link_info.relocations {
+                let index = each_addr - link_info.code_begin;
+                let mut buf = [0u8; size_of::<usize>()];
+                buf.copy_from_slice(&code[index..index + size_of::<usize>()]);
+
+                // note: 範囲外のプールインデックスは実行時に BYTECODE_ACCESS_VIOLATION となるよう置き換える
+                let pool_i = match pool_maps[module_i].get(usize::from_ne_bytes(buf)) {
+                    Some(Some(v)) => *v,
+                    _ => usize::MAX,
+                };
+
+                code[index..index + size_of::<usize>()].copy_from_slice(&pool_i.to_ne_bytes());
+            }
+
+            for each_addr in &link_info.const_relocations {
+                let index = each_addr - link_info.code_begin;
+                let const_i = u16::from_ne_bytes([code[index], code[index + 1]]) as usize;
+
+                // note: 範囲外の定数インデックスも同様に常に範囲外となる 0xffff に置き換える
+                let new_const_i = if const_i < const_len { (const_offsets[module_i] + const_i) as u16 } else { u16::MAX };
+                code[index..index + size_of::<u16>()].copy_from_slice(&new_const_i.to_ne_bytes());
+            }
+
+            code_offsets.push(code_len);
+            code_len += code.len();
+            relocated_codes.push(code);
+        }
+
+        return Linker::layout(&modules, &linked_funcs, &symbols, constants, &code_offsets, relocated_codes);
+    }
+
+    fn layout(modules: &Vec<Module>, linked_funcs: &Vec<(usize, usize)>, symbols: &HashMap<String, usize>, constants: Vec<Constant>, code_offsets: &Vec<usize>, relocated_codes: Vec<Vec<u8>>) -> LinkResult<Vec<u8>> {
+        let code_len = relocated_codes.iter().map(|v| v.len()).sum::<usize>();
+
+        // note: モジュール内のコードのアドレスを連結後のアドレスに変換
+        let relocate = |module_i: usize, code_begin: usize, addr: usize| -> usize {
+            return code_begin + code_offsets[module_i] + (addr - modules[module_i].link_info.code_begin);
+        };
+
+        let pool_funcs = |code_begin: usize| -> Vec<PoolFunc> {
+            return linked_funcs.iter().enumerate().map(|(pool_i, (module_i, module_pool_i))| {
+                let func = &modules[*module_i].funcs[*module_pool_i];
+
+                return PoolFunc {
+                    pool_i: pool_i,
+                    start_addr: relocate(*module_i, code_begin, func.start_addr),
+                    var_len: func.var_len,
+                    arg_len: func.arg_len,
+                    handlers: func.handlers.iter().map(|v| ExceptionHandler {
+                        begin_addr: relocate(*module_i, code_begin, v.begin_addr),
+                        end_addr: relocate(*module_i, code_begin, v.end_addr),
+                        handler_addr: relocate(*module_i, code_begin, v.handler_addr),
+                        code: v.code,
+                    }).collect(),
+                    symbol: func.symbol.clone(),
+                };
+            }).collect();
+        };
+
+        let debug_info = |code_begin: usize| -> Option<DebugInfo> {
+            if modules.iter().all(|v| v.debug_info.is_none()) {
+                return None;
+            }
+
+            let mut debug_info = DebugInfo::new();
+
+            debug_info.func_names = linked_funcs.iter().map(|(module_i, module_pool_i)| {
+                return modules[*module_i].debug_info.as_ref().and_then(|v| v.func_name(*module_pool_i)).unwrap_or_default().to_string();
+            }).collect();
+
+            for (module_i, each_module) in modules.iter().enumerate() {
+                if let Some(module_debug_info) = &each_module.debug_info {
+                    // note: ファイル数と各モジュールのファイルインデックスは検査済みのため u16 に収まる
+                    let file_offset = debug_info.files.len() as u16;
+                    debug_info.files.extend(module_debug_info.files.iter().cloned());
+
+                    for each_line in module_debug_info.lines.iter().filter(|v| each_module.link_info.is_in_code(v.addr)) {
+                        debug_info.lines.push(LineEntry {
+                            addr: relocate(module_i, code_begin, each_line.addr),
+                            file_i: file_offset + each_line.file_i,
+                            line: each_line.line,
+                            column: each_line.column,
+                        });
+                    }
+                }
+            }
+
+            return Some(debug_info);
+        };
+
+        let link_info = |code_begin: usize| -> LinkInfo {
+            let mut link_info = LinkInfo::new();
+            link_info.code_begin = code_begin;
+            link_info.code_end = code_begin + code_len;
+
+            let mut exports = symbols.iter().map(|(name, pool_i)| (name.clone(), *pool_i)).collect::<Vec<(String, usize)>>();
+            exports.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+            link_info.exports = exports;
+
+            for (module_i, each_module) in modules.iter().enumerate() {
+                link_info.relocations.extend(each_module.link_info.relocations.iter().map(|v| relocate(module_i, code_begin, *v)));
+                link_info.const_relocations.extend(each_module.link_info.const_relocations.iter().map(|v| relocate(module_i, code_begin, *v)));
+            }
+
+            return link_info;
+        };
+
+        // note: ヘッダはエントリポイントのモジュールのものを引き継ぐ
+        let mut bytes = modules[0].bytes[..*HEADER_SIZE].to_vec();
+
+        for each_item in &[HeaderItem::DebugInfoAddr, HeaderItem::LinkInfoAddr, HeaderItem::ConstPoolAddr] {
+            let addr_range = each_item.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&0usize.to_ne_bytes());
+        }
+
+        let mut const_pool = ConstPool::new();
+        const_pool.constants = constants;
+
+        // note: アセンブラと同様にプール要素のアドレス表 -> プール要素 -> 定数プール -> デバッグ情報 -> リンク情報 -> 各関数のコードの順に配置
+        let pool_entry_sizes = pool_funcs(0).iter().map(|v| v.encode().map(|v| v.len())).collect::<EncodeResult<Vec<usize>>>().map_err(LinkError::Encode)?;
+        let entry_table_begin = bytes.len() + linked_funcs.len() * size_of::<usize>();
+        let const_pool_begin = entry_table_begin + pool_entry_sizes.iter().sum::<usize>();
+        let const_pool_len = if const_pool.constants.len() == 0 { 0 } else { const_pool.encode(0).len() };
+        let debug_info_begin = const_pool_begin + const_pool_len;
+        let debug_info_len = match debug_info(0) {
+            Some(v) => v.encode().map_err(LinkError::Encode)?.len(),
+            None => 0,
+        };
+        let link_info_begin = debug_info_begin + debug_info_len;
+        let code_begin = link_info_begin + link_info(0).encode().map_err(LinkError::Encode)?.len();
+
+        let mut entry_addr = entry_table_begin;
+
+        for each_size in &pool_entry_sizes {
+            bytes.extend_from_slice(&entry_addr.to_ne_bytes());
+            entry_addr += each_size;
+        }
+
+        for each_func in pool_funcs(code_begin) {
+            bytes.append(&mut each_func.encode().map_err(LinkError::Encode)?);
+        }
+
+        if const_pool.constants.len() != 0 {
+            let addr_range = HeaderItem::ConstPoolAddr.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&const_pool_begin.to_ne_bytes());
+            bytes.append(&mut const_pool.encode(const_pool_begin));
+        }
+
+        if let Some(debug_info) = debug_info(code_begin) {
+            let addr_range = HeaderItem::DebugInfoAddr.get_bytecode_range();
+            bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&debug_info_begin.to_ne_bytes());
+            bytes.append(&mut debug_info.encode().map_err(LinkError::Encode)?);
+        }
+
+        let addr_range = HeaderItem::LinkInfoAddr.get_bytecode_range();
+        bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&link_info_begin.to_ne_bytes());
+        bytes.append(&mut link_info(code_begin).encode().map_err(LinkError::Encode)?);
+
+        for mut each_code in relocated_codes {
+            bytes.append(&mut each_code);
+        }
+
+        // note: エントリポイントからのリターン先 (バイトコード末尾)
+        bytes.push(Opcode::Exit.into());
+        return Ok(bytes);
+    }
+}
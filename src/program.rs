@@ -0,0 +1,129 @@
+use std::fmt::{Formatter, Display};
+
+use crate::bytecode::*;
+use crate::linker::*;
+use crate::runtime::ExitStatus;
+
+// note: 呼び出す関数の指定
+#[derive(Clone, Copy)]
+pub enum FuncRef<'a> {
+    // note: プール要素もしくはデバッグ情報の関数名
+    Name(&'a str),
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for FuncRef<'a> {
+    fn from(v: &'a str) -> FuncRef<'a> {
+        return FuncRef::Name(v);
+    }
+}
+
+impl<'a> From<usize> for FuncRef<'a> {
+    fn from(v: usize) -> FuncRef<'a> {
+        return FuncRef::Index(v);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadError {
+    InvalidHeader,
+    // note: エントリポイントの関数がない, もしくは開始アドレスがバイトコード外
+    InvalidEntryPoint,
+    Link(LinkError),
+}
+
+impl LoadError {
+    // note: 読み込まずに実行した場合と同じ終了ステータス
+    pub fn exit_status(&self) -> ExitStatus {
+        return match self {
+            LoadError::InvalidHeader => ExitStatus::InvalidHeader,
+            LoadError::InvalidEntryPoint => ExitStatus::BytecodeAccessViolation,
+            LoadError::Link(_) => ExitStatus::LinkError,
+        };
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            LoadError::InvalidHeader => write!(f, "invalid header"),
+            LoadError::InvalidEntryPoint => write!(f, "entry point is out of bytecode"),
+            LoadError::Link(e) => write!(f, "link error: {}", e),
+        };
+    }
+}
+
+pub type LoadResult<T> = Result<T, LoadError>;
+
+// note: リンクとヘッダの検証, プール要素とデバッグ情報の読み込みを済ませたバイトコード
+// note: 実行時には書き換えられないため複数のインタプリタで同時に実行できる
+pub struct Program {
+    bytes: Vec<u8>,
+    funcs: Vec<PoolFunc>,
+    debug_info: Option<DebugInfo>,
+}
+
+impl Program {
+    pub fn load(bytes: Vec<u8>) -> LoadResult<Program> {
+        return Program::link(vec![bytes]);
+    }
+
+    // note: 先頭のモジュールのプールインデックス 0 がエントリポイントとなる
+    pub fn link(modules: Vec<Vec<u8>>) -> LoadResult<Program> {
+        let mut linker = Linker::new();
+
+        for each_module in modules {
+            linker.add_module(each_module);
+        }
+
+        let bytes = match linker.link() {
+            Ok(v) => v,
+            Err(e) => return Err(LoadError::Link(e)),
+        };
+
+        let magic_number_range = HeaderItem::MagicNumber.get_bytecode_range();
+
+        if bytes.len() < *HEADER_SIZE || bytes[magic_number_range.begin..magic_number_range.begin + magic_number_range.len] != MAGIC_NUMBER[..] || !Bytecode::is_supported_version(&bytes) {
+            return Err(LoadError::InvalidHeader);
+        }
+
+        let funcs = Bytecode::read_pool_funcs(&bytes);
+
+        match funcs.first() {
+            Some(v) if v.pool_i == 0 && v.start_addr < bytes.len() => (),
+            _ => return Err(LoadError::InvalidEntryPoint),
+        }
+
+        return Ok(Program {
+            debug_info: DebugInfo::read(&bytes),
+            funcs: funcs,
+            bytes: bytes,
+        });
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        return &self.bytes;
+    }
+
+    pub fn funcs(&self) -> &Vec<PoolFunc> {
+        return &self.funcs;
+    }
+
+    pub fn debug_info(&self) -> Option<&DebugInfo> {
+        return self.debug_info.as_ref();
+    }
+
+    // note: 名前はプール要素の名前を優先し, なければデバッグ情報の関数名で検索
+    pub fn find_func<'a, T: Into<FuncRef<'a>>>(&self, func: T) -> Option<&PoolFunc> {
+        return match func.into() {
+            FuncRef::Name(name) => Bytecode::find_pool_func_by_name(&self.funcs, name).or_else(|| {
+                self.funcs.iter().find(|v| self.debug_info().and_then(|debug_info| debug_info.func_name(v.pool_i)) == Some(name))
+            }),
+            FuncRef::Index(pool_i) => self.funcs.iter().find(|v| v.pool_i == pool_i),
+        };
+    }
+
+    pub fn print(&self) {
+        Bytecode::new(self.bytes.clone()).print();
+    }
+}
@@ -1,17 +1,22 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Formatter, Display};
+use std::ptr::{copy, copy_nonoverlapping};
 use std::slice::from_raw_parts;
 use std::mem::size_of;
 
 use crate::bytecode::*;
+use crate::program::*;
+use crate::text::*;
 
 use colored::*;
 
-use libc::{c_void, malloc, free, read, write};
+use libc::{c_void, calloc, free, read, write};
 
 use num::FromPrimitive;
 use num_derive::*;
 
-#[derive(FromPrimitive)]
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
 pub enum ExitStatus {
     Success,
     UnknownOpcode,
@@ -22,6 +27,13 @@ pub enum ExitStatus {
     ArrayAccessViolation,
     ArithmeticOverflow,
     DivideByZero,
+    InvalidHeader,
+    OutOfMemory,
+    Aborted,
+    UncaughtException,
+    LinkError,
+    ObjectAccessViolation,
+    InvalidEncoding,
     Unknown,
 }
 
@@ -37,6 +49,13 @@ impl Display for ExitStatus {
             ExitStatus::ArrayAccessViolation => "ARRAY_ACCESS_VIOLATION",
             ExitStatus::ArithmeticOverflow => "ARITHMETIC_OVERFLOW",
             ExitStatus::DivideByZero => "DIVIDE_BY_ZERO",
+            ExitStatus::InvalidHeader => "INVALID_HEADER",
+            ExitStatus::OutOfMemory => "OUT_OF_MEMORY",
+            ExitStatus::Aborted => "ABORTED",
+            ExitStatus::UncaughtException => "UNCAUGHT_EXCEPTION",
+            ExitStatus::LinkError => "LINK_ERROR",
+            ExitStatus::ObjectAccessViolation => "OBJECT_ACCESS_VIOLATION",
+            ExitStatus::InvalidEncoding => "INVALID_ENCODING",
             ExitStatus::Unknown => "UNKNOWN",
         };
 
@@ -53,7 +72,7 @@ impl From<u32> for ExitStatus {
     }
 }
 
-#[derive(FromPrimitive)]
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
 pub enum Opcode {
     Unknown,
     Nop,
@@ -105,6 +124,28 @@ pub enum Opcode {
     Goto,
     If,
     IfNot,
+    Throw,
+    BAConst,
+    Ldc,
+    Ldc2,
+    New,
+    GetField,
+    SetField,
+    RAPush,
+    RALoad,
+    RAStore,
+    MAPush,
+    ArrayLength,
+    ArrayCopy,
+    ArrayFill,
+    ArrayResize,
+    BAAppend,
+    TableSwitch,
+    LookupSwitch,
+    GotoW,
+    IfW,
+    IfNotW,
+    TailInvoke,
 }
 
 impl Display for Opcode {
@@ -160,6 +201,28 @@ impl Display for Opcode {
             Opcode::Goto => "goto",
             Opcode::If => "if",
             Opcode::IfNot => "ifnot",
+            Opcode::Throw => "throw",
+            Opcode::BAConst => "baconst",
+            Opcode::Ldc => "ldc",
+            Opcode::Ldc2 => "ldc2",
+            Opcode::New => "new",
+            Opcode::GetField => "getfield",
+            Opcode::SetField => "setfield",
+            Opcode::RAPush => "rapush",
+            Opcode::RALoad => "raload",
+            Opcode::RAStore => "rastore",
+            Opcode::MAPush => "mapush",
+            Opcode::ArrayLength => "arraylength",
+            Opcode::ArrayCopy => "arraycopy",
+            Opcode::ArrayFill => "arrayfill",
+            Opcode::ArrayResize => "arrayresize",
+            Opcode::BAAppend => "baappend",
+            Opcode::TableSwitch => "tableswitch",
+            Opcode::LookupSwitch => "lookupswitch",
+            Opcode::GotoW => "goto_w",
+            Opcode::IfW => "if_w",
+            Opcode::IfNotW => "ifnot_w",
+            Opcode::TailInvoke => "tailinvoke",
         };
 
         return write!(f, "{}", s);
@@ -182,46 +245,512 @@ impl Into<u8> for Opcode {
     }
 }
 
+pub struct InterpreterConfig {
+    // note: 命令ごとのトレース出力を行うかどうか (既定では行わない)
+    pub is_traced: bool,
+    // note: コンソール出力を標準出力でなく RunResult に格納するかどうか
+    pub is_output_captured: bool,
+    // note: 配列に確保できるヒープ領域の合計バイト数
+    pub max_heap_size: usize,
+}
+
+// spec: ヒープ領域の合計バイト数には配列やオブジェクトごとのバイトサイズ (usize) の領域も含める
+pub(crate) const HEAP_HEADER_SIZE: usize = size_of::<usize>();
+
+// note: 配列ハンドルの最上位ビットを立てて小さな整数値と衝突しないようにする
+// spec: ハンドルは実行中に再利用しない (解放済みのハンドルが後から確保した配列を指すことはない)
+pub(crate) const ARRAY_HANDLE_BASE: usize = (usize::MAX >> 1) + 1;
+
+impl InterpreterConfig {
+    pub fn new() -> InterpreterConfig {
+        return InterpreterConfig {
+            is_traced: false,
+            is_output_captured: false,
+            max_heap_size: 64 * 1024 * 1024,
+        };
+    }
+}
+
+// spec: 関数呼び出しの引数と戻り値 (int は変数テーブルの 1 要素, long は 2 要素を占める)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Void,
+    Int(u32),
+    Long(u64),
+}
+
+impl Value {
+    // note: 変数テーブル上の要素数
+    pub fn slot_len(&self) -> usize {
+        return match self {
+            Value::Void => 0,
+            Value::Int(_) => 1,
+            Value::Long(_) => 2,
+        };
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Value::Void => write!(f, "void"),
+            Value::Int(v) => write!(f, "int 0x{:x} ({})", v, v),
+            Value::Long(v) => write!(f, "long 0x{:x} ({})", v, v),
+        };
+    }
+}
+
+pub struct RunResult {
+    pub exit_status: ExitStatus,
+    // note: 実行された命令の数 (終了時の命令を含む)
+    pub inst_count: usize,
+    // note: 出力をキャプチャした場合のコンソール出力
+    pub output: Vec<u8>,
+    // note: 命令の実行中に異常終了した場合の詳細
+    pub fault: Option<Fault>,
+    // note: 異常終了時の呼び出し履歴 (先頭が最上位)
+    pub backtrace: Vec<BacktraceFrame>,
+    // note: 関数を呼び出して実行した場合に ret で返された値
+    pub ret_value: Option<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaultDetail {
+    None,
+    // note: 配列の要素インデックスとバイトサイズ
+    ArrayIndex { index: usize, size: usize },
+    // note: 確保済みでない配列のハンドル
+    ArrayPointer(usize),
+    // note: 命令と種類 (参照配列かどうか) の異なる配列, もしくはオブジェクトのハンドル
+    ArrayKind(usize),
+    // note: mapush / arraylength などのオペランドの次元数と要素のバイトサイズ
+    ArrayShape { dims: usize, elem_size: usize },
+    // note: 演算の被演算子
+    Operands { left: u64, right: u64 },
+    // note: スタックポインタ, ベースポインタとアクセスしようとしたバイトサイズ
+    StackAccess { sp: usize, bp: usize, size: usize },
+    // note: 範囲外のバイトコードアドレス
+    BytecodeAddress(usize),
+    // note: 負のアドレスとなるジャンプ先
+    JumpTarget(isize),
+    // note: 確保しようとした配列の要素数と要素のバイトサイズ
+    Allocation { len: usize, elem_size: usize },
+    // note: 呼び出そうとした関数のプールインデックスと変数長・引数長
+    Invocation { pool_i: usize, var_len: usize, arg_len: usize },
+    CallNumber(u8),
+    OpcodeByte(u8),
+    // note: 捕捉されなかった例外コード
+    Exception(u32),
+    // note: 呼び出された関数の ret 時点でのオペランドスタックのバイトサイズ
+    ReturnValue(isize),
+    // note: 存在しない, もしくは種別の異なる定数のインデックス
+    Constant(usize),
+    // note: new で確保されたオブジェクトでないハンドル
+    ObjectPointer(usize),
+    // note: オブジェクトのフィールドインデックスとフィールド数
+    Field { field_i: usize, field_len: usize },
+    // note: 文字列操作で不正な UTF-8 のバイト列を受け取った場合の先頭から有効なバイト数
+    Encoding { valid_len: usize },
+    // note: 範囲外の文字インデックスと文字数
+    CharIndex { index: usize, len: usize },
+    // note: 文字列操作で不足している, もしくは種類の異なる引数のインデックス
+    TextArgument(usize),
+}
+
+impl Display for FaultDetail {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            FaultDetail::None => Ok(()),
+            FaultDetail::ArrayIndex { index, size } => write!(f, "index {} / {} byte size", index, size),
+            FaultDetail::ArrayPointer(ptr) => write!(f, "array 0x{:x} is not allocated", ptr),
+            FaultDetail::ArrayKind(ptr) => write!(f, "array 0x{:x} is of another kind", ptr),
+            FaultDetail::ArrayShape { dims, elem_size } => write!(f, "{} dimensions / {} byte element size", dims, elem_size),
+            FaultDetail::Operands { left, right } => write!(f, "left 0x{:x} / right 0x{:x}", left, right),
+            FaultDetail::StackAccess { sp, bp, size } => write!(f, "sp 0x{:x} / bp 0x{:x} / {} byte access", sp, bp, size),
+            FaultDetail::BytecodeAddress(addr) => write!(f, "address 0x{:x}", addr),
+            FaultDetail::JumpTarget(addr) => write!(f, "jump to {}", addr),
+            FaultDetail::Allocation { len, elem_size } => write!(f, "{} elements / {} byte element size", len, elem_size),
+            FaultDetail::Invocation { pool_i, var_len, arg_len } => write!(f, "pool index 0x{:x} / {} variables / {} arguments", pool_i, var_len, arg_len),
+            FaultDetail::CallNumber(code) => write!(f, "call number 0x{:x}", code),
+            FaultDetail::OpcodeByte(opcode) => write!(f, "opcode 0x{:x}", opcode),
+            FaultDetail::Exception(code) => write!(f, "exception code 0x{:x}", code),
+            FaultDetail::ReturnValue(size) => write!(f, "{} byte return value", size),
+            FaultDetail::Constant(const_i) => write!(f, "constant index 0x{:x}", const_i),
+            FaultDetail::ObjectPointer(ptr) => write!(f, "object 0x{:x} is not allocated", ptr),
+            FaultDetail::Field { field_i, field_len } => write!(f, "field {} / {} fields", field_i, field_len),
+            FaultDetail::Encoding { valid_len } => write!(f, "invalid utf-8 sequence after {} bytes", valid_len),
+            FaultDetail::CharIndex { index, len } => write!(f, "char index {} / {} chars", index, len),
+            FaultDetail::TextArgument(index) => write!(f, "text argument {} is missing or of wrong kind", index),
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fault {
+    pub status: ExitStatus,
+    // note: 異常終了した命令の位置
+    pub pc: usize,
+    pub opcode: Opcode,
+    pub detail: FaultDetail,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at 0x{:x} ({})", self.status, self.pc, self.opcode.to_string().to_uppercase())?;
+
+        if self.detail != FaultDetail::None {
+            write!(f, ": {}", self.detail)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BacktraceFrame {
+    // note: 最上位のフレームでは異常終了した命令の位置, それ以外ではリターンアドレス
+    pub pc: usize,
+    // note: 関数を特定できない場合は None
+    pub pool_i: Option<usize>,
+    pub func_name: Option<String>,
+    // note: 関数の開始位置からのオフセット
+    pub offset: usize,
+    // note: デバッグ情報から得たソース位置 (ファイル, 行, 列)
+    pub source: Option<(String, u32, u32)>,
+}
+
+impl BacktraceFrame {
+    pub fn new(funcs: &[PoolFunc], debug_info: Option<&DebugInfo>, pc: usize) -> BacktraceFrame {
+        let func = Bytecode::find_pool_func(funcs, pc);
+
+        return BacktraceFrame {
+            pc: pc,
+            pool_i: func.map(|v| v.pool_i),
+            func_name: func.and_then(|v| v.name(debug_info)).map(|v| v.to_string()),
+            offset: func.map_or(0, |v| pc - v.start_addr),
+            source: debug_info.and_then(|v| v.location(pc)).map(|v| (v.file.to_string(), v.line, v.column)),
+        };
+    }
+
+    // note: 退避されたベースポインタとリターンアドレスを辿ってバックトレースを生成
+    pub(crate) fn collect(state: &InterpreterState, funcs: &[PoolFunc], debug_info: Option<&DebugInfo>) -> Vec<BacktraceFrame> {
+        return state.frames().iter().map(|v| BacktraceFrame::new(funcs, debug_info, v.pc)).collect();
+    }
+}
+
+impl Display for BacktraceFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:x}", self.pc)?;
+
+        match (&self.func_name, self.pool_i) {
+            (Some(name), _) => write!(f, " ({} +0x{:x})", name, self.offset)?,
+            (None, Some(pool_i)) => write!(f, " (func {} +0x{:x})", pool_i, self.offset)?,
+            (None, None) => (),
+        }
+
+        if let Some((file, line, column)) = &self.source {
+            write!(f, " at {}:{}:{}", file, line, column)?;
+        }
+
+        return Ok(());
+    }
+}
+
+// note: 例外を捕捉するハンドラへの巻き戻し先
+pub(crate) struct HandlerTarget {
+    // note: 巻き戻すフレーム数
+    pub(crate) depth: usize,
+    pub(crate) bp: usize,
+    pub(crate) sp: usize,
+    pub(crate) handler_addr: usize,
+}
+
+pub struct StackFrame {
+    // note: 最上位のフレームでは実行中の位置, それ以外ではリターンアドレス
+    pub pc: usize,
+    pub bp: usize,
+}
+
+// note: 命令の実行直前の状態
+pub struct InterpreterState<'a> {
+    // note: Program Counter
+    pub pc: usize,
+    // note: Stack Pointer
+    pub sp: usize,
+    // note: Base Pointer
+    pub bp: usize,
+    pub opcode: Opcode,
+    // note: エントリポイントを 0 とした呼び出しの深さ
+    pub call_depth: usize,
+    pub stack: &'a [u8],
+    pub bytecode: &'a [u8],
+}
+
+impl<'a> InterpreterState<'a> {
+    fn read_stack_usize(&self, index: usize) -> Option<usize> {
+        if index + size_of::<usize>() > self.stack.len() {
+            return None;
+        }
+
+        let mut buf = [0u8; size_of::<usize>()];
+        buf.copy_from_slice(&self.stack[index..index + size_of::<usize>()]);
+        return Some(usize::from_ne_bytes(buf));
+    }
+
+    // note: 現在の関数の変数値 (スタックポインタ以降であれば None)
+    pub fn local(&self, var_i: usize) -> Option<u32> {
+        return self.frame_local(self.bp, var_i);
+    }
+
+    // note: ベースポインタが bp であるフレームの変数値
+    pub fn frame_local(&self, bp: usize, var_i: usize) -> Option<u32> {
+        let index = bp + size_of::<usize>() * 2 + var_i * size_of::<u32>();
+
+        if index + size_of::<u32>() > self.sp {
+            return None;
+        }
+
+        let mut buf = [0u8; size_of::<u32>()];
+        buf.copy_from_slice(&self.stack[index..index + size_of::<u32>()]);
+        return Some(u32::from_ne_bytes(buf));
+    }
+
+    // note: 退避されたベースポインタとリターンアドレスを辿ってフレームを列挙 (先頭が最上位)
+    pub fn frames(&self) -> Vec<StackFrame> {
+        let mut frames = vec![StackFrame {
+            pc: self.pc,
+            bp: self.bp,
+        }];
+
+        let mut bp = self.bp;
+
+        for _ in 0..self.call_depth {
+            let (saved_bp, ret_addr) = match (self.read_stack_usize(bp), self.read_stack_usize(bp + size_of::<usize>())) {
+                (Some(saved_bp), Some(ret_addr)) => (saved_bp, ret_addr),
+                _ => break,
+            };
+
+            frames.push(StackFrame {
+                pc: ret_addr,
+                bp: saved_bp,
+            });
+
+            bp = saved_bp;
+        }
+
+        return frames;
+    }
+
+    // note: 最上位のフレームから順に例外コードに一致するハンドラを探索 (呼び出し元では invoke 命令の位置で判定)
+    pub(crate) fn find_handler(&self, funcs: &[PoolFunc], code: u32) -> Option<HandlerTarget> {
+        let frames = self.frames();
+
+        for (frame_i, each_frame) in frames.iter().enumerate() {
+            let pc = if frame_i == 0 { each_frame.pc } else { each_frame.pc.saturating_sub(1) };
+
+            let func = match Bytecode::find_pool_func(funcs, pc) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if let Some(handler) = func.handlers.iter().find(|v| v.is_matched(pc, code)) {
+                return Some(HandlerTarget {
+                    depth: frame_i,
+                    bp: each_frame.bp,
+                    // note: 呼び出し元では ret と同様に呼び出し先のフレームを破棄し, オペランドスタックは呼び出し時点のまま
+                    sp: if frame_i == 0 { self.sp } else { frames[frame_i - 1].bp },
+                    handler_addr: handler.handler_addr,
+                });
+            }
+        }
+
+        return None;
+    }
+}
+
+pub enum HookAction {
+    Continue,
+    // note: 実行を中断して ABORTED で終了
+    Abort,
+}
+
+// note: 各命令の実行直前に呼び出される
+pub trait InterpreterHook {
+    fn on_inst(&mut self, state: &InterpreterState) -> HookAction;
+}
+
 pub struct Interpreter {}
 
 impl Interpreter {
-    pub unsafe fn launch(bytecode_bytes: Vec<u8>) -> ExitStatus {
+    pub unsafe fn launch(bytecode_bytes: Vec<u8>, config: &InterpreterConfig) -> RunResult {
+        return Interpreter::launch_with_hook(bytecode_bytes, config, None);
+    }
+
+    pub unsafe fn launch_with_hook(bytecode_bytes: Vec<u8>, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        let bytecode = Bytecode::new(bytecode_bytes);
+
+        if let Some(result) = Interpreter::check_header(&bytecode, config) {
+            return result;
+        }
+
+        if config.is_traced {
+            bytecode.print();
+        }
+
+        return Interpreter::run(&bytecode.into_vec(), None, config, hook, None);
+    }
+
+    // note: 読み込み済みのプログラムをエントリポイントから実行
+    pub unsafe fn launch_program(program: &Program, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        if config.is_traced {
+            program.print();
+        }
+
+        return Interpreter::run(program.bytes(), Some(program), config, hook, None);
+    }
+
+    pub unsafe fn call_program(program: &Program, config: &InterpreterConfig, pool_i: usize, args: &[Value]) -> RunResult {
+        if config.is_traced {
+            program.print();
+        }
+
+        return Interpreter::run(program.bytes(), Some(program), config, None, Some((pool_i, args)));
+    }
+
+    // note: プールインデックスの関数を引数付きで呼び出し, 対応する ret までを実行
+    pub unsafe fn launch_call(bytecode_bytes: Vec<u8>, config: &InterpreterConfig, pool_i: usize, args: &[Value]) -> RunResult {
         let bytecode = Bytecode::new(bytecode_bytes);
 
-        if *HEADER_SIZE > bytecode.len() {
-            panic!("{}", "invalid header size".on_red());
+        if let Some(result) = Interpreter::check_header(&bytecode, config) {
+            return result;
         }
 
-        if !bytecode.match_bytes(HeaderItem::MagicNumber.get_bytecode_range(), &MAGIC_NUMBER.to_vec()) {
-            panic!("{}", "invalid magic number".on_red());
+        if config.is_traced {
+            bytecode.print();
         }
 
-        bytecode.print();
-        return Interpreter::run(&mut *bytecode.into_vec());
+        return Interpreter::run(&bytecode.into_vec(), None, config, None, Some((pool_i, args)));
     }
 
-    unsafe fn run(bytecode_bytes: &mut Vec<u8>) -> ExitStatus {
+    // note: ヘッダが不正な場合は実行せずに終了結果を返す
+    pub(crate) fn check_header(bytecode: &Bytecode, config: &InterpreterConfig) -> Option<RunResult> {
+        let header_err_msg = if *HEADER_SIZE > bytecode.len() {
+            "invalid header size"
+        } else if !bytecode.match_bytes(HeaderItem::MagicNumber.get_bytecode_range(), &MAGIC_NUMBER.to_vec()) {
+            "invalid magic number"
+        } else if !Bytecode::is_supported_version(bytecode.as_slice()) {
+            "unsupported ches version"
+        } else {
+            return None;
+        };
+
+        if config.is_traced {
+            println!("{}", header_err_msg.on_red());
+        }
+
+        return Some(RunResult {
+            exit_status: ExitStatus::InvalidHeader,
+            inst_count: 0,
+            output: Vec::new(),
+            fault: None,
+            backtrace: Vec::new(),
+            ret_value: None,
+        });
+    }
+
+    // note: program は bytecode_bytes を読み込み済みのプログラム, entry_call はエントリポイントの代わりに呼び出す関数のプールインデックスと引数
+    unsafe fn run(bytecode_bytes: &[u8], program: Option<&Program>, config: &InterpreterConfig, mut hook: Option<&mut dyn InterpreterHook>, entry_call: Option<(usize, &[Value])>) -> RunResult {
         let mut is_init_succeeded = true;
         // note: Exit Status
         let mut es = ExitStatus::Success as u32;
+        // note: Instruction Count
+        let mut inst_count = 0usize;
+        // note: Captured Output
+        let mut output = Vec::<u8>::new();
+        // note: Instruction PC (実行中の命令のアドレス)
+        let mut inst_pc = None;
+        // note: Fault Detail
+        let mut fault_detail = FaultDetail::None;
+        // note: Return Value (関数を呼び出した場合のみ)
+        let mut ret_value = None;
+        // note: Pool Functions (例外の送出時とバックトレースの生成時に参照する; 読み込み済みでなければ最初に必要になった時点で一度だけ読み込む)
+        let mut pool_funcs = program.map(|v| Cow::Borrowed(v.funcs().as_slice()));
+
+        // note: トレース無効時は出力しない
+        macro_rules! trace {
+            ($($arg:tt)*) => {
+                if config.is_traced {
+                    println!($($arg)*);
+                }
+            };
+        }
 
         let bytecode_len = bytecode_bytes.len();
-        let bytecode_ptr = bytecode_bytes.as_mut_ptr() as *mut c_void;
+        // note: バイトコードへの書き込みは行わない
+        let bytecode_ptr = bytecode_bytes.as_ptr() as *mut c_void;
 
-        let pool_offset = 128usize;
+        let pool_offset = *HEADER_SIZE;
         let mut pool_ptr = bytecode_ptr.add(pool_offset);
 
-        let entry_point_func_index = *(bytecode_ptr.add(*(pool_ptr as *mut usize)) as *mut usize);
-        let entry_point_pc = entry_point_func_index;
-        let mut inst_ptr = bytecode_ptr.add(entry_point_pc);
+        // note: 範囲外の値を読み込まないようプール要素のアドレスと開始アドレスを順にチェック
+        let entry_pool_i = entry_call.map_or(0, |v| v.0);
+        let mut entry_point_pc = bytecode_len;
+        // note: 呼び出す関数の変数長と引数長 (エントリポイントは変数テーブルを確保しない)
+        let mut entry_var_len = 0usize;
+        let mut entry_arg_len = 0usize;
+
+        let entry_index_addr = entry_pool_i.saturating_mul(size_of::<usize>()).saturating_add(pool_offset);
+
+        if entry_index_addr.saturating_add(size_of::<usize>()) <= bytecode_len {
+            let entry_point_pool_addr = (bytecode_ptr.add(entry_index_addr) as *mut usize).read_unaligned();
+
+            if entry_point_pool_addr < bytecode_len && bytecode_len - entry_point_pool_addr >= size_of::<usize>() {
+                entry_point_pc = (bytecode_ptr.add(entry_point_pool_addr) as *mut usize).read_unaligned();
+            }
+
+            if entry_call.is_some() && entry_point_pool_addr < bytecode_len && bytecode_len - entry_point_pool_addr >= size_of::<usize>() + size_of::<u16>() + size_of::<u8>() {
+                entry_var_len = (bytecode_ptr.add(entry_point_pool_addr + size_of::<usize>()) as *mut u16).read_unaligned() as usize;
+                entry_arg_len = *(bytecode_ptr.add(entry_point_pool_addr + size_of::<usize>() + size_of::<u16>()) as *mut u8) as usize;
+            }
+        }
 
         if entry_point_pc >= bytecode_len {
             is_init_succeeded = false;
             es = ExitStatus::BytecodeAccessViolation as u32;
+            entry_point_pc = 0;
+        }
+
+        // note: 引数の要素数が呼び出す関数の引数長と一致しなければ終了
+        if let Some((pool_i, args)) = entry_call {
+            if is_init_succeeded && (entry_var_len < entry_arg_len || args.iter().map(|v| v.slot_len()).sum::<usize>() != entry_arg_len) {
+                is_init_succeeded = false;
+                es = ExitStatus::StackAccessViolation as u32;
+                fault_detail = FaultDetail::Invocation { pool_i: pool_i, var_len: entry_var_len, arg_len: entry_arg_len };
+            }
         }
 
+        let mut inst_ptr = bytecode_ptr.add(entry_point_pc);
+
         let max_stack_size = 1024usize;
-        let mut stack_ptr = malloc(max_stack_size) as *mut c_void;
+        // note: 未初期化の変数が不定値とならないようゼロ初期化
+        let mut stack_ptr = calloc(max_stack_size, 1) as *mut c_void;
+
+        if stack_ptr.is_null() {
+            is_init_succeeded = false;
+            es = ExitStatus::OutOfMemory as u32;
+        }
+
+        // note: 確保済み配列のハンドルとアドレス (不正なハンドルや解放済みの配列へのアクセスを防ぐ)
+        // note: 解放したアドレスは malloc で再利用されうるためアドレスをそのままハンドルとしない
+        let mut heap = HashMap::<usize, *mut c_void>::new();
+        let mut heap_size = 0usize;
+        let mut next_handle = ARRAY_HANDLE_BASE;
+        // note: new で確保した配列のハンドルと構造体の定数インデックス (配列と同様に drop で解放する)
+        let mut objects = HashMap::<usize, usize>::new();
+        // note: new で参照した構造体の定数インデックスとフィールドの配置 (定数ごとに最初の new で一度だけ求める)
+        let mut layouts = HashMap::<usize, StructLayout>::new();
+        // note: 要素が配列やオブジェクトのハンドルである配列 (rapush / mapush で確保したもの)
+        let mut ref_arrays = HashSet::<usize>::new();
 
         // note: Stack Pointer
         let mut sp = 0usize;
@@ -231,23 +760,32 @@ impl Interpreter {
         let mut pc = entry_point_pc;
         // note: Pool Pointer
         let mut pp = pool_offset;
+        // note: Call Depth
+        let mut call_depth = 0usize;
+        // note: 呼び出し中の関数ごとの変数長 (先頭がエントリポイント, 末尾が現在の関数)
+        let mut var_lens = vec![entry_var_len];
 
         // note: 'operator ブロック外での終了処理
         // fix: 処理が中断されない
         macro_rules! exit {
             ($status_kind:ident) => {
+                exit!($status_kind, FaultDetail::None)
+            };
+
+            ($status_kind:ident, $detail:expr) => {
                 {
                     es = ExitStatus::$status_kind as u32;
+                    fault_detail = $detail;
                     is_init_succeeded = false;
                 }
             };
         }
 
         macro_rules! jump_to {
-            ($ptr:expr, $curr_pos:expr, $jump_to:expr, $size:expr, $err_status:ident) => {
+            ($ptr:expr, $curr_pos:expr, $jump_to:expr, $size:expr, $err_status:ident, $detail:expr) => {
                 {
                     if $jump_to > $size {
-                        exit!($err_status);
+                        exit!($err_status, $detail);
                     }
 
                     $ptr = $ptr.offset($jump_to as isize - $curr_pos as isize);
@@ -258,37 +796,39 @@ impl Interpreter {
 
         macro_rules! jump_prg_to {
             ($index:expr) => {
-                jump_to!(inst_ptr, pc, $index, bytecode_len, BytecodeAccessViolation)
+                jump_to!(inst_ptr, pc, $index, bytecode_len, BytecodeAccessViolation, FaultDetail::BytecodeAddress($index))
             };
         }
 
         macro_rules! jump_pool_to {
             ($pool_index:expr) => {
                 {
-                    jump_to!(pool_ptr, pp, pool_offset + $pool_index * size_of::<usize>(), bytecode_len, BytecodeAccessViolation);
+                    let index_addr = ($pool_index).saturating_mul(size_of::<usize>()).saturating_add(pool_offset);
+                    jump_to!(pool_ptr, pp, index_addr, bytecode_len, BytecodeAccessViolation, FaultDetail::BytecodeAddress(index_addr));
                     let value_addr = next_pool!(usize);
-                    jump_to!(pool_ptr, pp, value_addr, bytecode_len, BytecodeAccessViolation);
+                    jump_to!(pool_ptr, pp, value_addr, bytecode_len, BytecodeAccessViolation, FaultDetail::BytecodeAddress(value_addr));
                 }
             };
         }
 
         macro_rules! jump_stack_to {
             ($index:expr) => {
-                jump_to!(stack_ptr, sp, $index, max_stack_size, StackAccessViolation)
+                jump_to!(stack_ptr, sp, $index, max_stack_size, StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: ($index).saturating_sub(sp) })
             };
         }
 
+        // spec: スタック上の値は 4 バイト単位で詰めて置くため long や usize は 8 バイト境界に揃わない; 読み書きは常にアラインメントを仮定しない
         macro_rules! push {
             ($ptr:expr, $curr_pos:expr, $ty:ty, $value:expr, $size:expr, $err_status:ident) => {
                 {
                     let value_size = size_of::<$ty>();
 
                     if $curr_pos + value_size > $size {
-                        exit!($err_status);
+                        exit!($err_status, FaultDetail::StackAccess { sp: $curr_pos, bp: bp, size: value_size });
                     }
 
                     let tmp_ptr = $ptr as *mut $ty;
-                    *tmp_ptr = $value;
+                    tmp_ptr.write_unaligned($value);
 
                     $curr_pos += value_size;
                     $ptr = $ptr.add(value_size);
@@ -317,14 +857,45 @@ impl Interpreter {
             };
         }
 
+        // note: バイトサイズの領域を含めて指定バイト数をヒープに確保できるかどうか
+        macro_rules! fits_heap {
+            ($len:expr) => {
+                matches!(($len).checked_add(HEAP_HEADER_SIZE), Some(v) if v <= config.max_heap_size - heap_size)
+            };
+        }
+
+        // note: ゼロ初期化した配列を確保してハンドルとアドレスを返す
+        macro_rules! alloc_arr {
+            ($elem_len:expr, $elem_size:expr) => {
+                {
+                    let elem_len = $elem_len;
+
+                    let arr_len = match elem_len.checked_mul($elem_size) {
+                        Some(v) if fits_heap!(v) => v,
+                        _ => exit!(OutOfMemory, FaultDetail::Allocation { len: elem_len, elem_size: $elem_size }),
+                    };
+
+                    let arr_ptr = calloc(size_of::<usize>() + arr_len, 1);
+
+                    if arr_ptr.is_null() {
+                        exit!(OutOfMemory, FaultDetail::Allocation { len: elem_len, elem_size: $elem_size });
+                    }
+
+                    *(arr_ptr as *mut usize) = arr_len;
+                    let handle = next_handle;
+                    next_handle += 0x10;
+                    heap.insert(handle, arr_ptr);
+                    heap_size += HEAP_HEADER_SIZE + arr_len;
+                    (handle, arr_ptr)
+                }
+            };
+        }
+
         macro_rules! stack_push_arr {
             ($ty:ty) => {
                 {
-                    // fix: 指定サイズ過大によるオーバーフロー
-                    let arr_len = next_prg!(usize) * size_of::<$ty>();
-                    let arr_ptr = malloc(size_of::<usize>() + arr_len);
-                    *(arr_ptr as *mut usize) = arr_len;
-                    stack_push!(*mut $ty, arr_ptr as *mut $ty);
+                    let (handle, _) = alloc_arr!(next_prg!(usize), size_of::<$ty>());
+                    stack_push!(usize, handle);
                 }
             };
         }
@@ -335,13 +906,13 @@ impl Interpreter {
                     let value_size = size_of::<$ty>();
 
                     if $curr_pos < value_size {
-                        exit!($err_status);
+                        exit!($err_status, FaultDetail::StackAccess { sp: $curr_pos, bp: bp, size: value_size });
                     }
 
                     $curr_pos -= value_size;
                     $ptr = $ptr.sub(value_size);
 
-                    *($ptr as *mut $ty)
+                    ($ptr as *mut $ty).read_unaligned()
                 }
             };
         }
@@ -364,7 +935,7 @@ impl Interpreter {
                 {
                     // note: リターンアドレス以前の値にアクセスしないようチェック
                     if sp < bp + size_of::<usize>() * 2 + size_of::<$ty>() {
-                        exit!(StackAccessViolation);
+                        exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: size_of::<$ty>() });
                     }
 
                     unsafe_stack_pop!($ty)
@@ -383,14 +954,14 @@ impl Interpreter {
                 {
                     // note: リターンアドレス以前の値にアクセスしないようチェック
                     if sp < bp + size_of::<usize>() * 2 {
-                        exit!(StackAccessViolation);
+                        exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: size_of::<$ty>() });
                     }
 
                     let diff = sp - bp - size_of::<usize>() * 2;
 
                     // note: スタックポインタ以降の値にアクセスしないようチェック
                     if diff < size_of::<u32>() * $var_i as usize + size_of::<$ty>() {
-                        exit!(StackAccessViolation);
+                        exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: size_of::<$ty>() });
                     }
 
                     diff - $var_i as usize * size_of::<u32>()
@@ -403,28 +974,137 @@ impl Interpreter {
                 {
                     let diff = var_table_diff!($ty, $var_i);
                     let value = stack_ptr.sub(diff) as *mut $ty;
-                    stack_push!($ty, *value);
+                    stack_push!($ty, value.read_unaligned());
+                }
+            };
+        }
+
+        // note: 確保済みの配列のハンドルであればアドレスを返し, そうでなければ終了
+        macro_rules! check_arr {
+            ($handle:expr) => {
+                match heap.get(&$handle) {
+                    Some(v) => *v,
+                    None => exit!(ArrayAccessViolation, FaultDetail::ArrayPointer($handle)),
+                }
+            };
+
+            // note: 参照配列の要素は参照配列用の命令, それ以外の配列の要素はそれ以外の命令でのみアクセスできる (オブジェクトはいずれも不可)
+            ($handle:expr, $is_ref:expr) => {
+                {
+                    let arr_ptr = check_arr!($handle);
+
+                    if objects.contains_key(&$handle) || ref_arrays.contains(&$handle) != $is_ref {
+                        exit!(ArrayAccessViolation, FaultDetail::ArrayKind($handle));
+                    }
+
+                    arr_ptr
                 }
             };
         }
 
+        // note: arraylength / arraycopy / arrayfill / arrayresize のオペランドである要素のバイトサイズと対象の配列を検証
+        // spec: 参照配列の要素のバイトサイズは 8 (usize) のみ
+        // note: 配列のアドレスと要素数を返す
+        macro_rules! arr_elem_size {
+            ($handle:expr, $elem_size:expr) => {
+                {
+                    let arr_ptr = check_arr!($handle);
+
+                    if objects.contains_key(&$handle) {
+                        exit!(ArrayAccessViolation, FaultDetail::ArrayKind($handle));
+                    }
+
+                    if ![1, 2, 4, 8].contains(&$elem_size) || (ref_arrays.contains(&$handle) && $elem_size != size_of::<usize>()) {
+                        exit!(ArrayAccessViolation, FaultDetail::ArrayShape { dims: 1, elem_size: $elem_size });
+                    }
+
+                    (arr_ptr, *(arr_ptr as *mut usize) / $elem_size)
+                }
+            };
+        }
+
+        // note: 内容を保持したまま配列のバイトサイズを変更して新しいハンドルとアドレスを返す (増えた領域はゼロ初期化)
+        // spec: 変更前のハンドルは解放されるため以降はアクセスできない
+        macro_rules! resize_arr {
+            ($handle:expr, $arr_ptr:expr, $new_size:expr, $detail:expr) => {
+                {
+                    let old_size = *($arr_ptr as *mut usize);
+
+                    if $new_size > old_size && $new_size - old_size > config.max_heap_size - heap_size {
+                        exit!(OutOfMemory, $detail);
+                    }
+
+                    let new_ptr = calloc(size_of::<usize>() + $new_size, 1);
+
+                    if new_ptr.is_null() {
+                        exit!(OutOfMemory, $detail);
+                    }
+
+                    copy_nonoverlapping(($arr_ptr as *mut usize).add(1) as *const u8, (new_ptr as *mut usize).add(1) as *mut u8, old_size.min($new_size));
+                    *(new_ptr as *mut usize) = $new_size;
+
+                    let new_handle = next_handle;
+                    next_handle += 0x10;
+                    heap.remove(&$handle);
+                    heap.insert(new_handle, new_ptr);
+
+                    if ref_arrays.remove(&$handle) {
+                        ref_arrays.insert(new_handle);
+                    }
+
+                    heap_size = heap_size - old_size + $new_size;
+                    free($arr_ptr as *mut c_void);
+                    (new_handle, new_ptr)
+                }
+            };
+        }
+
+        // note: 要素数 $len の範囲が配列内になければ終了
+        macro_rules! check_arr_range {
+            ($arr_ptr:expr, $begin:expr, $len:expr, $elem_len:expr) => {
+                match $begin.checked_add($len) {
+                    Some(v) if v <= $elem_len => (),
+                    _ => exit!(ArrayAccessViolation, FaultDetail::ArrayIndex { index: $begin.saturating_add($len), size: *($arr_ptr as *mut usize) }),
+                }
+            };
+        }
+
+        // note: オブジェクトのフィールドの型とデータ領域内のオフセット
+        macro_rules! field {
+            ($handle:expr, $field_i:expr) => {
+                {
+                    let layout = match objects.get(&$handle).and_then(|v| layouts.get(v)) {
+                        Some(v) => v,
+                        None => exit!(ObjectAccessViolation, FaultDetail::ObjectPointer($handle)),
+                    };
+
+                    match layout.field($field_i) {
+                        Some(v) => v,
+                        None => exit!(ObjectAccessViolation, FaultDetail::Field { field_i: $field_i, field_len: layout.fields.len() }),
+                    }
+                }
+            };
+        }
+
+        // spec: byte / short 配列の要素はゼロ拡張して int (4 バイト) としてプッシュする; スタックの最小単位は int であり, 後続の int 演算や store と幅を揃える
         macro_rules! load_arr {
-            ($ty:ty) => {
+            ($ty:ty, $push_ty:ty, $is_ref:expr) => {
                 {
                     let arr_i = stack_pop!(usize);
-                    let arr_ptr = stack_pop!(*mut c_void);
+                    let handle = stack_pop!(usize);
+                    let arr_ptr = check_arr!(handle, $is_ref);
                     let arr_size = *(arr_ptr as *mut usize);
 
-                    if (arr_i + 1) * size_of::<$ty>() > arr_size {
-                        exit!(ArrayAccessViolation);
+                    if arr_i >= arr_size / size_of::<$ty>() {
+                        exit!(ArrayAccessViolation, FaultDetail::ArrayIndex { index: arr_i, size: arr_size });
                     }
 
                     let arr_top_ptr = (arr_ptr as *mut usize).add(1);
                     let value = *(arr_top_ptr as *mut $ty).add(arr_i);
-                    stack_push!($ty, value);
+                    stack_push!($push_ty, value as $push_ty);
 
-                    println!("{}", format!("[index {} / {} byte size / value 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed());
-                    println!();
+                    trace!("{}", format!("[index {} / {} byte size / value 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed());
+                    trace!();
                 }
             };
         }
@@ -433,29 +1113,36 @@ impl Interpreter {
             ($ty:ty, $var_i:expr, $value:expr) => {
                 let diff = var_table_diff!($ty, $var_i);
                 let ptr = stack_ptr.sub(diff) as *mut $ty;
-                *ptr = $value
+                ptr.write_unaligned($value)
             };
         }
 
         macro_rules! store_arr {
-            ($ty:ty, $pop_ty:ty) => {
+            ($ty:ty, $pop_ty:ty, $is_ref:expr) => {
                 {
                     // fix: キャストでのオーバーフロー対処 (現在は数値が丸められてる)
                     let value = stack_pop!($pop_ty) as $ty;
                     let arr_i = stack_pop!(usize);
-                    let arr_ptr = stack_pop!(*mut c_void);
+                    let handle = stack_pop!(usize);
+                    let arr_ptr = check_arr!(handle, $is_ref);
+
+                    // note: 参照配列には null (0) もしくは確保済みの配列のハンドルのみ格納できる
+                    if $is_ref && value as usize != 0 {
+                        check_arr!(value as usize);
+                    }
+
                     let arr_size = *(arr_ptr as *mut usize);
 
-                    if (arr_i + 1) * size_of::<$ty>() > arr_size {
-                        exit!(ArrayAccessViolation);
+                    if arr_i >= arr_size / size_of::<$ty>() {
+                        exit!(ArrayAccessViolation, FaultDetail::ArrayIndex { index: arr_i, size: arr_size });
                     }
 
                     let arr_top_ptr = (arr_ptr as *mut usize).add(1);
                     let arr_elem_ptr = (arr_top_ptr as *mut $ty).add(arr_i) as *mut $ty;
                     *arr_elem_ptr = value;
 
-                    println!("{}", format!("[index {} / {} byte size / change value to 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed());
-                    println!();
+                    trace!("{}", format!("[index {} / {} byte size / change value to 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed());
+                    trace!();
                 }
             };
         }
@@ -467,10 +1154,10 @@ impl Interpreter {
                     let value_size = size_of::<$ty>();
 
                     if $counter < value_size {
-                        exit!($err_status);
+                        exit!($err_status, FaultDetail::StackAccess { sp: $counter, bp: bp, size: value_size });
                     }
 
-                    *($ptr as *mut $ty).sub(1)
+                    ($ptr as *mut $ty).sub(1).read_unaligned()
                 }
             };
         }
@@ -486,7 +1173,7 @@ impl Interpreter {
                 {
                     // note: リターンアドレス以前の値にアクセスしないようチェック
                     if sp < bp + size_of::<usize>() * 2 + size_of::<$ty>() {
-                        exit!(StackAccessViolation);
+                        exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: size_of::<$ty>() });
                     }
 
                     unsafe_stack_top!($ty)
@@ -500,11 +1187,11 @@ impl Interpreter {
                     let value_size = size_of::<$ty>();
 
                     if $curr_pos + value_size > $size {
-                        exit!($err_status);
+                        exit!($err_status, FaultDetail::BytecodeAddress($curr_pos));
                     }
 
                     let tmp_ptr = $ptr as *mut $ty;
-                    let value = *tmp_ptr;
+                    let value = tmp_ptr.read_unaligned();
                     $ptr = (tmp_ptr as *mut c_void).add(value_size);
                     $curr_pos += value_size;
 
@@ -555,14 +1242,14 @@ impl Interpreter {
 
                     $(
                         if $check_divide_by_zero && right_term == 0 {
-                            exit!(DivideByZero);
+                            exit!(DivideByZero, FaultDetail::Operands { left: left_term as u64, right: right_term as u64 });
                         }
                     )?
 
                     let (value, overflowing) = left_term.$f(right_term);
 
                     if overflowing {
-                        exit!(ArithmeticOverflow);
+                        exit!(ArithmeticOverflow, FaultDetail::Operands { left: left_term as u64, right: right_term as u64 });
                     }
 
                     stack_push!($ty, value);
@@ -570,17 +1257,18 @@ impl Interpreter {
             };
         }
 
+        // note: オフセットは goto / if / ifnot では i16, goto_w / if_w / ifnot_w では i32
         macro_rules! goto {
-            () => {
+            ($offset_ty:ty) => {
                 {
-                    let offset = next_prg!(i16);
+                    let offset = next_prg!($offset_ty);
                     let inst_i = pc as isize + offset as isize;
 
-                    println!("{}", format!("[goto 0x{:0x}]", inst_i).bright_green().dimmed());
-                    println!();
+                    trace!("{}", format!("[goto 0x{:0x}]", inst_i).bright_green().dimmed());
+                    trace!();
 
                     if 0 > inst_i {
-                        exit!(BytecodeAccessViolation);
+                        exit!(BytecodeAccessViolation, FaultDetail::JumpTarget(inst_i));
                     }
 
                     jump_prg_to!(inst_i as usize);
@@ -589,16 +1277,16 @@ impl Interpreter {
         }
 
         macro_rules! goto_if {
-            ($cond:expr) => {
+            ($cond:expr, $offset_ty:ty) => {
                 {
                     let jump_txt = if $cond { format!("jump to 0x{:0x}", pc) } else { "no jump".to_string() };
-                    println!("{}", format!("[{}]", jump_txt).bright_green().dimmed());
-                    println!();
+                    trace!("{}", format!("[{}]", jump_txt).bright_green().dimmed());
+                    trace!();
 
                     if $cond {
-                        goto!();
+                        goto!($offset_ty);
                     } else {
-                        next_prg!(i16);
+                        next_prg!($offset_ty);
                     }
                 }
             };
@@ -606,31 +1294,77 @@ impl Interpreter {
 
         if is_init_succeeded {
             // note: エントリポイント用のコールスタック要素をプッシュ
-            println!("{}", "<INVOKE ENTRY POINT>".blue());
-            println!();
+            trace!("{}", "<INVOKE ENTRY POINT>".blue());
+            trace!();
             // * ベースポインタ
             stack_push!(usize, 0);
             // * リターンアドレス
             stack_push!(usize, bytecode_len - 1);
 
+            // note: 初期化時の exit! では処理が中断されないため事前にスタック長をチェック
+            if let Some((_, args)) = entry_call {
+                if sp + entry_arg_len * size_of::<u32>() > max_stack_size {
+                    exit!(StackOverflow, FaultDetail::StackAccess { sp: sp, bp: bp, size: entry_arg_len * size_of::<u32>() });
+                } else if sp + entry_var_len * size_of::<u32>() > max_stack_size {
+                    exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: entry_var_len * size_of::<u32>() });
+                } else {
+                    // note: invoke と同様に引数をプッシュして残りの変数の要素分をスキップ
+                    for each_arg in args {
+                        match each_arg {
+                            Value::Void => (),
+                            Value::Int(v) => stack_push!(u32, *v),
+                            Value::Long(v) => stack_push!(u64, *v),
+                        }
+                    }
+
+                    jump_stack_to!(sp + (entry_var_len - entry_arg_len) * size_of::<u32>());
+                }
+            }
+        }
+
+        if is_init_succeeded {
             'operator: loop {
                 // note: 'operator ブロック内での終了処理
                 macro_rules! exit {
                     ($status_kind:ident) => {
+                        exit!($status_kind, FaultDetail::None)
+                    };
+
+                    ($status_kind:ident, $detail:expr) => {
                         {
                             es = ExitStatus::$status_kind as u32;
+                            fault_detail = $detail;
                             break 'operator;
                         }
                     };
                 }
 
+                inst_pc = Some(pc);
+
+                if let Some(hook) = &mut hook {
+                    let state = InterpreterState {
+                        pc: pc,
+                        sp: sp,
+                        bp: bp,
+                        opcode: if pc < bytecode_len { Opcode::from(*(inst_ptr as *mut u8)) } else { Opcode::Unknown },
+                        call_depth: call_depth,
+                        stack: from_raw_parts(stack_ptr.sub(sp) as *const u8, sp),
+                        bytecode: from_raw_parts(bytecode_ptr as *const u8, bytecode_len),
+                    };
+
+                    if let HookAction::Abort = hook.on_inst(&state) {
+                        exit!(Aborted);
+                    }
+                }
+
                 let tmp_pc = pc;
                 let opcode = next_prg!(u8);
+                inst_count += 1;
                 let opcode_kind = Opcode::from(opcode);
 
-                println!("{}", format!("{} (0x{:0x} at 0x{:0x})", opcode_kind.to_string().to_uppercase(), opcode, tmp_pc).blue());
-                println!("{}", raw_ptr_to_string!(stack_ptr.sub(sp), sp).bright_black());
-                println!();
+                trace!("{}", format!("{} (0x{:0x} at 0x{:0x})", opcode_kind.to_string().to_uppercase(), opcode, tmp_pc).blue());
+                trace!("{}", raw_ptr_to_string!(stack_ptr.sub(sp), sp).bright_black());
+                trace!();
 
                 match opcode_kind {
                     Opcode::Nop => (),
@@ -641,21 +1375,81 @@ impl Interpreter {
 
                         match code {
                             0x00 => {
-                                let a = [0u8; 4].as_mut_ptr() as *mut c_void;
+                                let mut buf = [0u8; 4];
+                                let a = buf.as_mut_ptr() as *mut c_void;
                                 let size = read(0, a, 4);
 
-                                println!("{} {}", size, raw_ptr_to_string!(a, 4));
+                                trace!("{} {}", size, raw_ptr_to_string!(a, 4));
                             },
                             0x01 => {
-                                let arr_ptr = stack_pop!(*mut usize);
+                                let handle = stack_pop!(usize);
+                                let arr_ptr = check_arr!(handle, false) as *mut usize;
                                 let arr_len = *arr_ptr;
 
-                                println!("{}", "[console output]".bright_black());
-                                println!("{}", raw_ptr_to_string!(arr_ptr.add(1), arr_len).bright_black());
-                                write(1, arr_ptr.add(1) as *mut c_void, arr_len as u32);
-                                println!();
+                                trace!("{}", "[console output]".bright_black());
+                                trace!("{}", raw_ptr_to_string!(arr_ptr.add(1), arr_len).bright_black());
+
+                                if config.is_output_captured {
+                                    output.extend_from_slice(from_raw_parts(arr_ptr.add(1) as *const u8, arr_len));
+                                } else {
+                                    // note: write のバイト数の型はプラットフォームにより異なる (Windows では u32, それ以外では usize)
+                                    write(1, arr_ptr.add(1) as *mut c_void, arr_len as _);
+                                }
+
+                                trace!();
+                            },
+                            _ => {
+                                let text_call = match TextCall::from_code(code) {
+                                    Some(v) => v,
+                                    None => exit!(UnknownCallNumber, FaultDetail::CallNumber(code)),
+                                };
+
+                                let mut args = Vec::<TextValue>::new();
+
+                                for each_kind in text_call.arg_kinds().iter().rev() {
+                                    let arg = match each_kind {
+                                        TextArgKind::Array => {
+                                            let handle = stack_pop!(usize);
+                                            let arr_ptr = check_arr!(handle, false) as *mut usize;
+                                            TextValue::Array(from_raw_parts(arr_ptr.add(1) as *const u8, *arr_ptr).to_vec())
+                                        },
+                                        TextArgKind::Int => TextValue::Int(stack_pop!(u32)),
+                                        TextArgKind::Long => TextValue::Long(stack_pop!(u64)),
+                                        TextArgKind::Usize => TextValue::Usize(stack_pop!(usize)),
+                                    };
+
+                                    args.push(arg);
+                                }
+
+                                args.reverse();
+
+                                let values = match text_call.apply(&args) {
+                                    Ok(v) => v,
+                                    Err(e) => {
+                                        // note: 終了ステータスが文字列操作のエラーにより異なるため exit! を使わずに設定
+                                        let (status, detail) = e.to_fault();
+                                        es = status as u32;
+                                        fault_detail = detail;
+                                        break 'operator;
+                                    },
+                                };
+
+                                for each_value in values {
+                                    match each_value {
+                                        TextValue::Array(v) => {
+                                            let (handle, arr_ptr) = alloc_arr!(v.len(), size_of::<u8>());
+                                            copy_nonoverlapping(v.as_ptr(), (arr_ptr as *mut usize).add(1) as *mut u8, v.len());
+                                            stack_push!(usize, handle);
+                                        },
+                                        TextValue::Int(v) => stack_push!(u32, v),
+                                        TextValue::Long(v) => stack_push!(u64, v),
+                                        TextValue::Usize(v) => stack_push!(usize, v),
+                                    }
+                                }
+
+                                trace!("{}", format!("[{:?}]", text_call).bright_green().dimmed());
+                                trace!();
                             },
-                            _ => exit!(UnknownCallNumber),
                         }
                     },
                     Opcode::Invoke => {
@@ -666,14 +1460,14 @@ impl Interpreter {
                         let arg_len = next_pool!(u8) as usize;
 
                         if var_len < arg_len || sp < arg_len * size_of::<u32>() {
-                            exit!(StackAccessViolation);
+                            exit!(StackAccessViolation, FaultDetail::Invocation { pool_i: pool_i, var_len: var_len, arg_len: arg_len });
                         }
 
                         // note: 引数値を事前にポップ
                         let mut args = Vec::<u32>::new();
 
                         for i in 0..arg_len {
-                            let new_arg = *((stack_ptr as *mut u32).sub(arg_len - i));
+                            let new_arg = (stack_ptr as *mut u32).sub(arg_len - i).read_unaligned();
                             args.push(new_arg);
                         }
 
@@ -698,13 +1492,75 @@ impl Interpreter {
 
                         // note: 開始アドレスにジャンプ
                         jump_prg_to!(start_addr);
+                        call_depth += 1;
+                        var_lens.push(var_len);
+
+                        trace!("{}", format!("[pool index 0x{:0x} / start at 0x{:0x} / return to 0x{:0x} / {} arguments]", pool_i, start_addr, ret_addr, arg_len).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::TailInvoke => {
+                        let pool_i = next_prg!(usize);
+                        jump_pool_to!(pool_i);
+                        let start_addr = next_pool!(usize);
+                        let var_len = next_pool!(u16) as usize;
+                        let arg_len = next_pool!(u8) as usize;
+                        let frame_header_size = size_of::<usize>() * 2;
+
+                        if sp < bp || sp - bp < frame_header_size {
+                            exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: frame_header_size });
+                        }
+
+                        // note: 引数は現在の関数の変数テーブルより上にあるオペランドスタックの値のみ
+                        let cur_var_len = var_lens[var_lens.len() - 1];
+
+                        if var_len < arg_len || sp - bp - frame_header_size < (cur_var_len + arg_len) * size_of::<u32>() {
+                            exit!(StackAccessViolation, FaultDetail::Invocation { pool_i: pool_i, var_len: var_len, arg_len: arg_len });
+                        }
+
+                        let mut args = Vec::<u32>::new();
+
+                        for i in 0..arg_len {
+                            let new_arg = (stack_ptr as *mut u32).sub(arg_len - i).read_unaligned();
+                            args.push(new_arg);
+                        }
+
+                        // note: 保存済みの bp とリターンアドレスを残して現在のフレームを破棄
+                        let pop_size = sp - bp - frame_header_size;
+                        unsafe_stack_pop!(u8, pop_size);
+
+                        for each_arg in args {
+                            stack_push!(u32, each_arg);
+                        }
+
+                        jump_stack_to!(sp + (var_len - arg_len) * size_of::<u32>());
+                        jump_prg_to!(start_addr);
 
-                        println!("{}", format!("[pool index 0x{:0x} / start at 0x{:0x} / return to 0x{:0x} / {} arguments]", pool_i, start_addr, ret_addr, arg_len).bright_green().dimmed());
-                        println!();
+                        // spec: フレームを再利用するため呼び出しの深さは変わらず, 呼び出された関数の ret で元の呼び出し元に戻る
+                        let var_len_i = var_lens.len() - 1;
+                        var_lens[var_len_i] = var_len;
+
+                        trace!("{}", format!("[pool index 0x{:0x} / start at 0x{:0x} / reuse frame at 0x{:0x} / pop {} bytes / {} arguments]", pool_i, start_addr, bp, pop_size, arg_len).bright_green().dimmed());
+                        trace!();
                     },
                     Opcode::Ret => {
                         if sp < bp || sp - bp < size_of::<usize>() * 2 {
-                            exit!(StackAccessViolation);
+                            exit!(StackAccessViolation, FaultDetail::StackAccess { sp: sp, bp: bp, size: size_of::<usize>() * 2 });
+                        }
+
+                        // spec: 呼び出された関数の ret ではオペランドスタックに残った値を戻り値として終了 (0 / 4 / 8 バイトで void / int / long)
+                        if entry_call.is_some() && call_depth == 0 {
+                            let operand_size = sp as isize - (bp + size_of::<usize>() * 2 + var_lens[0] * size_of::<u32>()) as isize;
+
+                            ret_value = Some(match operand_size {
+                                0 => Value::Void,
+                                4 => Value::Int(unsafe_stack_top!(u32)),
+                                8 => Value::Long(unsafe_stack_top!(u64)),
+                                _ => exit!(StackAccessViolation, FaultDetail::ReturnValue(operand_size)),
+                            });
+
+                            trace!("{}", format!("[return {}]", ret_value.unwrap()).bright_green().dimmed());
+                            trace!();
+                            exit!(Success);
                         }
 
                         // note: オペランドスタックと変数テーブルをポップ
@@ -717,14 +1573,180 @@ impl Interpreter {
 
                         // note: bp 設定
                         bp = unsafe_stack_pop!(usize);
+                        call_depth = call_depth.saturating_sub(1);
 
-                        println!("{}", format!("[return to 0x{:0x} / pop {} bytes / return void]", ret_addr, pop_size).bright_green().dimmed());
-                        println!();
+                        if var_lens.len() > 1 {
+                            var_lens.pop();
+                        }
+
+                        trace!("{}", format!("[return to 0x{:0x} / pop {} bytes / return void]", ret_addr, pop_size).bright_green().dimmed());
+                        trace!();
                     },
                     Opcode::BAPush => stack_push_arr!(u8),
                     Opcode::SAPush => stack_push_arr!(u16),
                     Opcode::IAPush => stack_push_arr!(u32),
                     Opcode::LAPush => stack_push_arr!(u64),
+                    Opcode::RAPush => {
+                        let (handle, _) = alloc_arr!(next_prg!(usize), size_of::<usize>());
+                        ref_arrays.insert(handle);
+                        stack_push!(usize, handle);
+                    },
+                    Opcode::MAPush => {
+                        let shape = ArrayShape::from_operand(next_prg!(u16));
+                        let (dims, elem_size) = (shape.dims as usize, shape.elem_size as usize);
+
+                        if !shape.is_valid() {
+                            exit!(ArrayAccessViolation, FaultDetail::ArrayShape { dims: dims, elem_size: elem_size });
+                        }
+
+                        // note: 外側の次元の要素数から順にプッシュされている
+                        let mut lens = vec![0usize; dims];
+
+                        for each_len in lens.iter_mut().rev() {
+                            *each_len = stack_pop!(usize);
+                        }
+
+                        // note: 外側の次元から順に確保して親の参照配列の要素に設定
+                        let (root_handle, root_ptr) = alloc_arr!(lens[0], if dims == 1 { elem_size } else { size_of::<usize>() });
+                        let mut parents = vec![root_ptr];
+                        let mut arr_count = 1usize;
+
+                        if dims > 1 {
+                            ref_arrays.insert(root_handle);
+                        }
+
+                        for dim_i in 1..dims {
+                            let is_leaf = dim_i == dims - 1;
+                            let mut children = Vec::<*mut c_void>::new();
+
+                            for each_parent in &parents {
+                                for parent_i in 0..lens[dim_i - 1] {
+                                    let (handle, arr_ptr) = alloc_arr!(lens[dim_i], if is_leaf { elem_size } else { size_of::<usize>() });
+                                    ((*each_parent as *mut usize).add(1 + parent_i)).write_unaligned(handle);
+                                    children.push(arr_ptr);
+
+                                    if !is_leaf {
+                                        ref_arrays.insert(handle);
+                                    }
+                                }
+                            }
+
+                            arr_count += children.len();
+                            parents = children;
+                        }
+
+                        stack_push!(usize, root_handle);
+
+                        trace!("{}", format!("[{} dimensions / {} byte element size / {} arrays]", dims, elem_size, arr_count).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::ArrayLength => {
+                        let elem_size = next_prg!(u8) as usize;
+                        let handle = stack_pop!(usize);
+                        let (_, elem_len) = arr_elem_size!(handle, elem_size);
+                        stack_push!(usize, elem_len);
+
+                        trace!("{}", format!("[{} elements / {} byte element size]", elem_len, elem_size).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::ArrayCopy => {
+                        let elem_size = next_prg!(u8) as usize;
+                        let len = stack_pop!(usize);
+                        let dest_i = stack_pop!(usize);
+                        let dest_handle = stack_pop!(usize);
+                        let src_i = stack_pop!(usize);
+                        let src_handle = stack_pop!(usize);
+                        let (src_ptr, src_elem_len) = arr_elem_size!(src_handle, elem_size);
+                        let (dest_ptr, dest_elem_len) = arr_elem_size!(dest_handle, elem_size);
+
+                        // note: 参照配列と他の配列の間ではコピーできない
+                        if ref_arrays.contains(&src_handle) != ref_arrays.contains(&dest_handle) {
+                            exit!(ArrayAccessViolation, FaultDetail::ArrayKind(dest_handle));
+                        }
+
+                        check_arr_range!(src_ptr, src_i, len, src_elem_len);
+                        check_arr_range!(dest_ptr, dest_i, len, dest_elem_len);
+
+                        // note: 同じ配列内で範囲が重なる場合も正しくコピーされるよう memmove 相当の copy を使用
+                        let src_top_ptr = ((src_ptr as *mut usize).add(1) as *mut u8).add(src_i * elem_size);
+                        let dest_top_ptr = ((dest_ptr as *mut usize).add(1) as *mut u8).add(dest_i * elem_size);
+                        copy(src_top_ptr, dest_top_ptr, len * elem_size);
+
+                        trace!("{}", format!("[copy {} elements from index {} to index {} / {} byte element size]", len, src_i, dest_i, elem_size).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::ArrayFill => {
+                        let elem_size = next_prg!(u8) as usize;
+                        // note: 要素のバイトサイズが 8 の場合のみ値は long となる
+                        let value = if elem_size == size_of::<u64>() { stack_pop!(u64) } else { stack_pop!(u32) as u64 };
+                        let len = stack_pop!(usize);
+                        let begin = stack_pop!(usize);
+                        let handle = stack_pop!(usize);
+                        let (arr_ptr, elem_len) = arr_elem_size!(handle, elem_size);
+
+                        if ref_arrays.contains(&handle) && value != 0 {
+                            check_arr!(value as usize);
+                        }
+
+                        check_arr_range!(arr_ptr, begin, len, elem_len);
+
+                        let arr_top_ptr = ((arr_ptr as *mut usize).add(1) as *mut u8).add(begin * elem_size);
+
+                        for elem_i in 0..len {
+                            let elem_ptr = arr_top_ptr.add(elem_i * elem_size);
+
+                            match elem_size {
+                                1 => *elem_ptr = value as u8,
+                                2 => (elem_ptr as *mut u16).write_unaligned(value as u16),
+                                4 => (elem_ptr as *mut u32).write_unaligned(value as u32),
+                                _ => (elem_ptr as *mut u64).write_unaligned(value),
+                            }
+                        }
+
+                        trace!("{}", format!("[fill {} elements from index {} with 0x{:0x} / {} byte element size]", len, begin, value, elem_size).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::ArrayResize => {
+                        let elem_size = next_prg!(u8) as usize;
+                        let new_len = stack_pop!(usize);
+                        let handle = stack_pop!(usize);
+                        let (arr_ptr, old_len) = arr_elem_size!(handle, elem_size);
+
+                        let new_size = match new_len.checked_mul(elem_size) {
+                            Some(v) => v,
+                            None => exit!(OutOfMemory, FaultDetail::Allocation { len: new_len, elem_size: elem_size }),
+                        };
+
+                        let (new_handle, _) = resize_arr!(handle, arr_ptr, new_size, FaultDetail::Allocation { len: new_len, elem_size: elem_size });
+                        stack_push!(usize, new_handle);
+
+                        trace!("{}", format!("[resize {} elements to {} elements / {} byte element size]", old_len, new_len, elem_size).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::BAAppend => {
+                        let src_handle = stack_pop!(usize);
+                        let dest_handle = stack_pop!(usize);
+                        let dest_ptr = check_arr!(dest_handle, false);
+                        let src_ptr = check_arr!(src_handle, false);
+
+                        let src_size = *(src_ptr as *mut usize);
+                        let dest_size = *(dest_ptr as *mut usize);
+
+                        let new_size = match dest_size.checked_add(src_size) {
+                            Some(v) => v,
+                            None => exit!(OutOfMemory, FaultDetail::Allocation { len: usize::MAX, elem_size: size_of::<u8>() }),
+                        };
+
+                        let (new_handle, new_ptr) = resize_arr!(dest_handle, dest_ptr, new_size, FaultDetail::Allocation { len: new_size, elem_size: size_of::<u8>() });
+                        let new_top_ptr = (new_ptr as *mut usize).add(1) as *mut u8;
+                        // note: 自身を追加する場合は解放済みの変更前の領域でなく複製済みの先頭から読み込む
+                        let src_top_ptr = if src_handle == dest_handle { new_top_ptr as *const u8 } else { (src_ptr as *mut usize).add(1) as *const u8 };
+                        copy_nonoverlapping(src_top_ptr, new_top_ptr.add(dest_size), src_size);
+                        stack_push!(usize, new_handle);
+
+                        trace!("{}", format!("[append {} bytes to {} bytes]", src_size, dest_size).bright_green().dimmed());
+                        trace!();
+                    },
                     Opcode::BPush => stack_push_next_prg!(u8 as u32, u32),
                     Opcode::SPush => stack_push_next_prg!(u16 as u32, u32),
                     Opcode::IPush => stack_push_next_prg!(u32, u32),
@@ -751,10 +1773,11 @@ impl Interpreter {
                         let var_i = next_prg!(u16);
                         load!(u64, var_i);
                     },
-                    Opcode::BALoad => load_arr!(u8),
-                    Opcode::SALoad => load_arr!(u16),
-                    Opcode::IALoad => load_arr!(u32),
-                    Opcode::LALoad => load_arr!(u64),
+                    Opcode::BALoad => load_arr!(u8, u32, false),
+                    Opcode::SALoad => load_arr!(u16, u32, false),
+                    Opcode::IALoad => load_arr!(u32, u32, false),
+                    Opcode::LALoad => load_arr!(u64, u64, false),
+                    Opcode::RALoad => load_arr!(usize, usize, true),
                     Opcode::Store => {
                         let var_i = next_prg!(u16);
                         let value = stack_pop!(u32);
@@ -765,12 +1788,18 @@ impl Interpreter {
                         let value = stack_pop!(u64);
                         store!(u64, var_i, value);
                     },
-                    Opcode::BAStore => store_arr!(u8, u32),
-                    Opcode::SAStore => store_arr!(u16, u32),
-                    Opcode::IAStore => store_arr!(u32, u32),
-                    Opcode::LAStore => store_arr!(u64, u64),
+                    Opcode::BAStore => store_arr!(u8, u32, false),
+                    Opcode::SAStore => store_arr!(u16, u32, false),
+                    Opcode::IAStore => store_arr!(u32, u32, false),
+                    Opcode::LAStore => store_arr!(u64, u64, false),
+                    Opcode::RAStore => store_arr!(usize, usize, true),
                     Opcode::Drop => {
-                        let ptr = stack_pop!(*mut c_void);
+                        let handle = stack_pop!(usize);
+                        let ptr = check_arr!(handle);
+                        heap.remove(&handle);
+                        objects.remove(&handle);
+                        ref_arrays.remove(&handle);
+                        heap_size -= HEAP_HEADER_SIZE + *(ptr as *mut usize);
                         free(ptr);
                     },
                     Opcode::IAdd => calc!(u32, overflowing_add),
@@ -821,30 +1850,267 @@ impl Interpreter {
                         let value1 = stack_pop!(u64);
                         stack_push!(u32, (value1 <= value2) as u32);
                     },
-                    Opcode::Goto => goto!(),
+                    Opcode::Goto => goto!(i16),
                     Opcode::If => {
                         let cond = stack_pop!(u32) != 0;
-                        goto_if!(cond);
+                        goto_if!(cond, i16);
                     },
                     Opcode::IfNot => {
                         let cond = stack_pop!(u32) == 0;
-                        goto_if!(cond);
+                        goto_if!(cond, i16);
+                    },
+                    Opcode::GotoW => goto!(i32),
+                    Opcode::IfW => {
+                        let cond = stack_pop!(u32) != 0;
+                        goto_if!(cond, i32);
+                    },
+                    Opcode::IfNotW => {
+                        let cond = stack_pop!(u32) == 0;
+                        goto_if!(cond, i32);
+                    },
+                    Opcode::TableSwitch | Opcode::LookupSwitch => {
+                        let key = stack_pop!(u32);
+                        let operand = from_raw_parts(inst_ptr as *const u8, bytecode_len - pc);
+                        let read_switch = if opcode_kind == Opcode::TableSwitch { SwitchTable::read_table } else { SwitchTable::read_lookup };
+
+                        let (operand_len, offset) = match read_switch(operand, key) {
+                            Some(v) => v,
+                            None => exit!(BytecodeAccessViolation, FaultDetail::BytecodeAddress(pc)),
+                        };
+
+                        let inst_i = (pc + operand_len) as isize + offset as isize;
+
+                        trace!("{}", format!("[key 0x{:0x} / goto 0x{:0x}]", key, inst_i).bright_green().dimmed());
+                        trace!();
+
+                        if 0 > inst_i {
+                            exit!(BytecodeAccessViolation, FaultDetail::JumpTarget(inst_i));
+                        }
+
+                        jump_prg_to!(inst_i as usize);
+                    },
+                    Opcode::Throw => {
+                        let code = stack_pop!(u32);
+
+                        let state = InterpreterState {
+                            pc: tmp_pc,
+                            sp: sp,
+                            bp: bp,
+                            opcode: opcode_kind,
+                            call_depth: call_depth,
+                            stack: from_raw_parts(stack_ptr.sub(sp) as *const u8, sp),
+                            bytecode: from_raw_parts(bytecode_ptr as *const u8, bytecode_len),
+                        };
+
+                        let funcs = pool_funcs.get_or_insert_with(|| Cow::Owned(Bytecode::read_pool_funcs(bytecode_bytes)));
+
+                        let target = match state.find_handler(funcs, code) {
+                            Some(v) => v,
+                            None => exit!(UncaughtException, FaultDetail::Exception(code)),
+                        };
+
+                        // note: ハンドラのフレームまで巻き戻して例外コードをプッシュ
+                        jump_stack_to!(target.sp);
+                        bp = target.bp;
+                        call_depth -= target.depth;
+                        var_lens.truncate(var_lens.len() - target.depth);
+                        stack_push!(u32, code);
+                        jump_prg_to!(target.handler_addr);
+
+                        trace!("{}", format!("[exception code 0x{:0x} / unwind {} frames / catch at 0x{:0x}]", code, target.depth, target.handler_addr).bright_green().dimmed());
+                        trace!();
                     },
-                    Opcode::Unknown => exit!(UnknownOpcode),
+                    Opcode::BAConst => {
+                        let const_i = next_prg!(u16) as usize;
+
+                        let value = match ConstPool::read_entry(bytecode_bytes, const_i) {
+                            Some((CONSTANT_BYTES, v)) => v,
+                            _ => exit!(BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+                        };
+
+                        // note: 定数は書き換えられないよう複製した配列をプッシュ
+                        let (handle, arr_ptr) = alloc_arr!(value.len(), size_of::<u8>());
+                        copy_nonoverlapping(value.as_ptr(), (arr_ptr as *mut usize).add(1) as *mut u8, value.len());
+                        stack_push!(usize, handle);
+
+                        trace!("{}", format!("[constant index 0x{:0x} / {} bytes]", const_i, value.len()).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::Ldc => {
+                        let const_i = next_prg!(u16) as usize;
+
+                        match ConstPool::read_entry(bytecode_bytes, const_i).and_then(|(kind, value)| Constant::from_entry(kind, value)) {
+                            Some(Constant::Int(v)) => stack_push!(u32, v),
+                            _ => exit!(BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+                        }
+                    },
+                    Opcode::Ldc2 => {
+                        let const_i = next_prg!(u16) as usize;
+
+                        match ConstPool::read_entry(bytecode_bytes, const_i).and_then(|(kind, value)| Constant::from_entry(kind, value)) {
+                            Some(Constant::Long(v)) => stack_push!(u64, v),
+                            _ => exit!(BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+                        }
+                    },
+                    Opcode::New => {
+                        let const_i = next_prg!(u16) as usize;
+
+                        if !layouts.contains_key(&const_i) {
+                            let fields = match ConstPool::read_entry(bytecode_bytes, const_i).and_then(|(kind, value)| Constant::from_entry(kind, value)) {
+                                Some(Constant::Struct(v)) => v,
+                                _ => exit!(BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+                            };
+
+                            layouts.insert(const_i, StructLayout::new(fields));
+                        }
+
+                        let field_len = layouts[&const_i].fields.len();
+                        let obj_len = layouts[&const_i].size;
+
+                        // note: オブジェクトはフィールドのデータ領域を 1 要素とする配列として確保
+                        let (handle, _) = alloc_arr!(1usize, obj_len);
+                        objects.insert(handle, const_i);
+                        stack_push!(usize, handle);
+
+                        trace!("{}", format!("[constant index 0x{:0x} / {} fields / {} bytes]", const_i, field_len, obj_len).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::GetField => {
+                        let field_i = next_prg!(u16) as usize;
+                        let handle = stack_pop!(usize);
+                        let (field_type, offset) = field!(handle, field_i);
+                        let field_ptr = (heap[&handle] as *mut usize).add(1) as *mut u8;
+
+                        let value = match field_type {
+                            FieldType::Int => {
+                                let value = (field_ptr.add(offset) as *mut u32).read_unaligned();
+                                stack_push!(u32, value);
+                                value as u64
+                            },
+                            FieldType::Long => {
+                                let value = (field_ptr.add(offset) as *mut u64).read_unaligned();
+                                stack_push!(u64, value);
+                                value
+                            },
+                            FieldType::Ref => {
+                                let value = (field_ptr.add(offset) as *mut usize).read_unaligned();
+                                stack_push!(usize, value);
+                                value as u64
+                            },
+                        };
+
+                        trace!("{}", format!("[field {} / value 0x{:0x}]", field_i, value).bright_green().dimmed());
+                        trace!();
+                    },
+                    // spec: 値の幅はフィールドの型で決まるためオブジェクトの参照を値より後にプッシュする
+                    Opcode::SetField => {
+                        let field_i = next_prg!(u16) as usize;
+                        let handle = stack_pop!(usize);
+                        let (field_type, offset) = field!(handle, field_i);
+                        let field_ptr = ((heap[&handle] as *mut usize).add(1) as *mut u8).add(offset);
+
+                        let value = match field_type {
+                            FieldType::Int => {
+                                let value = stack_pop!(u32);
+                                (field_ptr as *mut u32).write_unaligned(value);
+                                value as u64
+                            },
+                            FieldType::Long => {
+                                let value = stack_pop!(u64);
+                                (field_ptr as *mut u64).write_unaligned(value);
+                                value
+                            },
+                            FieldType::Ref => {
+                                let value = stack_pop!(usize);
+
+                                // note: 参照フィールドには null (0) もしくは確保済みの配列やオブジェクトのハンドルのみ格納できる (参照配列と同様)
+                                if value != 0 {
+                                    check_arr!(value);
+                                }
+
+                                (field_ptr as *mut usize).write_unaligned(value);
+                                value as u64
+                            },
+                        };
+
+                        trace!("{}", format!("[field {} / value 0x{:0x}]", field_i, value).bright_green().dimmed());
+                        trace!();
+                    },
+                    Opcode::Unknown => exit!(UnknownOpcode, FaultDetail::OpcodeByte(opcode)),
                 }
             }
         }
 
+        let fault = match inst_pc {
+            Some(v) if es != ExitStatus::Success as u32 => Some(Fault {
+                status: ExitStatus::from(es),
+                pc: v,
+                opcode: if v < bytecode_len { Opcode::from(*(bytecode_ptr.add(v) as *mut u8)) } else { Opcode::Unknown },
+                detail: fault_detail,
+            }),
+            _ => None,
+        };
+
+        let backtrace = match &fault {
+            Some(v) if !stack_ptr.is_null() => {
+                let funcs = pool_funcs.get_or_insert_with(|| Cow::Owned(Bytecode::read_pool_funcs(bytecode_bytes)));
+                let read_debug_info;
+
+                let debug_info = match program {
+                    Some(program) => program.debug_info(),
+                    None => {
+                        read_debug_info = DebugInfo::read(bytecode_bytes);
+                        read_debug_info.as_ref()
+                    },
+                };
+
+                BacktraceFrame::collect(&InterpreterState {
+                    pc: v.pc,
+                    sp: sp,
+                    bp: bp,
+                    opcode: v.opcode,
+                    call_depth: call_depth,
+                    stack: from_raw_parts(stack_ptr.sub(sp) as *const u8, sp),
+                    bytecode: from_raw_parts(bytecode_ptr as *const u8, bytecode_len),
+                }, funcs, debug_info)
+            },
+            _ => Vec::new(),
+        };
+
         let exit_status_msg = format!("exit status 0x{:0x} ({})", es, ExitStatus::from(es).to_string());
 
-        println!("{}", if es == 0 {
+        trace!("{}", if es == 0 {
             exit_status_msg.on_bright_black()
         } else {
             exit_status_msg.on_red()
         });
 
+        if let Some(v) = &fault {
+            trace!("{}", v.to_string().red());
+        }
+
+        for (i, each_frame) in backtrace.iter().enumerate() {
+            trace!("{}", format!("#{} {}", i, each_frame).red());
+        }
+
         free(stack_ptr.sub(sp));
 
-        return ExitStatus::from(es);
+        // note: 解放されなかった配列を解放
+        if heap.len() != 0 {
+            trace!("{}", format!("[free {} leaked arrays / {} bytes]", heap.len(), heap_size).bright_black());
+        }
+
+        for each_arr_ptr in heap.into_values() {
+            free(each_arr_ptr);
+        }
+
+        return RunResult {
+            exit_status: ExitStatus::from(es),
+            inst_count: inst_count,
+            output: output,
+            fault: fault,
+            backtrace: backtrace,
+            ret_value: ret_value,
+        };
     }
 }
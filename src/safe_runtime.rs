@@ -0,0 +1,1352 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::LowerHex;
+use std::io::{Read, Write};
+use std::mem::size_of;
+
+use crate::bytecode::*;
+use crate::program::*;
+use crate::runtime::*;
+use crate::text::*;
+
+use colored::*;
+
+const MAX_STACK_SIZE: usize = 1024;
+
+// note: Err((ExitStatus::Success, _)) は正常終了を表す
+type StepResult<T> = Result<T, (ExitStatus, FaultDetail)>;
+
+fn fail<T>(status: ExitStatus, detail: FaultDetail) -> StepResult<T> {
+    return Err((status, detail));
+}
+
+trait StackValue: Copy {
+    const SIZE: usize;
+
+    fn read_from(bytes: &[u8]) -> Self;
+    fn write_to(self, bytes: &mut [u8]);
+    fn to_u64(self) -> u64;
+}
+
+macro_rules! impl_stack_value {
+    ($($ty:ty),*) => {
+        $(
+            impl StackValue for $ty {
+                const SIZE: usize = size_of::<$ty>();
+
+                fn read_from(bytes: &[u8]) -> $ty {
+                    let mut buf = [0u8; size_of::<$ty>()];
+                    buf.copy_from_slice(&bytes[..size_of::<$ty>()]);
+                    return <$ty>::from_ne_bytes(buf);
+                }
+
+                fn write_to(self, bytes: &mut [u8]) {
+                    bytes[..size_of::<$ty>()].copy_from_slice(&self.to_ne_bytes());
+                }
+
+                fn to_u64(self) -> u64 {
+                    return self as u64;
+                }
+            }
+        )*
+    };
+}
+
+impl_stack_value!(u8, u16, u32, u64, usize, i16, i32);
+
+fn bytes_to_trace_string(bytes: &[u8]) -> String {
+    if bytes.len() == 0 {
+        return "<empty>".to_string();
+    }
+
+    return bytes.iter().enumerate().map(|(i, v)| {
+        let div = if i != 0 && i % 8 == 0 { "|\n" } else { "" };
+        format!("{}{:02x} ", div, v)
+    }).collect::<Vec<String>>().join("");
+}
+
+// note: Interpreter と同一の終了ステータスを返す境界チェック付きの実装
+pub struct SafeInterpreter<'a> {
+    config: &'a InterpreterConfig,
+    bytecode: &'a [u8],
+    stack: Vec<u8>,
+    // note: 配列ハンドルと要素のバイト列
+    heap: HashMap<usize, Vec<u8>>,
+    heap_size: usize,
+    next_handle: usize,
+    // note: new で確保した配列ハンドルと構造体の定数インデックス
+    objects: HashMap<usize, usize>,
+    // note: new で参照した構造体の定数インデックスとフィールドの配置 (定数ごとに最初の new で一度だけ求める)
+    layouts: HashMap<usize, StructLayout>,
+    // note: 要素が配列やオブジェクトのハンドルである配列 (rapush / mapush で確保したもの)
+    ref_arrays: HashSet<usize>,
+    // note: Stack Pointer
+    sp: usize,
+    // note: Base Pointer
+    bp: usize,
+    // note: Program Counter
+    pc: usize,
+    // note: Pool Pointer
+    pp: usize,
+    // note: Call Depth
+    call_depth: usize,
+    inst_count: usize,
+    output: Vec<u8>,
+    // note: 実行中の命令のアドレス
+    inst_pc: Option<usize>,
+    // note: エントリポイントの代わりに呼び出す関数のプールインデックスと引数
+    entry_call: Option<(usize, &'a [Value])>,
+    // note: 呼び出し中の関数ごとの変数長 (先頭がエントリポイント, 末尾が現在の関数)
+    // note: 先頭は呼び出す関数の変数長 (エントリポイントは変数テーブルを確保しない)
+    var_lens: Vec<usize>,
+    ret_value: Option<Value>,
+    // note: bytecode を読み込み済みのプログラム
+    program: Option<&'a Program>,
+    // note: 例外の送出時とバックトレースの生成時に参照するプール要素 (読み込み済みでなければ最初に必要になった時点で一度だけ読み込む)
+    funcs: Option<Cow<'a, [PoolFunc]>>,
+}
+
+impl<'a> SafeInterpreter<'a> {
+    pub fn launch(bytecode_bytes: Vec<u8>, config: &InterpreterConfig) -> RunResult {
+        return SafeInterpreter::launch_with_hook(bytecode_bytes, config, None);
+    }
+
+    pub fn launch_with_hook(bytecode_bytes: Vec<u8>, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        return SafeInterpreter::launch_entry(bytecode_bytes, config, hook, None);
+    }
+
+    // note: プールインデックスの関数を引数付きで呼び出し, 対応する ret までを実行
+    pub fn launch_call(bytecode_bytes: Vec<u8>, config: &InterpreterConfig, pool_i: usize, args: &[Value]) -> RunResult {
+        return SafeInterpreter::launch_entry(bytecode_bytes, config, None, Some((pool_i, args)));
+    }
+
+    // note: 読み込み済みのプログラムをエントリポイントから実行
+    pub fn launch_program(program: &Program, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>) -> RunResult {
+        if config.is_traced {
+            program.print();
+        }
+
+        return SafeInterpreter::execute(program.bytes(), Some(program), config, hook, None);
+    }
+
+    pub fn call_program(program: &Program, config: &InterpreterConfig, pool_i: usize, args: &[Value]) -> RunResult {
+        if config.is_traced {
+            program.print();
+        }
+
+        return SafeInterpreter::execute(program.bytes(), Some(program), config, None, Some((pool_i, args)));
+    }
+
+    fn launch_entry(bytecode_bytes: Vec<u8>, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>, entry_call: Option<(usize, &[Value])>) -> RunResult {
+        let bytecode = Bytecode::new(bytecode_bytes);
+
+        if let Some(result) = Interpreter::check_header(&bytecode, config) {
+            return result;
+        }
+
+        if config.is_traced {
+            bytecode.print();
+        }
+
+        return SafeInterpreter::execute(&bytecode.into_vec(), None, config, hook, entry_call);
+    }
+
+    fn execute(bytecode: &[u8], program: Option<&Program>, config: &InterpreterConfig, hook: Option<&mut dyn InterpreterHook>, entry_call: Option<(usize, &[Value])>) -> RunResult {
+        let mut interpreter = SafeInterpreter {
+            config: config,
+            bytecode: bytecode,
+            stack: vec![0u8; MAX_STACK_SIZE],
+            heap: HashMap::new(),
+            heap_size: 0,
+            next_handle: ARRAY_HANDLE_BASE,
+            objects: HashMap::new(),
+            layouts: HashMap::new(),
+            ref_arrays: HashSet::new(),
+            sp: 0,
+            bp: 0,
+            pc: 0,
+            pp: *HEADER_SIZE,
+            call_depth: 0,
+            inst_count: 0,
+            output: Vec::new(),
+            inst_pc: None,
+            entry_call: entry_call,
+            var_lens: vec![0],
+            ret_value: None,
+            program: program,
+            funcs: program.map(|v| Cow::Borrowed(v.funcs().as_slice())),
+        };
+
+        let (es, fault, backtrace) = interpreter.run(hook);
+
+        return RunResult {
+            exit_status: es,
+            inst_count: interpreter.inst_count,
+            output: interpreter.output,
+            fault: fault,
+            backtrace: backtrace,
+            ret_value: interpreter.ret_value,
+        };
+    }
+
+    fn trace(&self, msg: String) {
+        if self.config.is_traced {
+            println!("{}", msg);
+        }
+    }
+
+    fn run(&mut self, mut hook: Option<&mut dyn InterpreterHook>) -> (ExitStatus, Option<Fault>, Vec<BacktraceFrame>) {
+        let result = match self.init() {
+            Ok(()) => {
+                self.trace(format!("{}\n", "<INVOKE ENTRY POINT>".blue()));
+
+                loop {
+                    self.inst_pc = Some(self.pc);
+
+                    if let Some(hook) = &mut hook {
+                        if let HookAction::Abort = hook.on_inst(&self.state()) {
+                            break (ExitStatus::Aborted, FaultDetail::None);
+                        }
+                    }
+
+                    if let Err(e) = self.step() {
+                        break e;
+                    }
+                }
+            },
+            Err(e) => e,
+        };
+
+        let (es, fault_detail) = result;
+
+        let fault = match self.inst_pc {
+            Some(v) if es != ExitStatus::Success => Some(Fault {
+                status: es,
+                pc: v,
+                opcode: match self.bytecode.get(v) {
+                    Some(v) => Opcode::from(*v),
+                    None => Opcode::Unknown,
+                },
+                detail: fault_detail,
+            }),
+            _ => None,
+        };
+
+        let backtrace = match &fault {
+            Some(v) => {
+                self.load_funcs();
+                let read_debug_info;
+
+                let debug_info = match self.program {
+                    Some(program) => program.debug_info(),
+                    None => {
+                        read_debug_info = DebugInfo::read(self.bytecode);
+                        read_debug_info.as_ref()
+                    },
+                };
+
+                let mut state = self.state();
+                state.pc = v.pc;
+                state.opcode = v.opcode;
+                BacktraceFrame::collect(&state, self.funcs.as_deref().unwrap_or_default(), debug_info)
+            },
+            None => Vec::new(),
+        };
+
+        let exit_status_msg = format!("exit status 0x{:0x} ({})", es as u32, es);
+
+        self.trace(format!("{}", if es == ExitStatus::Success {
+            exit_status_msg.on_bright_black()
+        } else {
+            exit_status_msg.on_red()
+        }));
+
+        if let Some(v) = &fault {
+            self.trace(format!("{}", v.to_string().red()));
+        }
+
+        for (i, each_frame) in backtrace.iter().enumerate() {
+            self.trace(format!("{}", format!("#{} {}", i, each_frame).red()));
+        }
+
+        if self.heap.len() != 0 {
+            self.trace(format!("{}", format!("[free {} leaked arrays / {} bytes]", self.heap.len(), self.heap_size).bright_black()));
+        }
+
+        return (es, fault, backtrace);
+    }
+
+    fn load_funcs(&mut self) {
+        if self.funcs.is_none() {
+            self.funcs = Some(Cow::Owned(Bytecode::read_pool_funcs(self.bytecode)));
+        }
+    }
+
+    fn state(&self) -> InterpreterState<'_> {
+        return InterpreterState {
+            pc: self.pc,
+            sp: self.sp,
+            bp: self.bp,
+            opcode: match self.bytecode.get(self.pc) {
+                Some(v) => Opcode::from(*v),
+                None => Opcode::Unknown,
+            },
+            call_depth: self.call_depth,
+            stack: &self.stack[..self.sp],
+            bytecode: self.bytecode,
+        };
+    }
+
+    fn init(&mut self) -> StepResult<()> {
+        let bytecode_len = self.bytecode.len();
+        let pool_offset = *HEADER_SIZE;
+
+        // note: 範囲外の値を読み込まないようプール要素のアドレスと開始アドレスを順にチェック
+        let entry_pool_i = self.entry_call.map_or(0, |v| v.0);
+        let mut entry_point_pc = bytecode_len;
+        let mut entry_arg_len = 0usize;
+
+        let entry_index_addr = entry_pool_i.saturating_mul(size_of::<usize>()).saturating_add(pool_offset);
+
+        if entry_index_addr.saturating_add(size_of::<usize>()) <= bytecode_len {
+            let entry_point_pool_addr = usize::read_from(&self.bytecode[entry_index_addr..]);
+
+            if entry_point_pool_addr < bytecode_len && bytecode_len - entry_point_pool_addr >= size_of::<usize>() {
+                entry_point_pc = usize::read_from(&self.bytecode[entry_point_pool_addr..]);
+            }
+
+            if self.entry_call.is_some() && entry_point_pool_addr < bytecode_len && bytecode_len - entry_point_pool_addr >= size_of::<usize>() + size_of::<u16>() + size_of::<u8>() {
+                self.var_lens[0] = u16::read_from(&self.bytecode[entry_point_pool_addr + size_of::<usize>()..]) as usize;
+                entry_arg_len = self.bytecode[entry_point_pool_addr + size_of::<usize>() + size_of::<u16>()] as usize;
+            }
+        }
+
+        if entry_point_pc >= bytecode_len {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(entry_point_pc));
+        }
+
+        // note: 引数の要素数が呼び出す関数の引数長と一致しなければ終了
+        if let Some((pool_i, args)) = self.entry_call {
+            if self.var_lens[0] < entry_arg_len || args.iter().map(|v| v.slot_len()).sum::<usize>() != entry_arg_len {
+                return fail(ExitStatus::StackAccessViolation, FaultDetail::Invocation { pool_i: pool_i, var_len: self.var_lens[0], arg_len: entry_arg_len });
+            }
+        }
+
+        self.pc = entry_point_pc;
+
+        // note: エントリポイント用のコールスタック要素をプッシュ
+        // * ベースポインタ
+        self.push(0usize)?;
+        // * リターンアドレス
+        self.push(bytecode_len - 1)?;
+
+        if let Some((_, args)) = self.entry_call {
+            // note: invoke と同様に引数をプッシュして残りの変数の要素分をスキップ
+            for each_arg in args {
+                match each_arg {
+                    Value::Void => (),
+                    Value::Int(v) => self.push(*v)?,
+                    Value::Long(v) => self.push(*v)?,
+                }
+            }
+
+            self.jump_stack_to(self.sp + (self.var_lens[0] - entry_arg_len) * size_of::<u32>())?;
+        }
+
+        return Ok(());
+    }
+
+    fn next_prg<T: StackValue>(&mut self) -> StepResult<T> {
+        if self.pc + T::SIZE > self.bytecode.len() {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(self.pc));
+        }
+
+        let value = T::read_from(&self.bytecode[self.pc..]);
+        self.pc += T::SIZE;
+        return Ok(value);
+    }
+
+    fn next_pool<T: StackValue>(&mut self) -> StepResult<T> {
+        if self.pp + T::SIZE > self.bytecode.len() {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(self.pp));
+        }
+
+        let value = T::read_from(&self.bytecode[self.pp..]);
+        self.pp += T::SIZE;
+        return Ok(value);
+    }
+
+    fn jump_prg_to(&mut self, index: usize) -> StepResult<()> {
+        if index > self.bytecode.len() {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(index));
+        }
+
+        self.pc = index;
+        return Ok(());
+    }
+
+    fn jump_pool_to(&mut self, pool_index: usize) -> StepResult<()> {
+        let index_addr = pool_index.saturating_mul(size_of::<usize>()).saturating_add(*HEADER_SIZE);
+
+        if index_addr > self.bytecode.len() {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(index_addr));
+        }
+
+        self.pp = index_addr;
+        let value_addr = self.next_pool::<usize>()?;
+
+        if value_addr > self.bytecode.len() {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(value_addr));
+        }
+
+        self.pp = value_addr;
+        return Ok(());
+    }
+
+    fn jump_stack_to(&mut self, index: usize) -> StepResult<()> {
+        if index > MAX_STACK_SIZE {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: index.saturating_sub(self.sp) });
+        }
+
+        self.sp = index;
+        return Ok(());
+    }
+
+    fn push<T: StackValue>(&mut self, value: T) -> StepResult<()> {
+        if self.sp + T::SIZE > MAX_STACK_SIZE {
+            return fail(ExitStatus::StackOverflow, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: T::SIZE });
+        }
+
+        value.write_to(&mut self.stack[self.sp..]);
+        self.sp += T::SIZE;
+        return Ok(());
+    }
+
+    // spec: リターンアドレス以前の領域にアクセス可能
+    fn unsafe_pop<T: StackValue>(&mut self) -> StepResult<T> {
+        if self.sp < T::SIZE {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: T::SIZE });
+        }
+
+        self.sp -= T::SIZE;
+        return Ok(T::read_from(&self.stack[self.sp..]));
+    }
+
+    // note: リターンアドレス以前の値にアクセスしないようチェック
+    fn check_frame<T: StackValue>(&self) -> StepResult<()> {
+        if self.sp < self.bp + size_of::<usize>() * 2 + T::SIZE {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: T::SIZE });
+        }
+
+        return Ok(());
+    }
+
+    fn pop<T: StackValue>(&mut self) -> StepResult<T> {
+        self.check_frame::<T>()?;
+        return self.unsafe_pop::<T>();
+    }
+
+    fn top<T: StackValue>(&self) -> StepResult<T> {
+        self.check_frame::<T>()?;
+        return Ok(T::read_from(&self.stack[self.sp - T::SIZE..]));
+    }
+
+    // note: 変数のスタック上のインデックスを返す
+    fn var_index<T: StackValue>(&self, var_i: u16) -> StepResult<usize> {
+        if self.sp < self.bp + size_of::<usize>() * 2 {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: T::SIZE });
+        }
+
+        let diff = self.sp - self.bp - size_of::<usize>() * 2;
+
+        // note: スタックポインタ以降の値にアクセスしないようチェック
+        if diff < size_of::<u32>() * var_i as usize + T::SIZE {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: T::SIZE });
+        }
+
+        return Ok(self.sp - (diff - var_i as usize * size_of::<u32>()));
+    }
+
+    fn load<T: StackValue>(&mut self) -> StepResult<()> {
+        let var_i = self.next_prg::<u16>()?;
+        let index = self.var_index::<T>(var_i)?;
+        let value = T::read_from(&self.stack[index..]);
+        return self.push(value);
+    }
+
+    fn store<T: StackValue>(&mut self) -> StepResult<()> {
+        let var_i = self.next_prg::<u16>()?;
+        let value = self.pop::<T>()?;
+        let index = self.var_index::<T>(var_i)?;
+        value.write_to(&mut self.stack[index..]);
+        return Ok(());
+    }
+
+    // note: バイトサイズの領域を含めて指定バイト数をヒープに確保できるかどうか
+    fn fits_heap(&self, len: usize) -> bool {
+        return matches!(len.checked_add(HEAP_HEADER_SIZE), Some(v) if v <= self.config.max_heap_size - self.heap_size);
+    }
+
+    // note: ゼロ初期化した配列を確保してハンドルを返す
+    fn alloc_arr(&mut self, elem_len: usize, elem_size: usize) -> StepResult<usize> {
+        let arr_len = match elem_len.checked_mul(elem_size) {
+            Some(v) if self.fits_heap(v) => v,
+            _ => return fail(ExitStatus::OutOfMemory, FaultDetail::Allocation { len: elem_len, elem_size: elem_size }),
+        };
+
+        let handle = self.next_handle;
+        self.next_handle += 0x10;
+        self.heap.insert(handle, vec![0u8; arr_len]);
+        self.heap_size += HEAP_HEADER_SIZE + arr_len;
+        return Ok(handle);
+    }
+
+    fn push_arr(&mut self, elem_size: usize) -> StepResult<()> {
+        let elem_len = self.next_prg::<usize>()?;
+        let handle = self.alloc_arr(elem_len, elem_size)?;
+        return self.push(handle);
+    }
+
+    fn push_ref_arr(&mut self) -> StepResult<()> {
+        let elem_len = self.next_prg::<usize>()?;
+        let handle = self.alloc_arr(elem_len, size_of::<usize>())?;
+        self.ref_arrays.insert(handle);
+        return self.push(handle);
+    }
+
+    fn push_multi_arr(&mut self) -> StepResult<()> {
+        let shape = ArrayShape::from_operand(self.next_prg::<u16>()?);
+        let (dims, elem_size) = (shape.dims as usize, shape.elem_size as usize);
+
+        if !shape.is_valid() {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayShape { dims: dims, elem_size: elem_size });
+        }
+
+        // note: 外側の次元の要素数から順にプッシュされている
+        let mut lens = vec![0usize; dims];
+
+        for each_len in lens.iter_mut().rev() {
+            *each_len = self.pop::<usize>()?;
+        }
+
+        // note: 外側の次元から順に確保して親の参照配列の要素に設定
+        let root_handle = self.alloc_arr(lens[0], if dims == 1 { elem_size } else { size_of::<usize>() })?;
+        let mut parents = vec![root_handle];
+        let mut arr_count = 1usize;
+
+        if dims > 1 {
+            self.ref_arrays.insert(root_handle);
+        }
+
+        for dim_i in 1..dims {
+            let is_leaf = dim_i == dims - 1;
+            let mut children = Vec::<usize>::new();
+
+            for each_parent in &parents {
+                for parent_i in 0..lens[dim_i - 1] {
+                    let handle = self.alloc_arr(lens[dim_i], if is_leaf { elem_size } else { size_of::<usize>() })?;
+                    handle.write_to(&mut self.heap.get_mut(each_parent).unwrap()[parent_i * size_of::<usize>()..]);
+                    children.push(handle);
+                }
+            }
+
+            if !is_leaf {
+                self.ref_arrays.extend(children.iter().cloned());
+            }
+
+            arr_count += children.len();
+            parents = children;
+        }
+
+        self.push(root_handle)?;
+
+        self.trace(format!("{}\n", format!("[{} dimensions / {} byte element size / {} arrays]", dims, elem_size, arr_count).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    // note: 定数は書き換えられないよう複製した配列をプッシュ
+    fn push_const_arr(&mut self) -> StepResult<()> {
+        let const_i = self.next_prg::<u16>()? as usize;
+
+        let value = match ConstPool::read_entry(self.bytecode, const_i) {
+            Some((CONSTANT_BYTES, v)) => v,
+            _ => return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+        };
+
+        let handle = self.alloc_arr(value.len(), size_of::<u8>())?;
+        self.heap.get_mut(&handle).unwrap().copy_from_slice(value);
+        self.push(handle)?;
+
+        self.trace(format!("{}\n", format!("[constant index 0x{:0x} / {} bytes]", const_i, value.len()).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn read_const(&mut self) -> StepResult<(usize, Option<Constant>)> {
+        let const_i = self.next_prg::<u16>()? as usize;
+        let constant = ConstPool::read_entry(self.bytecode, const_i).and_then(|(kind, value)| Constant::from_entry(kind, value));
+        return Ok((const_i, constant));
+    }
+
+    // note: 確保済みの配列でなければ終了
+    fn check_arr(&self, handle: usize) -> StepResult<()> {
+        return if self.heap.contains_key(&handle) {
+            Ok(())
+        } else {
+            fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayPointer(handle))
+        };
+    }
+
+    // note: 参照配列の要素は参照配列用の命令, それ以外の配列の要素はそれ以外の命令でのみアクセスできる (オブジェクトはいずれも不可)
+    fn check_arr_kind(&self, handle: usize, is_ref: bool) -> StepResult<()> {
+        self.check_arr(handle)?;
+
+        return if self.objects.contains_key(&handle) || self.ref_arrays.contains(&handle) != is_ref {
+            fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayKind(handle))
+        } else {
+            Ok(())
+        };
+    }
+
+    // note: arraylength / arraycopy / arrayfill / arrayresize のオペランドである要素のバイトサイズと対象の配列を検証して要素数を返す
+    // spec: 参照配列の要素のバイトサイズは 8 (usize) のみ
+    fn arr_elem_len(&self, handle: usize, elem_size: usize) -> StepResult<usize> {
+        self.check_arr(handle)?;
+
+        if self.objects.contains_key(&handle) {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayKind(handle));
+        }
+
+        if ![1, 2, 4, 8].contains(&elem_size) || (self.ref_arrays.contains(&handle) && elem_size != size_of::<usize>()) {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayShape { dims: 1, elem_size: elem_size });
+        }
+
+        return Ok(self.heap[&handle].len() / elem_size);
+    }
+
+    // note: 要素数 len の範囲が配列内になければ終了
+    fn check_arr_range(&self, handle: usize, begin: usize, len: usize, elem_len: usize) -> StepResult<()> {
+        return match begin.checked_add(len) {
+            Some(v) if v <= elem_len => Ok(()),
+            _ => fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayIndex { index: begin.saturating_add(len), size: self.heap[&handle].len() }),
+        };
+    }
+
+    fn arr_length(&mut self) -> StepResult<()> {
+        let elem_size = self.next_prg::<u8>()? as usize;
+        let handle = self.pop::<usize>()?;
+        let elem_len = self.arr_elem_len(handle, elem_size)?;
+        self.push(elem_len)?;
+
+        self.trace(format!("{}\n", format!("[{} elements / {} byte element size]", elem_len, elem_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn arr_copy(&mut self) -> StepResult<()> {
+        let elem_size = self.next_prg::<u8>()? as usize;
+        let len = self.pop::<usize>()?;
+        let dest_i = self.pop::<usize>()?;
+        let dest_handle = self.pop::<usize>()?;
+        let src_i = self.pop::<usize>()?;
+        let src_handle = self.pop::<usize>()?;
+        let src_elem_len = self.arr_elem_len(src_handle, elem_size)?;
+        let dest_elem_len = self.arr_elem_len(dest_handle, elem_size)?;
+
+        // note: 参照配列と他の配列の間ではコピーできない
+        if self.ref_arrays.contains(&src_handle) != self.ref_arrays.contains(&dest_handle) {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayKind(dest_handle));
+        }
+
+        self.check_arr_range(src_handle, src_i, len, src_elem_len)?;
+        self.check_arr_range(dest_handle, dest_i, len, dest_elem_len)?;
+
+        let (src_begin, dest_begin, size) = (src_i * elem_size, dest_i * elem_size, len * elem_size);
+
+        // note: 同じ配列内で範囲が重なる場合も正しくコピーされるよう copy_within を使用
+        if src_handle == dest_handle {
+            self.heap.get_mut(&src_handle).unwrap().copy_within(src_begin..src_begin + size, dest_begin);
+        } else {
+            let src = self.heap[&src_handle][src_begin..src_begin + size].to_vec();
+            self.heap.get_mut(&dest_handle).unwrap()[dest_begin..dest_begin + size].copy_from_slice(&src);
+        }
+
+        self.trace(format!("{}\n", format!("[copy {} elements from index {} to index {} / {} byte element size]", len, src_i, dest_i, elem_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn arr_fill(&mut self) -> StepResult<()> {
+        let elem_size = self.next_prg::<u8>()? as usize;
+        // note: 要素のバイトサイズが 8 の場合のみ値は long となる
+        let value = if elem_size == size_of::<u64>() { self.pop::<u64>()? } else { self.pop::<u32>()? as u64 };
+        let len = self.pop::<usize>()?;
+        let begin = self.pop::<usize>()?;
+        let handle = self.pop::<usize>()?;
+        let elem_len = self.arr_elem_len(handle, elem_size)?;
+
+        if self.ref_arrays.contains(&handle) && value != 0 {
+            self.check_arr(value as usize)?;
+        }
+
+        self.check_arr_range(handle, begin, len, elem_len)?;
+
+        let arr = self.heap.get_mut(&handle).unwrap();
+
+        for each_elem in arr[begin * elem_size..(begin + len) * elem_size].chunks_mut(elem_size) {
+            match elem_size {
+                1 => (value as u8).write_to(each_elem),
+                2 => (value as u16).write_to(each_elem),
+                4 => (value as u32).write_to(each_elem),
+                _ => value.write_to(each_elem),
+            }
+        }
+
+        self.trace(format!("{}\n", format!("[fill {} elements from index {} with 0x{:0x} / {} byte element size]", len, begin, value, elem_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    // note: 内容を保持したまま配列のバイトサイズを変更して新しいハンドルを返す (増えた領域はゼロ初期化)
+    // spec: Interpreter と同様に変更前のハンドルは解放されるため以降はアクセスできない
+    fn resize_arr(&mut self, handle: usize, new_size: usize, detail: FaultDetail) -> StepResult<usize> {
+        let old_size = self.heap[&handle].len();
+
+        if new_size > old_size && new_size - old_size > self.config.max_heap_size - self.heap_size {
+            return fail(ExitStatus::OutOfMemory, detail);
+        }
+
+        let mut arr = self.heap.remove(&handle).unwrap();
+        arr.resize(new_size, 0);
+
+        let new_handle = self.next_handle;
+        self.next_handle += 0x10;
+        self.heap.insert(new_handle, arr);
+
+        if self.ref_arrays.remove(&handle) {
+            self.ref_arrays.insert(new_handle);
+        }
+
+        self.heap_size = self.heap_size - old_size + new_size;
+        return Ok(new_handle);
+    }
+
+    fn arr_resize(&mut self) -> StepResult<()> {
+        let elem_size = self.next_prg::<u8>()? as usize;
+        let new_len = self.pop::<usize>()?;
+        let handle = self.pop::<usize>()?;
+        let old_len = self.arr_elem_len(handle, elem_size)?;
+        let detail = FaultDetail::Allocation { len: new_len, elem_size: elem_size };
+
+        let new_size = match new_len.checked_mul(elem_size) {
+            Some(v) => v,
+            None => return fail(ExitStatus::OutOfMemory, detail),
+        };
+
+        let new_handle = self.resize_arr(handle, new_size, detail)?;
+        self.push(new_handle)?;
+
+        self.trace(format!("{}\n", format!("[resize {} elements to {} elements / {} byte element size]", old_len, new_len, elem_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn append_arr(&mut self) -> StepResult<()> {
+        let src_handle = self.pop::<usize>()?;
+        let dest_handle = self.pop::<usize>()?;
+        self.check_arr_kind(dest_handle, false)?;
+        self.check_arr_kind(src_handle, false)?;
+
+        // note: 自身を追加する場合にも備えて変更前に追加するバイト列を複製
+        let src = self.heap[&src_handle].clone();
+        let dest_size = self.heap[&dest_handle].len();
+
+        let new_size = match dest_size.checked_add(src.len()) {
+            Some(v) => v,
+            None => return fail(ExitStatus::OutOfMemory, FaultDetail::Allocation { len: usize::MAX, elem_size: size_of::<u8>() }),
+        };
+
+        let new_handle = self.resize_arr(dest_handle, new_size, FaultDetail::Allocation { len: new_size, elem_size: size_of::<u8>() })?;
+        self.heap.get_mut(&new_handle).unwrap()[dest_size..].copy_from_slice(&src);
+        self.push(new_handle)?;
+
+        self.trace(format!("{}\n", format!("[append {} bytes to {} bytes]", src.len(), dest_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn load_arr<T: StackValue>(&mut self, into_push_value: fn(T) -> u64, is_wide: bool, is_ref: bool) -> StepResult<()> {
+        let arr_i = self.pop::<usize>()?;
+        let handle = self.pop::<usize>()?;
+        self.check_arr_kind(handle, is_ref)?;
+        let arr = &self.heap[&handle];
+        let arr_size = arr.len();
+
+        if arr_i >= arr_size / T::SIZE {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayIndex { index: arr_i, size: arr_size });
+        }
+
+        let value = into_push_value(T::read_from(&arr[arr_i * T::SIZE..]));
+
+        if is_wide {
+            self.push(value)?;
+        } else {
+            self.push(value as u32)?;
+        }
+
+        self.trace(format!("{}\n", format!("[index {} / {} byte size / value 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn store_arr<T: StackValue + LowerHex>(&mut self, from_pop_value: fn(u64) -> T, is_wide: bool, is_ref: bool) -> StepResult<()> {
+        // fix: キャストでのオーバーフロー対処 (現在は数値が丸められてる)
+        let popped_value = if is_wide { self.pop::<u64>()? } else { self.pop::<u32>()? as u64 };
+        let value = from_pop_value(popped_value);
+        let arr_i = self.pop::<usize>()?;
+        let handle = self.pop::<usize>()?;
+        self.check_arr_kind(handle, is_ref)?;
+
+        // note: 参照配列には null (0) もしくは確保済みの配列のハンドルのみ格納できる
+        if is_ref && popped_value != 0 {
+            self.check_arr(popped_value as usize)?;
+        }
+
+        let arr = self.heap.get_mut(&handle).unwrap();
+        let arr_size = arr.len();
+
+        if arr_i >= arr_size / T::SIZE {
+            return fail(ExitStatus::ArrayAccessViolation, FaultDetail::ArrayIndex { index: arr_i, size: arr_size });
+        }
+
+        value.write_to(&mut arr[arr_i * T::SIZE..]);
+
+        self.trace(format!("{}\n", format!("[index {} / {} byte size / change value to 0x{:0x}]", arr_i, arr_size, value).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn calc<T: StackValue>(&mut self, f: fn(T, T) -> (T, bool), is_zero: Option<fn(T) -> bool>) -> StepResult<()> {
+        let right_term = self.pop::<T>()?;
+        let left_term = self.pop::<T>()?;
+
+        if let Some(is_zero) = is_zero {
+            if is_zero(right_term) {
+                return fail(ExitStatus::DivideByZero, FaultDetail::Operands { left: left_term.to_u64(), right: right_term.to_u64() });
+            }
+        }
+
+        let (value, overflowing) = f(left_term, right_term);
+
+        if overflowing {
+            return fail(ExitStatus::ArithmeticOverflow, FaultDetail::Operands { left: left_term.to_u64(), right: right_term.to_u64() });
+        }
+
+        return self.push(value);
+    }
+
+    fn compare<T: StackValue>(&mut self, f: fn(T, T) -> bool) -> StepResult<()> {
+        let value2 = self.pop::<T>()?;
+        let value1 = self.pop::<T>()?;
+        return self.push(f(value1, value2) as u32);
+    }
+
+    // note: オフセットは goto / if / ifnot では i16, goto_w / if_w / ifnot_w では i32
+    fn goto(&mut self, is_wide: bool) -> StepResult<()> {
+        let offset = if is_wide { self.next_prg::<i32>()? as isize } else { self.next_prg::<i16>()? as isize };
+        let inst_i = self.pc as isize + offset;
+
+        self.trace(format!("{}\n", format!("[goto 0x{:0x}]", inst_i).bright_green().dimmed()));
+
+        if 0 > inst_i {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::JumpTarget(inst_i));
+        }
+
+        return self.jump_prg_to(inst_i as usize);
+    }
+
+    fn goto_if(&mut self, cond: bool, is_wide: bool) -> StepResult<()> {
+        let jump_txt = if cond { format!("jump to 0x{:0x}", self.pc) } else { "no jump".to_string() };
+        self.trace(format!("{}\n", format!("[{}]", jump_txt).bright_green().dimmed()));
+
+        if cond {
+            self.goto(is_wide)?;
+        } else if is_wide {
+            self.next_prg::<i32>()?;
+        } else {
+            self.next_prg::<i16>()?;
+        }
+
+        return Ok(());
+    }
+
+    fn switch(&mut self, opcode: Opcode) -> StepResult<()> {
+        let key = self.pop::<u32>()?;
+        let read_switch = if opcode == Opcode::TableSwitch { SwitchTable::read_table } else { SwitchTable::read_lookup };
+
+        let (operand_len, offset) = match read_switch(&self.bytecode[self.pc..], key) {
+            Some(v) => v,
+            None => return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::BytecodeAddress(self.pc)),
+        };
+
+        let inst_i = (self.pc + operand_len) as isize + offset as isize;
+
+        self.trace(format!("{}\n", format!("[key 0x{:0x} / goto 0x{:0x}]", key, inst_i).bright_green().dimmed()));
+
+        if 0 > inst_i {
+            return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::JumpTarget(inst_i));
+        }
+
+        return self.jump_prg_to(inst_i as usize);
+    }
+
+    fn invoke(&mut self) -> StepResult<()> {
+        let pool_i = self.next_prg::<usize>()?;
+        self.jump_pool_to(pool_i)?;
+        let start_addr = self.next_pool::<usize>()?;
+        let var_len = self.next_pool::<u16>()? as usize;
+        let arg_len = self.next_pool::<u8>()? as usize;
+
+        if var_len < arg_len || self.sp < arg_len * size_of::<u32>() {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::Invocation { pool_i: pool_i, var_len: var_len, arg_len: arg_len });
+        }
+
+        // note: 引数値を事前にポップ
+        let args_begin = self.sp - arg_len * size_of::<u32>();
+        let args = self.stack[args_begin..self.sp].to_vec();
+
+        for _ in 0..arg_len {
+            self.pop::<u32>()?;
+        }
+
+        // note: bp をプッシュ & 設定
+        let new_bp = self.sp;
+        self.push(self.bp)?;
+        self.bp = new_bp;
+
+        // note: リターンアドレスをプッシュ
+        let ret_addr = self.pc;
+        self.push(ret_addr)?;
+
+        // note: 引数をプッシュ
+        for each_arg in args.chunks(size_of::<u32>()) {
+            self.push(u32::read_from(each_arg))?;
+        }
+
+        // note: 引数の要素分 (self 参照含む) をスキップ
+        self.jump_stack_to(self.sp + (var_len - arg_len) * size_of::<u32>())?;
+
+        // note: 開始アドレスにジャンプ
+        self.jump_prg_to(start_addr)?;
+        self.call_depth += 1;
+        self.var_lens.push(var_len);
+
+        self.trace(format!("{}\n", format!("[pool index 0x{:0x} / start at 0x{:0x} / return to 0x{:0x} / {} arguments]", pool_i, start_addr, ret_addr, arg_len).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn tail_invoke(&mut self) -> StepResult<()> {
+        let pool_i = self.next_prg::<usize>()?;
+        self.jump_pool_to(pool_i)?;
+        let start_addr = self.next_pool::<usize>()?;
+        let var_len = self.next_pool::<u16>()? as usize;
+        let arg_len = self.next_pool::<u8>()? as usize;
+        let frame_header_size = size_of::<usize>() * 2;
+
+        if self.sp < self.bp || self.sp - self.bp < frame_header_size {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: frame_header_size });
+        }
+
+        // note: 引数は現在の関数の変数テーブルより上にあるオペランドスタックの値のみ
+        let cur_var_len = self.var_lens[self.var_lens.len() - 1];
+
+        if var_len < arg_len || self.sp - self.bp - frame_header_size < (cur_var_len + arg_len) * size_of::<u32>() {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::Invocation { pool_i: pool_i, var_len: var_len, arg_len: arg_len });
+        }
+
+        let args_begin = self.sp - arg_len * size_of::<u32>();
+        let args = self.stack[args_begin..self.sp].to_vec();
+
+        // note: 保存済みの bp とリターンアドレスを残して現在のフレームを破棄
+        let pop_size = self.sp - self.bp - frame_header_size;
+        self.sp = self.bp + frame_header_size;
+
+        for each_arg in args.chunks(size_of::<u32>()) {
+            self.push(u32::read_from(each_arg))?;
+        }
+
+        self.jump_stack_to(self.sp + (var_len - arg_len) * size_of::<u32>())?;
+        self.jump_prg_to(start_addr)?;
+
+        // spec: フレームを再利用するため呼び出しの深さは変わらず, 呼び出された関数の ret で元の呼び出し元に戻る
+        let var_len_i = self.var_lens.len() - 1;
+        self.var_lens[var_len_i] = var_len;
+
+        self.trace(format!("{}\n", format!("[pool index 0x{:0x} / start at 0x{:0x} / reuse frame at 0x{:0x} / pop {} bytes / {} arguments]", pool_i, start_addr, self.bp, pop_size, arg_len).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn ret(&mut self) -> StepResult<()> {
+        if self.sp < self.bp || self.sp - self.bp < size_of::<usize>() * 2 {
+            return fail(ExitStatus::StackAccessViolation, FaultDetail::StackAccess { sp: self.sp, bp: self.bp, size: size_of::<usize>() * 2 });
+        }
+
+        // spec: 呼び出された関数の ret ではオペランドスタックに残った値を戻り値として終了 (0 / 4 / 8 バイトで void / int / long)
+        if self.entry_call.is_some() && self.call_depth == 0 {
+            let operand_size = self.sp as isize - (self.bp + size_of::<usize>() * 2 + self.var_lens[0] * size_of::<u32>()) as isize;
+
+            let value = match operand_size {
+                0 => Value::Void,
+                4 => Value::Int(u32::read_from(&self.stack[self.sp - 4..])),
+                8 => Value::Long(u64::read_from(&self.stack[self.sp - 8..])),
+                _ => return fail(ExitStatus::StackAccessViolation, FaultDetail::ReturnValue(operand_size)),
+            };
+
+            self.ret_value = Some(value);
+            self.trace(format!("{}\n", format!("[return {}]", value).bright_green().dimmed()));
+            return fail(ExitStatus::Success, FaultDetail::None);
+        }
+
+        // note: オペランドスタックと変数テーブルをポップ
+        let pop_size = self.sp - self.bp - size_of::<usize>() * 2;
+        self.sp -= pop_size;
+
+        // note: pc 設定
+        let ret_addr = self.unsafe_pop::<usize>()?;
+        self.jump_prg_to(ret_addr)?;
+
+        // note: bp 設定
+        self.bp = self.unsafe_pop::<usize>()?;
+        self.call_depth = self.call_depth.saturating_sub(1);
+
+        if self.var_lens.len() > 1 {
+            self.var_lens.pop();
+        }
+
+        self.trace(format!("{}\n", format!("[return to 0x{:0x} / pop {} bytes / return void]", ret_addr, pop_size).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn call(&mut self) -> StepResult<()> {
+        let code = self.next_prg::<u8>()?;
+
+        match code {
+            0x00 => {
+                let mut buf = [0u8; 4];
+                let size = match std::io::stdin().read(&mut buf) {
+                    Ok(v) => v as isize,
+                    Err(_) => -1,
+                };
+
+                self.trace(format!("{} {}", size, bytes_to_trace_string(&buf)));
+            },
+            0x01 => {
+                let handle = self.pop::<usize>()?;
+                self.check_arr_kind(handle, false)?;
+                let arr = &self.heap[&handle];
+
+                self.trace(format!("{}", "[console output]".bright_black()));
+                self.trace(format!("{}", bytes_to_trace_string(arr).bright_black()));
+
+                if self.config.is_output_captured {
+                    self.output.extend_from_slice(arr);
+                } else {
+                    let mut stdout = std::io::stdout();
+                    let _ = stdout.write_all(arr);
+                    let _ = stdout.flush();
+                }
+
+                self.trace(String::new());
+            },
+            _ => match TextCall::from_code(code) {
+                Some(v) => self.call_text(v)?,
+                None => return fail(ExitStatus::UnknownCallNumber, FaultDetail::CallNumber(code)),
+            },
+        }
+
+        return Ok(());
+    }
+
+    fn call_text(&mut self, text_call: TextCall) -> StepResult<()> {
+        let mut args = Vec::<TextValue>::new();
+
+        for each_kind in text_call.arg_kinds().iter().rev() {
+            let arg = match each_kind {
+                TextArgKind::Array => {
+                    let handle = self.pop::<usize>()?;
+                    self.check_arr_kind(handle, false)?;
+                    TextValue::Array(self.heap[&handle].clone())
+                },
+                TextArgKind::Int => TextValue::Int(self.pop::<u32>()?),
+                TextArgKind::Long => TextValue::Long(self.pop::<u64>()?),
+                TextArgKind::Usize => TextValue::Usize(self.pop::<usize>()?),
+            };
+
+            args.push(arg);
+        }
+
+        args.reverse();
+
+        for each_value in text_call.apply(&args).map_err(|e| e.to_fault())? {
+            match each_value {
+                TextValue::Array(v) => {
+                    let handle = self.alloc_arr(v.len(), size_of::<u8>())?;
+                    self.heap.get_mut(&handle).unwrap().copy_from_slice(&v);
+                    self.push(handle)?;
+                },
+                TextValue::Int(v) => self.push(v)?,
+                TextValue::Long(v) => self.push(v)?,
+                TextValue::Usize(v) => self.push(v)?,
+            }
+        }
+
+        self.trace(format!("{}\n", format!("[{:?}]", text_call).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn drop_arr(&mut self) -> StepResult<()> {
+        let handle = self.pop::<usize>()?;
+        self.check_arr(handle)?;
+        let arr = self.heap.remove(&handle).unwrap();
+        self.objects.remove(&handle);
+        self.ref_arrays.remove(&handle);
+        self.heap_size -= HEAP_HEADER_SIZE + arr.len();
+        return Ok(());
+    }
+
+    fn new_obj(&mut self) -> StepResult<()> {
+        let const_i = self.next_prg::<u16>()? as usize;
+
+        if !self.layouts.contains_key(&const_i) {
+            let fields = match ConstPool::read_entry(self.bytecode, const_i).and_then(|(kind, value)| Constant::from_entry(kind, value)) {
+                Some(Constant::Struct(v)) => v,
+                _ => return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+            };
+
+            self.layouts.insert(const_i, StructLayout::new(fields));
+        }
+
+        let field_len = self.layouts[&const_i].fields.len();
+        let obj_len = self.layouts[&const_i].size;
+
+        // note: オブジェクトはフィールドのデータ領域を 1 要素とする配列として確保
+        let handle = self.alloc_arr(1, obj_len)?;
+        self.objects.insert(handle, const_i);
+        self.push(handle)?;
+
+        self.trace(format!("{}\n", format!("[constant index 0x{:0x} / {} fields / {} bytes]", const_i, field_len, obj_len).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    // note: オブジェクトのフィールドの型とデータ領域内のオフセット
+    fn field(&self, handle: usize, field_i: usize) -> StepResult<(FieldType, usize)> {
+        let layout = match self.objects.get(&handle).and_then(|v| self.layouts.get(v)) {
+            Some(v) => v,
+            None => return fail(ExitStatus::ObjectAccessViolation, FaultDetail::ObjectPointer(handle)),
+        };
+
+        return match layout.field(field_i) {
+            Some(v) => Ok(v),
+            None => fail(ExitStatus::ObjectAccessViolation, FaultDetail::Field { field_i: field_i, field_len: layout.fields.len() }),
+        };
+    }
+
+    fn get_field(&mut self) -> StepResult<()> {
+        let field_i = self.next_prg::<u16>()? as usize;
+        let handle = self.pop::<usize>()?;
+        let (field_type, offset) = self.field(handle, field_i)?;
+        let obj = &self.heap[&handle][offset..];
+
+        let value = match field_type {
+            FieldType::Int => u32::read_from(obj) as u64,
+            FieldType::Long => u64::read_from(obj),
+            FieldType::Ref => usize::read_from(obj) as u64,
+        };
+
+        match field_type {
+            FieldType::Int => self.push(value as u32)?,
+            FieldType::Long => self.push(value)?,
+            FieldType::Ref => self.push(value as usize)?,
+        }
+
+        self.trace(format!("{}\n", format!("[field {} / value 0x{:0x}]", field_i, value).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    // spec: 値の幅はフィールドの型で決まるためオブジェクトの参照を値より後にプッシュする
+    fn set_field(&mut self) -> StepResult<()> {
+        let field_i = self.next_prg::<u16>()? as usize;
+        let handle = self.pop::<usize>()?;
+        let (field_type, offset) = self.field(handle, field_i)?;
+
+        let value = match field_type {
+            FieldType::Int => {
+                let value = self.pop::<u32>()?;
+                value.write_to(&mut self.heap.get_mut(&handle).unwrap()[offset..]);
+                value as u64
+            },
+            FieldType::Long => {
+                let value = self.pop::<u64>()?;
+                value.write_to(&mut self.heap.get_mut(&handle).unwrap()[offset..]);
+                value
+            },
+            FieldType::Ref => {
+                let value = self.pop::<usize>()?;
+
+                // note: 参照フィールドには null (0) もしくは確保済みの配列やオブジェクトのハンドルのみ格納できる (参照配列と同様)
+                if value != 0 {
+                    self.check_arr(value)?;
+                }
+
+                value.write_to(&mut self.heap.get_mut(&handle).unwrap()[offset..]);
+                value as u64
+            },
+        };
+
+        self.trace(format!("{}\n", format!("[field {} / value 0x{:0x}]", field_i, value).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn throw(&mut self, inst_pc: usize) -> StepResult<()> {
+        let code = self.pop::<u32>()?;
+
+        self.load_funcs();
+
+        let mut state = self.state();
+        state.pc = inst_pc;
+
+        let target = match state.find_handler(self.funcs.as_deref().unwrap_or_default(), code) {
+            Some(v) => v,
+            None => return fail(ExitStatus::UncaughtException, FaultDetail::Exception(code)),
+        };
+
+        // note: ハンドラのフレームまで巻き戻して例外コードをプッシュ
+        self.jump_stack_to(target.sp)?;
+        self.bp = target.bp;
+        self.call_depth -= target.depth;
+        self.var_lens.truncate(self.var_lens.len() - target.depth);
+        self.push(code)?;
+        self.jump_prg_to(target.handler_addr)?;
+
+        self.trace(format!("{}\n", format!("[exception code 0x{:0x} / unwind {} frames / catch at 0x{:0x}]", code, target.depth, target.handler_addr).bright_green().dimmed()));
+        return Ok(());
+    }
+
+    fn step(&mut self) -> StepResult<()> {
+        let tmp_pc = self.pc;
+        let opcode = self.next_prg::<u8>()?;
+        let opcode_kind = Opcode::from(opcode);
+        self.inst_count += 1;
+
+        self.trace(format!("{}", format!("{} (0x{:0x} at 0x{:0x})", opcode_kind.to_string().to_uppercase(), opcode, tmp_pc).blue()));
+        self.trace(format!("{}\n", bytes_to_trace_string(&self.stack[..self.sp]).bright_black()));
+
+        match opcode_kind {
+            Opcode::Nop => (),
+            Opcode::Exit => return fail(ExitStatus::Success, FaultDetail::None),
+            Opcode::Call => self.call()?,
+            Opcode::Invoke => self.invoke()?,
+            Opcode::TailInvoke => self.tail_invoke()?,
+            Opcode::Ret => self.ret()?,
+            Opcode::BAPush => self.push_arr(size_of::<u8>())?,
+            Opcode::SAPush => self.push_arr(size_of::<u16>())?,
+            Opcode::IAPush => self.push_arr(size_of::<u32>())?,
+            Opcode::LAPush => self.push_arr(size_of::<u64>())?,
+            Opcode::RAPush => self.push_ref_arr()?,
+            Opcode::MAPush => self.push_multi_arr()?,
+            Opcode::ArrayLength => self.arr_length()?,
+            Opcode::ArrayCopy => self.arr_copy()?,
+            Opcode::ArrayFill => self.arr_fill()?,
+            Opcode::ArrayResize => self.arr_resize()?,
+            Opcode::BAAppend => self.append_arr()?,
+            Opcode::BPush => {
+                let value = self.next_prg::<u8>()? as u32;
+                self.push(value)?;
+            },
+            Opcode::SPush => {
+                let value = self.next_prg::<u16>()? as u32;
+                self.push(value)?;
+            },
+            Opcode::IPush => {
+                let value = self.next_prg::<u32>()?;
+                self.push(value)?;
+            },
+            Opcode::LPush => {
+                let value = self.next_prg::<u64>()?;
+                self.push(value)?;
+            },
+            Opcode::Dup => {
+                let top_value = self.top::<u32>()?;
+                self.push(top_value)?;
+            },
+            Opcode::Dup2 => {
+                let top_value = self.top::<u64>()?;
+                self.push(top_value)?;
+            },
+            Opcode::Pop => {
+                self.pop::<u32>()?;
+            },
+            Opcode::Pop2 => {
+                self.pop::<u64>()?;
+            },
+            Opcode::Load => self.load::<u32>()?,
+            Opcode::Load2 => self.load::<u64>()?,
+            Opcode::BALoad => self.load_arr::<u8>(|v| v as u64, false, false)?,
+            Opcode::SALoad => self.load_arr::<u16>(|v| v as u64, false, false)?,
+            Opcode::IALoad => self.load_arr::<u32>(|v| v as u64, false, false)?,
+            Opcode::LALoad => self.load_arr::<u64>(|v| v, true, false)?,
+            Opcode::RALoad => self.load_arr::<usize>(|v| v as u64, true, true)?,
+            Opcode::Store => self.store::<u32>()?,
+            Opcode::Store2 => self.store::<u64>()?,
+            Opcode::BAStore => self.store_arr::<u8>(|v| v as u8, false, false)?,
+            Opcode::SAStore => self.store_arr::<u16>(|v| v as u16, false, false)?,
+            Opcode::IAStore => self.store_arr::<u32>(|v| v as u32, false, false)?,
+            Opcode::LAStore => self.store_arr::<u64>(|v| v, true, false)?,
+            Opcode::RAStore => self.store_arr::<usize>(|v| v as usize, true, true)?,
+            Opcode::Drop => self.drop_arr()?,
+            Opcode::IAdd => self.calc::<u32>(u32::overflowing_add, None)?,
+            Opcode::LAdd => self.calc::<u64>(u64::overflowing_add, None)?,
+            Opcode::ISub => self.calc::<u32>(u32::overflowing_sub, None)?,
+            Opcode::LSub => self.calc::<u64>(u64::overflowing_sub, None)?,
+            Opcode::IMul => self.calc::<u32>(u32::overflowing_mul, None)?,
+            Opcode::LMul => self.calc::<u64>(u64::overflowing_mul, None)?,
+            Opcode::IDiv => self.calc::<u32>(u32::overflowing_div, Some(|v| v == 0))?,
+            Opcode::LDiv => self.calc::<u64>(u64::overflowing_div, Some(|v| v == 0))?,
+            Opcode::IEq => self.compare::<u32>(|a, b| a == b)?,
+            Opcode::LEq => self.compare::<u64>(|a, b| a == b)?,
+            Opcode::IOrd => self.compare::<u32>(|a, b| a < b)?,
+            Opcode::LOrd => self.compare::<u64>(|a, b| a < b)?,
+            Opcode::IRevOrd => self.compare::<u32>(|a, b| a > b)?,
+            Opcode::LRevOrd => self.compare::<u64>(|a, b| a > b)?,
+            Opcode::IEqOrd => self.compare::<u32>(|a, b| a <= b)?,
+            Opcode::LEqOrd => self.compare::<u64>(|a, b| a <= b)?,
+            Opcode::Goto => self.goto(false)?,
+            Opcode::If => {
+                let cond = self.pop::<u32>()? != 0;
+                self.goto_if(cond, false)?;
+            },
+            Opcode::IfNot => {
+                let cond = self.pop::<u32>()? == 0;
+                self.goto_if(cond, false)?;
+            },
+            Opcode::GotoW => self.goto(true)?,
+            Opcode::IfW => {
+                let cond = self.pop::<u32>()? != 0;
+                self.goto_if(cond, true)?;
+            },
+            Opcode::IfNotW => {
+                let cond = self.pop::<u32>()? == 0;
+                self.goto_if(cond, true)?;
+            },
+            Opcode::TableSwitch | Opcode::LookupSwitch => self.switch(opcode_kind)?,
+            Opcode::Throw => self.throw(tmp_pc)?,
+            Opcode::BAConst => self.push_const_arr()?,
+            Opcode::New => self.new_obj()?,
+            Opcode::GetField => self.get_field()?,
+            Opcode::SetField => self.set_field()?,
+            Opcode::Ldc => match self.read_const()? {
+                (_, Some(Constant::Int(v))) => self.push(v)?,
+                (const_i, _) => return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+            },
+            Opcode::Ldc2 => match self.read_const()? {
+                (_, Some(Constant::Long(v))) => self.push(v)?,
+                (const_i, _) => return fail(ExitStatus::BytecodeAccessViolation, FaultDetail::Constant(const_i)),
+            },
+            Opcode::Unknown => return fail(ExitStatus::UnknownOpcode, FaultDetail::OpcodeByte(opcode)),
+        }
+
+        return Ok(());
+    }
+}
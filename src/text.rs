@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+use std::str::from_utf8;
+
+use crate::runtime::*;
+
+use num::FromPrimitive;
+use num_derive::*;
+
+// note: call 命令で呼び出す UTF-8 文字列操作の呼び出し番号
+// spec: 文字数やインデックスはバイト数でなく Unicode スカラ値の個数で数える
+// spec: validate と parse 系以外は文字列として受け取る配列が有効な UTF-8 でなければ失敗する
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
+pub enum TextCall {
+    // note: [arr] -> [int (有効な UTF-8 なら 1, それ以外は 0)]
+    Validate = 0x10,
+    // note: [arr] -> [usize 文字数]
+    CharLen,
+    // note: [arr][usize 開始文字インデックス][usize 文字数] -> [新しい配列]
+    Substring,
+    // note: [arr][arr] -> [新しい配列] (いずれの配列も変更しない)
+    Concat,
+    // note: [arr][arr] -> [int (-1 / 0 / 1)] (バイト列の辞書順は UTF-8 ではコードポイント順と一致する)
+    Compare,
+    // note: [arr][検索する arr] -> [usize 最初に現れる文字インデックス (見つからなければ usize::MAX)]
+    Find,
+    // note: [int] -> [新しい配列] (符号付き 10 進数)
+    FormatInt,
+    // note: [long] -> [新しい配列] (符号付き 10 進数)
+    FormatLong,
+    // note: [long (f64 のビット列)] -> [新しい配列]
+    FormatDouble,
+    // note: [arr] -> [int 値][int (成功すれば 1, それ以外は 0)]
+    ParseInt,
+    // note: [arr] -> [long 値][int (成功すれば 1, それ以外は 0)]
+    ParseLong,
+    // note: [arr] -> [long (f64 のビット列)][int (成功すれば 1, それ以外は 0)]
+    ParseDouble,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextArgKind {
+    Array,
+    Int,
+    Long,
+    Usize,
+}
+
+// note: スタックとやり取りする引数と戻り値 (配列は要素のバイト列として受け渡す)
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextValue {
+    Array(Vec<u8>),
+    Int(u32),
+    Long(u64),
+    Usize(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextError {
+    // note: 先頭から有効な UTF-8 であるバイト数
+    InvalidUtf8 { valid_len: usize },
+    // note: 範囲外の文字インデックスと文字数
+    CharIndex { index: usize, len: usize },
+    // note: 不足している, もしくは arg_kinds と種類の異なる引数のインデックス
+    Argument { index: usize },
+}
+
+impl TextError {
+    pub fn to_fault(&self) -> (ExitStatus, FaultDetail) {
+        return match self {
+            TextError::InvalidUtf8 { valid_len } => (ExitStatus::InvalidEncoding, FaultDetail::Encoding { valid_len: *valid_len }),
+            TextError::CharIndex { index, len } => (ExitStatus::ArrayAccessViolation, FaultDetail::CharIndex { index: *index, len: *len }),
+            TextError::Argument { index } => (ExitStatus::StackAccessViolation, FaultDetail::TextArgument(*index)),
+        };
+    }
+}
+
+fn to_str(bytes: &[u8]) -> Result<&str, TextError> {
+    return from_utf8(bytes).map_err(|e| TextError::InvalidUtf8 { valid_len: e.valid_up_to() });
+}
+
+fn parse<T: std::str::FromStr + Default>(bytes: &[u8]) -> (T, bool) {
+    return match from_utf8(bytes).ok().and_then(|v| v.parse::<T>().ok()) {
+        Some(v) => (v, true),
+        None => (T::default(), false),
+    };
+}
+
+fn arg_arr(args: &[TextValue], i: usize) -> Result<&[u8], TextError> {
+    return match args.get(i) {
+        Some(TextValue::Array(v)) => Ok(v),
+        _ => Err(TextError::Argument { index: i }),
+    };
+}
+
+fn arg_num(args: &[TextValue], i: usize) -> Result<u64, TextError> {
+    return match args.get(i) {
+        Some(TextValue::Int(v)) => Ok(*v as u64),
+        Some(TextValue::Long(v)) => Ok(*v),
+        Some(TextValue::Usize(v)) => Ok(*v as u64),
+        _ => Err(TextError::Argument { index: i }),
+    };
+}
+
+impl TextValue {
+    pub fn kind(&self) -> TextArgKind {
+        return match self {
+            TextValue::Array(_) => TextArgKind::Array,
+            TextValue::Int(_) => TextArgKind::Int,
+            TextValue::Long(_) => TextArgKind::Long,
+            TextValue::Usize(_) => TextArgKind::Usize,
+        };
+    }
+}
+
+impl TextCall {
+    pub fn from_code(code: u8) -> Option<TextCall> {
+        return FromPrimitive::from_u8(code);
+    }
+
+    // note: スタックからポップする引数の種類 (末尾の引数がスタックの最上部)
+    pub fn arg_kinds(&self) -> &'static [TextArgKind] {
+        return match self {
+            TextCall::Validate | TextCall::CharLen | TextCall::ParseInt | TextCall::ParseLong | TextCall::ParseDouble => &[TextArgKind::Array],
+            TextCall::Substring => &[TextArgKind::Array, TextArgKind::Usize, TextArgKind::Usize],
+            TextCall::Concat | TextCall::Compare | TextCall::Find => &[TextArgKind::Array, TextArgKind::Array],
+            TextCall::FormatInt => &[TextArgKind::Int],
+            TextCall::FormatLong | TextCall::FormatDouble => &[TextArgKind::Long],
+        };
+    }
+
+    // note: arg_kinds の順に並んだ引数を受け取り, プッシュする順に並んだ戻り値を返す
+    // spec: 引数の個数や種類が arg_kinds と異なる場合は最初に食い違う引数のインデックスを返す
+    pub fn apply(&self, args: &[TextValue]) -> Result<Vec<TextValue>, TextError> {
+        let kinds = self.arg_kinds();
+
+        if let Some(index) = (0..kinds.len().max(args.len())).find(|i| args.get(*i).map(|v| v.kind()) != kinds.get(*i).cloned()) {
+            return Err(TextError::Argument { index: index });
+        }
+
+        let arr = |i: usize| arg_arr(args, i);
+        let num = |i: usize| arg_num(args, i);
+
+        let values = match self {
+            TextCall::Validate => vec![TextValue::Int(from_utf8(arr(0)?).is_ok() as u32)],
+            TextCall::CharLen => vec![TextValue::Usize(to_str(arr(0)?)?.chars().count())],
+            TextCall::Substring => {
+                let s = to_str(arr(0)?)?;
+                let (begin, len) = (num(1)? as usize, num(2)? as usize);
+                let char_len = s.chars().count();
+
+                match begin.checked_add(len) {
+                    Some(v) if v <= char_len => (),
+                    _ => return Err(TextError::CharIndex { index: begin.saturating_add(len), len: char_len }),
+                }
+
+                vec![TextValue::Array(s.chars().skip(begin).take(len).collect::<String>().into_bytes())]
+            },
+            TextCall::Concat => {
+                let (left, right) = (to_str(arr(0)?)?, to_str(arr(1)?)?);
+                vec![TextValue::Array([left, right].concat().into_bytes())]
+            },
+            TextCall::Compare => {
+                let (left, right) = (to_str(arr(0)?)?, to_str(arr(1)?)?);
+
+                let ord = match left.cmp(right) {
+                    Ordering::Less => -1i32,
+                    Ordering::Equal => 0,
+                    Ordering::Greater => 1,
+                };
+
+                vec![TextValue::Int(ord as u32)]
+            },
+            TextCall::Find => {
+                let (s, pattern) = (to_str(arr(0)?)?, to_str(arr(1)?)?);
+                let index = s.find(pattern).map_or(usize::MAX, |v| s[..v].chars().count());
+                vec![TextValue::Usize(index)]
+            },
+            TextCall::FormatInt => vec![TextValue::Array((num(0)? as u32 as i32).to_string().into_bytes())],
+            TextCall::FormatLong => vec![TextValue::Array((num(0)? as i64).to_string().into_bytes())],
+            // note: 再び解析できるよう整数値でも小数点を含める (1.0, 1e100 など)
+            TextCall::FormatDouble => vec![TextValue::Array(format!("{:?}", f64::from_bits(num(0)?)).into_bytes())],
+            TextCall::ParseInt => {
+                let (value, is_ok) = parse::<i32>(arr(0)?);
+                vec![TextValue::Int(value as u32), TextValue::Int(is_ok as u32)]
+            },
+            TextCall::ParseLong => {
+                let (value, is_ok) = parse::<i64>(arr(0)?);
+                vec![TextValue::Long(value as u64), TextValue::Int(is_ok as u32)]
+            },
+            TextCall::ParseDouble => {
+                let (value, is_ok) = parse::<f64>(arr(0)?);
+                vec![TextValue::Long(value.to_bits()), TextValue::Int(is_ok as u32)]
+            },
+        };
+
+        return Ok(values);
+    }
+}
@@ -0,0 +1,356 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: 各文字を要素のバイトサイズ分だけ繰り返したバイト列 (1 文字が配列の 1 要素に対応する)
+fn elem_letters(letters: &[u8], elem_size: usize) -> Vec<u8> {
+    return letters.iter().flat_map(|v| vec![*v; elem_size]).collect();
+}
+
+// note: 変数 0-1 = 6 要素の配列 (同じ配列内で arraycopy して内容を出力)
+fn copy_within(asm: &mut Assembler, elem_size: u8, src_i: u64, dest_i: u64, len: u64) {
+    let main_i = asm.add_func(2, 0);
+    let const_i = asm.add_string(&String::from_utf8(elem_letters(b"abcdef", elem_size as usize)).unwrap());
+    asm.func(main_i)
+        .push(Opcode::BAConst, Operand::Short(const_i))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::LPush, Operand::Long(src_i))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::LPush, Operand::Long(dest_i))
+        .push(Opcode::LPush, Operand::Long(len))
+        .push(Opcode::ArrayCopy, Operand::Byte(elem_size))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Call, Operand::Byte(0x01));
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("mapush", |asm| {
+            // note: 2 x 3 のバイト配列の [1][2] に書き込んで [1] を出力
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::LPush, Operand::Long(3))
+                .push(Opcode::MAPush, Operand::Short(ArrayShape::new(2, 1).to_operand()))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::RALoad, Operand::None)
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::BPush, Operand::Byte(b'z'))
+                .push(Opcode::BAStore, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"\0\0z".to_vec()),
+        Case::new("ref_array_of_objects", |asm| {
+            // note: 変数 3-4 = 参照配列の要素 1 に格納するオブジェクト
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Int]);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::RAPush, Operand::Index(2))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::IPush, Operand::Int(42))
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Store2, Operand::Short(3))
+                .push(Opcode::SetField, Operand::Short(0))
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::RAStore, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::RALoad, Operand::None)
+                .push(Opcode::GetField, Operand::Short(0));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(42)),
+        Case::new("raload_on_byte_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(8))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::RALoad, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("laload_on_ref_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::RAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LALoad, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("rastore_non_handle", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::RAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LPush, Operand::Long(0x1234))
+                .push(Opcode::RAStore, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("mapush_invalid_shape", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::MAPush, Operand::Short(ArrayShape::new(1, 3).to_operand()));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("mapush_out_of_memory", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(0x100000))
+                .push(Opcode::LPush, Operand::Long(0x100000))
+                .push(Opcode::MAPush, Operand::Short(ArrayShape::new(2, 8).to_operand()));
+        }, ExitStatus::OutOfMemory, vec![]),
+        Case::new("mapush_empty_arrays_out_of_memory", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(0x400000))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::MAPush, Operand::Short(ArrayShape::new(2, 8).to_operand()));
+        }, ExitStatus::OutOfMemory, vec![]),
+        Case::new("arraylength", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::IAPush, Operand::Index(3))
+                .push(Opcode::ArrayLength, Operand::Byte(4));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(3)),
+        Case::new("arraycopy", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let from_i = asm.add_string("abcdef");
+            let to_i = asm.add_string("------");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(to_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::BAConst, Operand::Short(from_i))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::LPush, Operand::Long(3))
+                .push(Opcode::ArrayCopy, Operand::Byte(1))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"--bcd-".to_vec()),
+        Case::new("arraycopy_overlap", |asm| {
+            // note: 同じ配列内で後方へコピーしても元の値が失われない
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("abcdef");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::LPush, Operand::Long(4))
+                .push(Opcode::ArrayCopy, Operand::Byte(1))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"ababcd".to_vec()),
+        // note: 前方 (コピー先が後ろ) と後方 (コピー先が前) に重なる範囲を要素のバイトサイズごとにコピー
+        Case::new("arraycopy_overlap_forward_1", |asm| copy_within(asm, 1, 0, 2, 4), ExitStatus::Success, elem_letters(b"ababcd", 1)),
+        Case::new("arraycopy_overlap_forward_2", |asm| copy_within(asm, 2, 0, 2, 4), ExitStatus::Success, elem_letters(b"ababcd", 2)),
+        Case::new("arraycopy_overlap_forward_4", |asm| copy_within(asm, 4, 0, 2, 4), ExitStatus::Success, elem_letters(b"ababcd", 4)),
+        Case::new("arraycopy_overlap_forward_8", |asm| copy_within(asm, 8, 0, 2, 4), ExitStatus::Success, elem_letters(b"ababcd", 8)),
+        Case::new("arraycopy_overlap_backward_1", |asm| copy_within(asm, 1, 2, 0, 4), ExitStatus::Success, elem_letters(b"cdefef", 1)),
+        Case::new("arraycopy_overlap_backward_2", |asm| copy_within(asm, 2, 2, 0, 4), ExitStatus::Success, elem_letters(b"cdefef", 2)),
+        Case::new("arraycopy_overlap_backward_4", |asm| copy_within(asm, 4, 2, 0, 4), ExitStatus::Success, elem_letters(b"cdefef", 4)),
+        Case::new("arraycopy_overlap_backward_8", |asm| copy_within(asm, 8, 2, 0, 4), ExitStatus::Success, elem_letters(b"cdefef", 8)),
+        // spec: 長さ 0 のコピーは配列の末尾 (インデックス = 要素数) を指定できるが, それより後ろは範囲外
+        Case::new("arraycopy_empty_at_end", |asm| copy_within(asm, 4, 6, 6, 0), ExitStatus::Success, elem_letters(b"abcdef", 4)),
+        Case::new("arraycopy_empty_past_end", |asm| copy_within(asm, 4, 6, 7, 0), ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arraycopy_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::IAPush, Operand::Index(4))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::IAPush, Operand::Index(4))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LPush, Operand::Long(3))
+                .push(Opcode::ArrayCopy, Operand::Byte(4));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arraycopy_to_ref_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::RAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::ArrayCopy, Operand::Byte(8));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arrayfill", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::SAPush, Operand::Index(3))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::IPush, Operand::Int(0x10041))
+                .push(Opcode::ArrayFill, Operand::Byte(2))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, vec![0, 0, 0x41, 0, 0x41, 0]),
+        Case::new("arrayfill_invalid_elem_size", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(3))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::IPush, Operand::Int(0))
+                .push(Opcode::ArrayFill, Operand::Byte(3));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("iaload_after_drop_and_realloc", |asm| {
+            // note: 解放した配列と同じサイズの配列を確保しても解放済みのハンドルではアクセスできない
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            fill_free_list(f, 16);
+            f.push(Opcode::IAPush, Operand::Index(4))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Drop, Operand::None)
+                .push(Opcode::IAPush, Operand::Index(4))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::IALoad, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arrayresize", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("abcdef");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::LPush, Operand::Long(3))
+                .push(Opcode::ArrayResize, Operand::Byte(1))
+                .push(Opcode::LPush, Operand::Long(5))
+                .push(Opcode::ArrayResize, Operand::Byte(1))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"abc\0\0".to_vec()),
+        Case::new("arrayresize_old_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(2))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(4))
+                .push(Opcode::ArrayResize, Operand::Byte(1))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arrayresize_old_array_after_realloc", |asm| {
+            // note: 変更前と同じサイズの配列を確保しても変更前のハンドルではアクセスできない
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            fill_free_list(f, 2);
+            f.push(Opcode::BAPush, Operand::Index(2))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(4))
+                .push(Opcode::ArrayResize, Operand::Byte(1))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::BAPush, Operand::Index(2))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("arrayresize_shrink_ref_array", |asm| {
+            let main_i = asm.add_func(2, 0);
+            let const_i = asm.add_string("ok");
+            asm.func(main_i)
+                .push(Opcode::RAPush, Operand::Index(3))
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::RAStore, Operand::None)
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::ArrayResize, Operand::Byte(8))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::RALoad, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"ok".to_vec()),
+        Case::new("arrayresize_shrink_ref_array_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::RAPush, Operand::Index(3))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::ArrayResize, Operand::Byte(8))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::RALoad, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        // note: バイトサイズの領域を含めてヒープの上限 (64 MiB) から 16 バイト残るバイト配列を拡張
+        Case::new("arrayresize_to_heap_limit", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(0x4000000 - 24))
+                .push(Opcode::LPush, Operand::Long(0x4000000 - 8))
+                .push(Opcode::ArrayResize, Operand::Byte(1));
+        }, ExitStatus::Success, vec![]),
+        Case::new("arrayresize_past_heap_limit", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(0x4000000 - 24))
+                .push(Opcode::LPush, Operand::Long(0x4000000 - 7))
+                .push(Opcode::ArrayResize, Operand::Byte(1));
+        }, ExitStatus::OutOfMemory, vec![]),
+        Case::new("arrayresize_out_of_memory", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(0x100000000000))
+                .push(Opcode::ArrayResize, Operand::Byte(8));
+        }, ExitStatus::OutOfMemory, vec![]),
+        Case::new("baappend", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let head_i = asm.add_string("ab");
+            let tail_i = asm.add_string("cd");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(head_i))
+                .push(Opcode::BAConst, Operand::Short(tail_i))
+                .push(Opcode::BAAppend, Operand::None)
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::BAAppend, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"abcdabcd".to_vec()),
+        Case::new("baappend_old_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let head_i = asm.add_string("ab");
+            let tail_i = asm.add_string("cd");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(head_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::BAConst, Operand::Short(tail_i))
+                .push(Opcode::BAAppend, Operand::None)
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("baappend_old_array_after_realloc", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let head_i = asm.add_string("ab");
+            let tail_i = asm.add_string("cd");
+            let f = asm.func(main_i);
+            fill_free_list(f, 2);
+            f.push(Opcode::BAConst, Operand::Short(head_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::BAConst, Operand::Short(tail_i))
+                .push(Opcode::BAAppend, Operand::None)
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::BAPush, Operand::Index(2))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("baappend_past_heap_limit", |asm| {
+            // note: 追加するバイト配列の確保後に残る 3 バイトを超えて拡張する
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(0x4000000 - 24))
+                .push(Opcode::BAPush, Operand::Index(5))
+                .push(Opcode::BAAppend, Operand::None);
+        }, ExitStatus::OutOfMemory, vec![]),
+        Case::new("baappend_ref_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(1))
+                .push(Opcode::RAPush, Operand::Index(1))
+                .push(Opcode::BAAppend, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_array_table() {
+    check_cases(cases());
+}
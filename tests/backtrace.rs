@@ -0,0 +1,76 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: main -> outer -> inner と呼び出して inner で範囲外の配列要素を読み込む
+fn nested_program(is_named: bool) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let outer_i = asm.add_func(0, 0);
+    let inner_i = asm.add_func(0, 0);
+
+    if is_named {
+        let file_i = asm.add_file("nested.ches");
+        asm.func(main_i).name = Some("main".to_string());
+        asm.func(outer_i).name = Some("outer".to_string());
+        asm.func(inner_i).name = Some("inner".to_string());
+        asm.func(inner_i).set_location(file_i, 10, 3);
+    }
+
+    asm.func(main_i)
+        .push(Opcode::Nop, Operand::None)
+        .push(Opcode::Invoke, Operand::Index(outer_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(outer_i)
+        .push(Opcode::Invoke, Operand::Index(inner_i))
+        .push(Opcode::Ret, Operand::None);
+
+    asm.func(inner_i)
+        .push(Opcode::BAPush, Operand::Index(2))
+        .push(Opcode::LPush, Operand::Long(8))
+        .push(Opcode::BALoad, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+#[test]
+fn walk_frames_on_fault() {
+    let bytes = nested_program(false);
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+    let result = launch(bytes);
+
+    assert_eq!(result.exit_status, ExitStatus::ArrayAccessViolation);
+
+    let frames = result.backtrace.iter().map(|v| (v.pool_i, v.offset)).collect::<Vec<(Option<usize>, usize)>>();
+    // note: 呼び出し元のフレームは invoke の次の命令 (リターンアドレス) を指す
+    assert_eq!(frames, vec![(Some(2), 18), (Some(1), 9), (Some(0), 10)]);
+    assert_eq!(result.backtrace[0].pc, result.fault.as_ref().unwrap().pc);
+    assert_eq!(result.backtrace[2].pc, funcs[0].start_addr + 10);
+    assert_eq!(result.backtrace[1].to_string(), format!("0x{:x} (func 1 +0x9)", funcs[1].start_addr + 9));
+}
+
+#[test]
+fn name_frames_with_debug_info() {
+    let result = launch(nested_program(true));
+    let names = result.backtrace.iter().map(|v| v.func_name.clone().unwrap()).collect::<Vec<String>>();
+
+    assert_eq!(names, vec!["inner", "outer", "main"]);
+    assert_eq!(result.backtrace[0].source, Some(("nested.ches".to_string(), 10, 3)));
+    assert!(result.backtrace[0].to_string().ends_with("(inner +0x12) at nested.ches:10:3"));
+    assert_eq!(result.backtrace[1].source, None);
+}
+
+#[test]
+fn omit_backtrace_on_success() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Nop, Operand::None);
+
+    assert!(launch(asm.assemble()).backtrace.is_empty());
+}
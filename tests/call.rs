@@ -0,0 +1,212 @@
+mod common;
+
+use std::fs;
+
+use rustnut::*;
+use rustnut::assembler::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: main 以外に add(a, b), widen(a), twice(a) (add を呼び出す), fail() を持つバイトコード
+fn plugin_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_func(2, 2);
+    let widen_i = asm.add_func(1, 1);
+    let twice_i = asm.add_func(1, 1);
+    let fail_i = asm.add_func(0, 0);
+
+    asm.func(main_i).push(Opcode::Exit, Operand::None);
+
+    asm.func(add_i)
+        .set_symbol("add", "(ii)i")
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    asm.func(widen_i)
+        .set_symbol("widen", "(i)l")
+        .push(Opcode::LPush, Operand::Long(0x1_0000_0000))
+        .push(Opcode::Ret, Operand::None);
+
+    // note: add の戻り値はオペランドスタックに残らないため変数に格納して返す
+    asm.func(twice_i).name = Some("twice".to_string());
+    asm.func(twice_i)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Invoke, Operand::Index(add_i))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    asm.func(fail_i)
+        .set_symbol("fail", "()v")
+        .push(Opcode::IPush, Operand::Int(3))
+        .push(Opcode::Throw, Operand::None);
+
+    return asm.assemble();
+}
+
+fn load_vm(name: &str, bytes: Vec<u8>) -> ChesVM {
+    let path = std::env::temp_dir().join(format!("rustnut_call_{}_{}.chesc", name, std::process::id()));
+    fs::write(&path, bytes).unwrap();
+
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+
+    let mut vm = ChesVM::with_config(config);
+    assert_eq!(vm.load(&[path.to_str().unwrap()]).unwrap(), Ok(()));
+    fs::remove_file(&path).unwrap();
+    return vm;
+}
+
+// note: sum(n, acc) を再帰的に呼び出して 1 から n までの和を出力
+fn sum_recursion(asm: &mut Assembler, n: u32, invoke_opcode: Opcode) {
+    let main_i = asm.add_func(0, 0);
+    let sum_i = asm.add_func(2, 2);
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(n))
+        .push(Opcode::IPush, Operand::Int(0))
+        .push(Opcode::Invoke, Operand::Index(sum_i))
+        .push(Opcode::Exit, Operand::None);
+
+    let f = asm.func(sum_i);
+    let end_label = f.new_label();
+    f.push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IfNot, Operand::Jump(end_label))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::ISub, Operand::None)
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IAdd, Operand::None)
+        .push(invoke_opcode, Operand::Index(sum_i))
+        .push(Opcode::Ret, Operand::None);
+    f.set_label(end_label);
+    f.push(Opcode::IAPush, Operand::Index(1))
+        .push(Opcode::Dup2, Operand::None)
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAStore, Operand::None)
+        .push(Opcode::Call, Operand::Byte(0x01))
+        .push(Opcode::Ret, Operand::None);
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("tailinvoke", |asm| sum_recursion(asm, 1000, Opcode::TailInvoke), ExitStatus::Success, int_bytes(500500)),
+        Case::new("invoke_deep_recursion", |asm| sum_recursion(asm, 1000, Opcode::Invoke), ExitStatus::StackOverflow, vec![]),
+        Case::new("tailinvoke_missing_args", |asm| {
+            // note: 呼び出し元の変数は引数として渡せない
+            let main_i = asm.add_func(0, 0);
+            let caller_i = asm.add_func(1, 1);
+            let callee_i = asm.add_func(1, 1);
+            asm.func(main_i)
+                .push(Opcode::IPush, Operand::Int(1))
+                .push(Opcode::Invoke, Operand::Index(caller_i))
+                .push(Opcode::Exit, Operand::None);
+            asm.func(caller_i).push(Opcode::TailInvoke, Operand::Index(callee_i));
+            asm.func(callee_i).push(Opcode::Ret, Operand::None);
+        }, ExitStatus::StackAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_call_table() {
+    check_cases(cases());
+}
+
+#[test]
+fn call_by_name_and_index() {
+    let vm = load_vm("name", plugin_program());
+
+    assert_eq!(vm.call("add", &[Value::Int(40), Value::Int(2)]), Ok(Value::Int(42)));
+    assert_eq!(vm.call(1usize, &[Value::Int(1), Value::Int(2)]), Ok(Value::Int(3)));
+    assert_eq!(vm.call("widen", &[Value::Int(0)]), Ok(Value::Long(0x1_0000_0000)));
+    // note: デバッグ情報の関数名でも呼び出せる
+    assert_eq!(vm.call("twice", &[Value::Int(21)]), Ok(Value::Int(42)));
+    // note: exit で終了した場合は戻り値なし
+    assert_eq!(vm.call(0usize, &[]), Ok(Value::Void));
+}
+
+#[test]
+fn report_call_errors() {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    assert_eq!(ChesVM::with_config(config).call("add", &[]), Err(CallError::NotLoaded));
+
+    let vm = load_vm("errors", plugin_program());
+    assert_eq!(vm.call("sub", &[]), Err(CallError::UnknownFunction("sub".to_string())));
+    assert_eq!(vm.call(9usize, &[]), Err(CallError::InvalidPoolIndex(9)));
+    assert_eq!(vm.call("add", &[Value::Long(1)]), Ok(Value::Int(1)));
+    assert_eq!(vm.call("add", &[Value::Int(1)]), Err(CallError::ArgumentMismatch { pool_i: 1, arg_len: 2, given_len: 1 }));
+
+    match vm.call("fail", &[]) {
+        Err(CallError::Exited { status, fault: Some(fault) }) => {
+            assert_eq!(status, ExitStatus::UncaughtException);
+            assert_eq!(fault.detail, FaultDetail::Exception(3));
+        },
+        v => panic!("unexpected result {:?}", v),
+    }
+}
+
+#[test]
+fn report_invalid_return_value() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.add_func(1, 0);
+    asm.func(0).push(Opcode::Exit, Operand::None);
+    asm.func(1)
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::IPush, Operand::Int(3))
+        .push(Opcode::Ret, Operand::None);
+
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    let result = unsafe { Interpreter::launch_call(asm.assemble(), &config, 1, &[]) };
+
+    // note: 変数テーブルの後ろに 12 バイトの値が残っている
+    assert_eq!(result.exit_status, ExitStatus::StackAccessViolation);
+    assert_eq!(result.fault.unwrap().detail, FaultDetail::ReturnValue(12));
+    assert_eq!(result.ret_value, None);
+}
+
+#[test]
+fn return_from_tail_call() {
+    // note: 呼び出した関数 sum(n) から変数長の異なる sum_acc(n, acc) へ末尾呼び出しして戻り値を返す
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let sum_i = asm.add_func(1, 1);
+    let sum_acc_i = asm.add_func(2, 2);
+
+    asm.func(main_i).push(Opcode::Exit, Operand::None);
+
+    asm.func(sum_i)
+        .set_symbol("sum", "(i)i")
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IPush, Operand::Int(0))
+        .push(Opcode::TailInvoke, Operand::Index(sum_acc_i));
+
+    let f = asm.func(sum_acc_i);
+    let end_label = f.new_label();
+    f.push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IfNot, Operand::Jump(end_label))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::ISub, Operand::None)
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::TailInvoke, Operand::Index(sum_acc_i));
+    f.set_label(end_label);
+    f.push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::Ret, Operand::None);
+
+    let vm = load_vm("tail", asm.assemble());
+    assert_eq!(vm.call("sum", &[Value::Int(10000)]), Ok(Value::Int(50005000)));
+}
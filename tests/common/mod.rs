@@ -0,0 +1,124 @@
+#![allow(dead_code)]
+
+use rustnut::assembler::*;
+use rustnut::runtime::*;
+#[cfg(feature = "safe-interpreter")]
+use rustnut::safe_runtime::*;
+
+pub struct Case {
+    pub name: &'static str,
+    pub build: fn(&mut Assembler),
+    pub status: ExitStatus,
+    pub output: Vec<u8>,
+}
+
+impl Case {
+    pub fn new(name: &'static str, build: fn(&mut Assembler), status: ExitStatus, output: Vec<u8>) -> Case {
+        return Case {
+            name: name,
+            build: build,
+            status: status,
+            output: output,
+        };
+    }
+}
+
+pub fn launch(bytes: Vec<u8>) -> RunResult {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+
+    unsafe {
+        return Interpreter::launch(bytes, &config);
+    }
+}
+
+// note: 両インタプリタの終了ステータス・出力・実行命令数・異常終了の詳細が一致することを確認
+#[cfg(feature = "safe-interpreter")]
+pub fn assert_same_result(bytes: Vec<u8>, name: &str) {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+
+    let expected = unsafe { Interpreter::launch(bytes.clone(), &config) };
+    let actual = SafeInterpreter::launch(bytes, &config);
+
+    assert_eq!(actual.exit_status, expected.exit_status, "exit status of `{}`", name);
+    assert_eq!(actual.output, expected.output, "output of `{}`", name);
+    assert_eq!(actual.inst_count, expected.inst_count, "instruction count of `{}`", name);
+    assert_eq!(actual.fault.as_ref().map(|v| (v.status, v.pc, v.opcode)), expected.fault.as_ref().map(|v| (v.status, v.pc, v.opcode)), "fault of `{}`", name);
+
+    // note: 配列やオブジェクトのハンドルは両インタプリタで同じ順に払い出されるため詳細まで比較する
+    assert_eq!(actual.fault, expected.fault, "fault detail of `{}`", name);
+
+    assert_eq!(actual.backtrace, expected.backtrace, "backtrace of `{}`", name);
+}
+
+// note: 各ケースのプログラムを実行して終了ステータスと出力を確認
+// note: safe-interpreter フィーチャ有効時は安全なインタプリタとの結果の一致も確認
+pub fn check_cases(cases: Vec<Case>) {
+    for each_case in cases {
+        let mut asm = Assembler::new();
+        (each_case.build)(&mut asm);
+        let bytes = asm.assemble();
+        let result = launch(bytes.clone());
+
+        assert_eq!(result.exit_status, each_case.status, "exit status of case `{}`", each_case.name);
+        assert_eq!(result.output, each_case.output, "output of case `{}`", each_case.name);
+
+        #[cfg(feature = "safe-interpreter")]
+        assert_same_result(bytes, each_case.name);
+    }
+}
+
+// note: 変数 0-1 = 出力用配列, 変数 2 = 出力値 (エントリポイントではスタック先頭が変数テーブルとなる)
+pub fn int_prologue(f: &mut FuncDef) {
+    f.push(Opcode::IAPush, Operand::Index(1))
+        .push(Opcode::BPush, Operand::Byte(0));
+}
+
+// note: スタック先頭の int 値をリトルエンディアンの 4 バイトとして出力
+pub fn emit_int(f: &mut FuncDef) {
+    f.push(Opcode::Store, Operand::Short(2))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::Load, Operand::Short(2))
+        .push(Opcode::IAStore, Operand::None)
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Call, Operand::Byte(0x01));
+}
+
+// note: 変数 0-1 = 出力用配列, 変数 2-3 = 出力値
+pub fn long_prologue(f: &mut FuncDef) {
+    f.push(Opcode::LAPush, Operand::Index(1))
+        .push(Opcode::LPush, Operand::Long(0));
+}
+
+pub fn emit_long(f: &mut FuncDef) {
+    f.push(Opcode::Store2, Operand::Short(2))
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::Load2, Operand::Short(2))
+        .push(Opcode::LAStore, Operand::None)
+        .push(Opcode::Load2, Operand::Short(0))
+        .push(Opcode::Call, Operand::Byte(0x01));
+}
+
+pub fn int_bytes(v: u32) -> Vec<u8> {
+    return v.to_le_bytes().to_vec();
+}
+
+pub fn long_bytes(v: u64) -> Vec<u8> {
+    return v.to_le_bytes().to_vec();
+}
+
+// note: 同じサイズの配列を確保しては解放し, 解放済み領域が calloc で再利用されやすい状態にする (glibc の calloc は tcache を使わないため)
+pub fn fill_free_list(f: &mut FuncDef, arr_size: usize) {
+    for _ in 0..8 {
+        f.push(Opcode::BAPush, Operand::Index(arr_size));
+    }
+
+    for _ in 0..8 {
+        f.push(Opcode::Drop, Operand::None);
+    }
+}
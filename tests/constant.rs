@@ -0,0 +1,135 @@
+mod common;
+
+use std::mem::size_of;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("baconst", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("hello");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"hello".to_vec()),
+        Case::new("baconst_copies_constant", |asm| {
+            // note: 書き換えた配列の内容は同じ定数から複製した配列に影響しない
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("ab");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::BPush, Operand::Byte(b'x'))
+                .push(Opcode::BAStore, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01))
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"xbab".to_vec()),
+        Case::new("baconst_unknown_index", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.add_string("hello");
+            asm.func(main_i).push(Opcode::BAConst, Operand::Short(1));
+        }, ExitStatus::BytecodeAccessViolation, vec![]),
+        Case::new("ldc", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_int(0xdeadbeef);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::Ldc, Operand::Short(const_i));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(0xdeadbeef)),
+        Case::new("ldc2", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_double(1.5);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::Ldc2, Operand::Short(const_i));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(1.5f64.to_bits())),
+        Case::new("ldc_kind_mismatch", |asm| {
+            // note: long 定数を ldc で読み込むことはできない
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_long(1);
+            asm.func(main_i).push(Opcode::Ldc, Operand::Short(const_i));
+        }, ExitStatus::BytecodeAccessViolation, vec![]),
+        Case::new("ldc2_without_const_pool", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Ldc2, Operand::Short(0));
+        }, ExitStatus::BytecodeAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_constant_table() {
+    check_cases(cases());
+}
+
+#[test]
+fn share_same_constants() {
+    let mut asm = Assembler::new();
+    let long_i = asm.add_long(0x123456789);
+
+    assert_eq!(asm.add_long(0x123456789), long_i);
+    assert_eq!(asm.add_double(f64::from_bits(0x123456789)), long_i);
+    // note: 値が同じでも種別が異なれば別の定数となる
+    assert_ne!(asm.add_int(7), asm.add_long(7));
+    assert_eq!(asm.add_string("ab"), asm.add_bytes(b"ab"));
+
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Nop, Operand::None);
+
+    let bytes = asm.assemble();
+    assert_eq!(ConstPool::read(&bytes).unwrap().constants, vec![
+        Constant::Long(0x123456789),
+        Constant::Int(7),
+        Constant::Long(7),
+        Constant::Bytes(b"ab".to_vec()),
+    ]);
+}
+
+#[test]
+fn shrink_code_with_shared_constant() {
+    let build = |is_const: bool| -> Vec<u8> {
+        let mut asm = Assembler::new();
+        asm.add_func(0, 0);
+
+        for _ in 0..16 {
+            if is_const {
+                let const_i = asm.add_long(u64::MAX);
+                asm.func(0).push(Opcode::Ldc2, Operand::Short(const_i));
+            } else {
+                asm.func(0).push(Opcode::LPush, Operand::Long(u64::MAX));
+            }
+
+            asm.func(0).push(Opcode::Pop2, Operand::None);
+        }
+
+        return asm.assemble();
+    };
+
+    let (inline_bytes, const_bytes) = (build(false), build(true));
+    assert_eq!(launch(const_bytes.clone()).exit_status, ExitStatus::Success);
+
+    // note: 定数は 1 つのみ格納され, 各命令のオペランドは u64 から u16 のインデックスとなる
+    let const_pool_len = ConstPool::read(&const_bytes).map(|v| v.encode(0).len()).unwrap();
+    assert_eq!(inline_bytes.len() - (const_bytes.len() - const_pool_len), 16 * (size_of::<u64>() - size_of::<u16>()));
+}
+
+#[test]
+fn report_constant_index() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    let const_i = asm.add_string("text");
+    asm.func(0).push(Opcode::Ldc, Operand::Short(const_i));
+
+    let fault = launch(asm.assemble()).fault.unwrap();
+    assert_eq!((fault.status, fault.opcode), (ExitStatus::BytecodeAccessViolation, Opcode::Ldc));
+    assert_eq!(fault.detail, FaultDetail::Constant(const_i as usize));
+    assert!(fault.to_string().ends_with("constant index 0x0"));
+}
@@ -0,0 +1,253 @@
+use std::io::Cursor;
+
+use rustnut::assembler::*;
+use rustnut::dap::*;
+use rustnut::program::*;
+use rustnut::runtime::*;
+
+use serde_json::{json, Value};
+
+// note: main から add(40, 2) を呼び出して結果を変数 2 に格納する
+fn add_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_func(3, 2);
+
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(40))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Invoke, Operand::Index(add_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(add_i)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Store, Operand::Short(2))
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+fn encode(requests: Vec<Value>) -> Vec<u8> {
+    let mut bytes = Vec::<u8>::new();
+
+    for (i, mut each_request) in requests.into_iter().enumerate() {
+        each_request["seq"] = json!(i + 1);
+        each_request["type"] = json!("request");
+        let content = each_request.to_string();
+        bytes.extend_from_slice(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).as_bytes());
+    }
+
+    return bytes;
+}
+
+fn decode(mut bytes: &[u8]) -> Vec<Value> {
+    let mut messages = Vec::<Value>::new();
+
+    while bytes.len() != 0 {
+        let header_end = bytes.windows(4).position(|v| v == b"\r\n\r\n").unwrap();
+        let header = std::str::from_utf8(&bytes[..header_end]).unwrap();
+        let content_len = header.trim_start_matches("Content-Length: ").parse::<usize>().unwrap();
+        let content = &bytes[header_end + 4..header_end + 4 + content_len];
+        messages.push(serde_json::from_slice(content).unwrap());
+        bytes = &bytes[header_end + 4 + content_len..];
+    }
+
+    return messages;
+}
+
+fn serve(bytes: Vec<u8>, requests: Vec<Value>) -> (Option<ExitStatus>, Vec<Value>) {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+
+    let mut output = Vec::<u8>::new();
+    let mut server = DapServer::new(Cursor::new(encode(requests)), &mut output);
+
+    let status = match server.wait_for_launch(|_| Program::load(bytes.clone()).map_err(|e| e.to_string())) {
+        Some(v) => {
+            let result = unsafe { Interpreter::launch_program(&v, &config, Some(&mut server)) };
+            server.finish(&result);
+            Some(result.exit_status)
+        },
+        None => None,
+    };
+
+    return (status, decode(&output));
+}
+
+fn response<'a>(messages: &'a Vec<Value>, command: &str) -> Vec<&'a Value> {
+    return messages.iter().filter(|v| v["type"] == "response" && v["command"] == command).collect();
+}
+
+fn events<'a>(messages: &'a Vec<Value>, event: &str) -> Vec<&'a Value> {
+    return messages.iter().filter(|v| v["type"] == "event" && v["event"] == event).collect();
+}
+
+fn request(command: &str, arguments: Value) -> Value {
+    return json!({ "command": command, "arguments": arguments });
+}
+
+#[test]
+fn stop_at_func_breakpoint_and_inspect_frames() {
+    let (status, messages) = serve(add_program(), vec![
+        request("initialize", json!({ "adapterID": "rustnut" })),
+        request("launch", json!({ "program": "add.chesc" })),
+        request("setFunctionBreakpoints", json!({ "breakpoints": [{ "name": "func 1" }, { "name": "func 9" }] })),
+        request("configurationDone", json!({})),
+        request("threads", json!({})),
+        request("stackTrace", json!({ "threadId": 1 })),
+        request("scopes", json!({ "frameId": 0 })),
+        request("variables", json!({ "variablesReference": 1 })),
+        request("variables", json!({ "variablesReference": 2 })),
+        request("continue", json!({ "threadId": 1 })),
+        request("disconnect", json!({})),
+    ]);
+
+    assert_eq!(status, Some(ExitStatus::Success));
+    assert_eq!(events(&messages, "initialized").len(), 1);
+
+    let breakpoints = &response(&messages, "setFunctionBreakpoints")[0]["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0]["body"]["reason"], "breakpoint");
+
+    let frames = &response(&messages, "stackTrace")[0]["body"]["stackFrames"];
+    assert_eq!(frames.as_array().unwrap().len(), 2);
+    assert_eq!(frames[0]["name"], "func 1");
+    assert_eq!(frames[1]["name"], "func 0");
+
+    let variables = response(&messages, "variables");
+    let locals = variables[0]["body"]["variables"].as_array().unwrap();
+    assert_eq!(locals.len(), 3);
+    assert_eq!(locals[0]["value"], "40");
+    assert_eq!(locals[1]["value"], "2");
+    assert_eq!(variables[1]["body"]["variables"].as_array().unwrap().len(), 0);
+
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+    assert_eq!(events(&messages, "terminated").len(), 1);
+    assert_eq!(response(&messages, "disconnect").len(), 1);
+}
+
+#[test]
+fn step_from_entry() {
+    let (status, messages) = serve(add_program(), vec![
+        request("initialize", json!({})),
+        request("launch", json!({ "program": "add.chesc", "stopOnEntry": true })),
+        request("configurationDone", json!({})),
+        request("next", json!({ "threadId": 1 })),
+        request("next", json!({ "threadId": 1 })),
+        request("stepIn", json!({ "threadId": 1 })),
+        request("stackTrace", json!({ "threadId": 1 })),
+        request("stepOut", json!({ "threadId": 1 })),
+        request("stackTrace", json!({ "threadId": 1 })),
+        request("continue", json!({ "threadId": 1 })),
+    ]);
+
+    assert_eq!(status, Some(ExitStatus::Success));
+
+    let reasons = events(&messages, "stopped").iter().map(|v| v["body"]["reason"].as_str().unwrap()).collect::<Vec<&str>>();
+    assert_eq!(reasons, vec!["entry", "step", "step", "step", "step"]);
+
+    let traces = response(&messages, "stackTrace");
+    assert_eq!(traces[0]["body"]["stackFrames"][0]["name"], "func 1");
+    assert_eq!(traces[1]["body"]["totalFrames"], 1);
+}
+
+#[test]
+fn stop_at_instruction_breakpoint() {
+    let bytes = add_program();
+    // note: 末尾の exit 直前にある add 関数の ret
+    let ret_offset = bytes.len() - 2;
+
+    let (status, messages) = serve(bytes, vec![
+        request("launch", json!({ "program": "add.chesc" })),
+        request("setInstructionBreakpoints", json!({ "breakpoints": [{ "instructionReference": format!("0x{:x}", ret_offset - 1), "offset": 1 }] })),
+        request("configurationDone", json!({})),
+        request("variables", json!({ "variablesReference": 1 })),
+        request("continue", json!({ "threadId": 1 })),
+    ]);
+
+    assert_eq!(status, Some(ExitStatus::Success));
+
+    let frames_pc = &response(&messages, "setInstructionBreakpoints")[0]["body"]["breakpoints"][0]["instructionReference"];
+    assert_eq!(frames_pc, &json!(format!("0x{:x}", ret_offset)));
+    assert_eq!(response(&messages, "variables")[0]["body"]["variables"][2]["value"], "42");
+}
+
+#[test]
+fn abort_on_disconnect() {
+    let (status, messages) = serve(add_program(), vec![
+        request("launch", json!({ "program": "add.chesc", "stopOnEntry": true })),
+        request("configurationDone", json!({})),
+        request("disconnect", json!({})),
+    ]);
+
+    assert_eq!(status, Some(ExitStatus::Aborted));
+    assert_eq!(events(&messages, "exited").len(), 0);
+
+    let (status, _) = serve(add_program(), vec![
+        request("initialize", json!({})),
+        request("disconnect", json!({})),
+    ]);
+
+    assert_eq!(status, None);
+}
+
+#[test]
+fn reject_unloadable_program() {
+    let mut bytes = add_program();
+    bytes.truncate(4);
+
+    let (status, messages) = serve(bytes, vec![
+        request("initialize", json!({})),
+        request("launch", json!({ "program": "add.chesc" })),
+        request("configurationDone", json!({})),
+        request("disconnect", json!({})),
+    ]);
+
+    assert_eq!(status, None);
+
+    let launch = response(&messages, "launch")[0];
+    assert_eq!(launch["success"], false);
+    assert!(launch["message"].as_str().unwrap().starts_with("failed to load program `add.chesc`: "));
+    assert_eq!(events(&messages, "initialized").len(), 0);
+}
+
+#[test]
+fn stop_at_source_breakpoint() {
+    let mut asm = Assembler::new();
+    let file_i = asm.add_file("add.ches");
+    let main_i = asm.add_func(0, 0);
+
+    asm.func(main_i).name = Some("main".to_string());
+    asm.func(main_i)
+        .set_location(file_i, 1, 1)
+        .push(Opcode::IPush, Operand::Int(40))
+        .set_location(file_i, 2, 1)
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Exit, Operand::None);
+
+    let (status, messages) = serve(asm.assemble(), vec![
+        request("launch", json!({ "program": "add.chesc" })),
+        request("setBreakpoints", json!({ "source": { "path": "/home/user/add.ches" }, "breakpoints": [{ "line": 2 }, { "line": 5 }] })),
+        request("configurationDone", json!({})),
+        request("stackTrace", json!({ "threadId": 1 })),
+        request("continue", json!({ "threadId": 1 })),
+    ]);
+
+    assert_eq!(status, Some(ExitStatus::Success));
+
+    let breakpoints = &response(&messages, "setBreakpoints")[0]["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let frame = &response(&messages, "stackTrace")[0]["body"]["stackFrames"][0];
+    assert_eq!(frame["name"], "main");
+    assert_eq!(frame["source"]["path"], "add.ches");
+    assert_eq!(frame["line"], 2);
+}
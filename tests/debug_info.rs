@@ -0,0 +1,109 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: main から div(1, 0) を呼び出してゼロ除算で終了する
+fn div_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let file_i = asm.add_file("div.ches");
+    let main_i = asm.add_func(0, 0);
+    let div_i = asm.add_func(2, 2);
+
+    asm.func(main_i).name = Some("main".to_string());
+    asm.func(main_i)
+        .set_location(file_i, 2, 5)
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::IPush, Operand::Int(0))
+        .set_location(file_i, 3, 5)
+        .push(Opcode::Invoke, Operand::Index(div_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(div_i).name = Some("div".to_string());
+    asm.func(div_i)
+        .set_location(file_i, 7, 12)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .set_location(file_i, 7, 14)
+        .push(Opcode::IDiv, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+#[test]
+fn read_assembled_debug_info() {
+    let bytes = div_program();
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+    let debug_info = DebugInfo::read(&bytes).unwrap();
+
+    assert_eq!(debug_info.files, vec!["div.ches".to_string()]);
+    assert_eq!(debug_info.func_name(0), Some("main"));
+    assert_eq!(debug_info.func_name(1), Some("div"));
+    assert_eq!(debug_info.func_name(2), None);
+    assert_eq!(debug_info.lines.len(), 4);
+    assert_eq!(debug_info.lines[0].addr, funcs[0].start_addr);
+    assert_eq!(debug_info.lines[2].addr, funcs[1].start_addr);
+
+    // note: 行情報の間にある命令は直前の行情報に属する
+    let location = debug_info.location(funcs[0].start_addr + 5).unwrap();
+    assert_eq!((location.file, location.line, location.column), ("div.ches", 2, 5));
+    assert!(debug_info.location(funcs[0].start_addr - 1).is_none());
+}
+
+#[test]
+fn omit_debug_info_without_locations() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Nop, Operand::None);
+    let bytes = asm.assemble();
+
+    assert!(DebugInfo::read(&bytes).is_none());
+    assert_eq!(DebugInfo::describe_bytecode_pc(&bytes, Bytecode::read_pool_funcs(&bytes)[0].start_addr), format!("0x{:x} (func 0 +0x0)", Bytecode::read_pool_funcs(&bytes)[0].start_addr));
+}
+
+#[test]
+fn report_fault_location() {
+    let bytes = div_program();
+    let div_addr = DebugInfo::read(&bytes).unwrap().lines[3].addr;
+    let result = launch(bytes.clone());
+
+    assert_eq!(result.exit_status, ExitStatus::DivideByZero);
+    assert_eq!(result.fault.as_ref().map(|v| v.pc), Some(div_addr));
+
+    let div_start = Bytecode::read_pool_funcs(&bytes)[1].start_addr;
+    assert_eq!(DebugInfo::describe_bytecode_pc(&bytes, div_addr), format!("0x{:x} (div +0x{:x}) at div.ches:7:14", div_addr, div_addr - div_start));
+}
+
+#[test]
+fn ignore_malformed_debug_info() {
+    let mut bytes = div_program();
+    let addr_range = HeaderItem::DebugInfoAddr.get_bytecode_range();
+    bytes[addr_range.begin..addr_range.begin + addr_range.len].copy_from_slice(&usize::MAX.to_ne_bytes());
+
+    assert!(DebugInfo::read(&bytes).is_none());
+    assert_eq!(launch(bytes).exit_status, ExitStatus::DivideByZero);
+}
+
+#[test]
+fn clear_fault_on_success() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Exit, Operand::None);
+
+    assert_eq!(launch(asm.assemble()).fault, None);
+}
+
+#[test]
+fn reject_oversized_strings() {
+    let mut debug_info = DebugInfo::new();
+    debug_info.files.push("a".repeat(MAX_ENCODED_LEN));
+    assert!(debug_info.encode().is_ok());
+
+    // note: u16 に切り詰めずにエラーとする
+    debug_info.files.push("\u{e9}".repeat(MAX_ENCODED_LEN / 2 + 1));
+    assert_eq!(debug_info.encode(), Err(EncodeError::StringTooLong(MAX_ENCODED_LEN + 1)));
+}
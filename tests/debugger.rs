@@ -0,0 +1,128 @@
+use std::io::Cursor;
+
+use rustnut::assembler::*;
+use rustnut::debugger::*;
+use rustnut::runtime::*;
+
+// note: main から add(40, 2) を呼び出して結果を変数 2 に格納する
+fn add_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_func(3, 2);
+
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(40))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Invoke, Operand::Index(add_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(add_i)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Store, Operand::Short(2))
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+fn debug(bytes: Vec<u8>, commands: &str) -> (ExitStatus, String) {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+
+    let mut output = Vec::<u8>::new();
+    let mut debugger = Debugger::new(Cursor::new(commands.as_bytes().to_vec()), &mut output);
+    let result = unsafe { Interpreter::launch_with_hook(bytes, &config, Some(&mut debugger)) };
+
+    return (result.exit_status, String::from_utf8(output).unwrap());
+}
+
+fn stops(output: &str) -> Vec<&str> {
+    return output.lines().filter(|v| v.contains("stopped at ")).collect();
+}
+
+#[test]
+fn stop_at_func_breakpoint() {
+    let (status, output) = debug(add_program(), "b func 1\nc\nl\nbt\nr\nc\n");
+    let stops = stops(&output);
+
+    assert_eq!(status, ExitStatus::Success);
+    assert_eq!(stops.len(), 2);
+    assert!(stops[1].contains("(func 1 +0x0) load"), "{}", stops[1]);
+    assert!(output.contains("var 0 = 0x28 (40)"));
+    assert!(output.contains("var 1 = 0x2 (2)"));
+    assert!(output.contains("#0 ") && output.contains("(func 1 +0x0)"));
+    assert!(output.contains("#1 ") && output.contains("(func 0 +0x"));
+    assert!(output.contains("/ depth 1"));
+}
+
+#[test]
+fn stop_at_offset_breakpoint() {
+    let bytes = add_program();
+    // note: 末尾の exit 直前にある add 関数の ret
+    let ret_offset = bytes.len() - 2;
+    let (status, output) = debug(bytes, &format!("b 0x{:x}\nc\nl\nc\n", ret_offset));
+    let stops = stops(&output);
+
+    assert_eq!(status, ExitStatus::Success);
+    assert!(stops[1].contains(&format!("0x{:x} (func 1 +0x", ret_offset)) && stops[1].ends_with("ret"), "{}", stops[1]);
+    assert!(output.contains("var 2 = 0x2a (42)"));
+}
+
+#[test]
+fn step_over_invoke() {
+    let (status, output) = debug(add_program(), "n\nn\nn\nc\n");
+    let stops = stops(&output);
+
+    assert_eq!(status, ExitStatus::Success);
+    assert_eq!(stops.len(), 4);
+    assert!(stops.iter().all(|v| v.contains("(func 0 ")));
+    assert!(stops[3].ends_with("exit"), "{}", stops[3]);
+}
+
+#[test]
+fn step_into_and_out_of_invoke() {
+    let (status, output) = debug(add_program(), "s\ns\ns\ns\nf\nc\n");
+    let stops = stops(&output);
+
+    assert_eq!(status, ExitStatus::Success);
+    assert!(stops[3].contains("(func 1 +0x0) load"), "{}", stops[3]);
+    assert!(stops[4].contains("(func 1 +0x3) load"), "{}", stops[4]);
+    assert!(stops[5].contains("(func 0 ") && stops[5].ends_with("exit"), "{}", stops[5]);
+}
+
+#[test]
+fn abort_on_quit() {
+    let (status, _) = debug(add_program(), "q\n");
+    assert_eq!(status, ExitStatus::Aborted);
+
+    let (status, output) = debug(add_program(), "unknown\n");
+    assert_eq!(status, ExitStatus::Aborted);
+    assert!(output.contains("unknown command `unknown`"));
+}
+
+#[test]
+fn stop_at_named_func_breakpoint() {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_func(3, 2);
+
+    asm.func(main_i)
+        .push(Opcode::IPush, Operand::Int(40))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Invoke, Operand::Index(add_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(add_i)
+        .set_symbol("add", "(ii)")
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Ret, Operand::None);
+
+    let (status, output) = debug(asm.assemble(), "b func sub\nb func add\nc\nc\n");
+    let stops = stops(&output);
+
+    assert_eq!(status, ExitStatus::Success);
+    assert!(output.contains("unknown function `sub`"));
+    assert!(output.contains("breakpoint 0 at func 1 (add)"));
+    assert!(stops[1].contains("(add +0x0) load"), "{}", stops[1]);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: 関数内で例外コードを送出し, 呼び出し元で捕捉した例外コードを出力 (呼び出し先には inner_code のハンドラを設定)
+fn throw_through(asm: &mut Assembler, inner_code: u32, thrown_code: u32) {
+    let main_i = asm.add_func(0, 0);
+    let thrower_i = asm.add_func(1, 0);
+
+    let f = asm.func(main_i);
+    let begin_label = f.new_label();
+    let end_label = f.new_label();
+    let handler_label = f.new_label();
+    int_prologue(f);
+    f.set_label(begin_label);
+    f.push(Opcode::Invoke, Operand::Index(thrower_i));
+    f.set_label(end_label);
+    f.push(Opcode::BPush, Operand::Byte(0));
+    f.set_label(handler_label);
+    emit_int(f);
+    f.push(Opcode::Ret, Operand::None)
+        .add_handler(begin_label, end_label, handler_label, CATCH_ALL_CODE);
+
+    let f = asm.func(thrower_i);
+    let begin_label = f.new_label();
+    let end_label = f.new_label();
+    let handler_label = f.new_label();
+    f.set_label(begin_label);
+    f.push(Opcode::IPush, Operand::Int(thrown_code))
+        .push(Opcode::Throw, Operand::None);
+    f.set_label(end_label);
+    f.push(Opcode::Ret, Operand::None);
+    f.set_label(handler_label);
+    f.push(Opcode::IPush, Operand::Int(0xff))
+        .push(Opcode::Throw, Operand::None);
+    f.add_handler(begin_label, end_label, handler_label, inner_code);
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("throw_in_same_func", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            let begin_label = f.new_label();
+            let end_label = f.new_label();
+            let handler_label = f.new_label();
+            int_prologue(f);
+            f.set_label(begin_label);
+            f.push(Opcode::IPush, Operand::Int(7))
+                .push(Opcode::Throw, Operand::None);
+            f.set_label(end_label);
+            f.push(Opcode::BPush, Operand::Byte(0));
+            f.set_label(handler_label);
+            emit_int(f);
+            f.add_handler(begin_label, end_label, handler_label, 7);
+        }, ExitStatus::Success, int_bytes(7)),
+        Case::new("throw_unwinds_invoke", |asm| throw_through(asm, 1, 0x2a), ExitStatus::Success, int_bytes(0x2a)),
+        Case::new("throw_to_inner_handler", |asm| throw_through(asm, 0x2a, 0x2a), ExitStatus::Success, int_bytes(0xff)),
+        Case::new("throw_uncaught", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::IPush, Operand::Int(3))
+                .push(Opcode::Throw, Operand::None);
+        }, ExitStatus::UncaughtException, vec![]),
+        Case::new("throw_outside_handler_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            let begin_label = f.new_label();
+            let end_label = f.new_label();
+            f.set_label(begin_label);
+            f.push(Opcode::Nop, Operand::None);
+            f.set_label(end_label);
+            f.push(Opcode::IPush, Operand::Int(3))
+                .push(Opcode::Throw, Operand::None);
+            f.add_handler(begin_label, end_label, begin_label, CATCH_ALL_CODE);
+        }, ExitStatus::UncaughtException, vec![]),
+        Case::new("throw_empty_stack", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Throw, Operand::None);
+        }, ExitStatus::StackAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_exception_table() {
+    check_cases(cases());
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use std::mem::size_of;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn launch_main(build: impl Fn(&mut FuncDef)) -> (usize, RunResult) {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    build(asm.func(0));
+
+    let bytes = asm.assemble();
+    let start_addr = Bytecode::read_pool_funcs(&bytes)[0].start_addr;
+    return (start_addr, launch(bytes));
+}
+
+#[test]
+fn report_array_index() {
+    let (start_addr, result) = launch_main(|func| {
+        func.push(Opcode::BAPush, Operand::Index(8))
+            .push(Opcode::LPush, Operand::Long(10))
+            .push(Opcode::BALoad, Operand::None)
+            .push(Opcode::Exit, Operand::None);
+    });
+
+    let fault = result.fault.unwrap();
+    let pc = start_addr + 1 + size_of::<usize>() + 1 + size_of::<u64>();
+
+    assert_eq!(fault, Fault {
+        status: ExitStatus::ArrayAccessViolation,
+        pc: pc,
+        opcode: Opcode::BALoad,
+        detail: FaultDetail::ArrayIndex { index: 10, size: 8 },
+    });
+
+    assert_eq!(fault.to_string(), format!("ARRAY_ACCESS_VIOLATION at 0x{:x} (BALOAD): index 10 / 8 byte size", pc));
+}
+
+#[test]
+fn report_operands() {
+    let (_, result) = launch_main(|func| {
+        func.push(Opcode::IPush, Operand::Int(7))
+            .push(Opcode::IPush, Operand::Int(0))
+            .push(Opcode::IDiv, Operand::None)
+            .push(Opcode::Exit, Operand::None);
+    });
+
+    let fault = result.fault.unwrap();
+    assert_eq!(fault.opcode, Opcode::IDiv);
+    assert_eq!(fault.detail, FaultDetail::Operands { left: 7, right: 0 });
+}
+
+#[test]
+fn report_call_number() {
+    let (start_addr, result) = launch_main(|func| {
+        func.push(Opcode::Call, Operand::Byte(0xff))
+            .push(Opcode::Exit, Operand::None);
+    });
+
+    let fault = result.fault.unwrap();
+    assert_eq!((fault.status, fault.pc, fault.opcode), (ExitStatus::UnknownCallNumber, start_addr, Opcode::Call));
+    assert_eq!(fault.detail, FaultDetail::CallNumber(0xff));
+}
+
+#[test]
+fn report_stack_access() {
+    let (_, result) = launch_main(|func| {
+        func.push(Opcode::Pop, Operand::None)
+            .push(Opcode::Exit, Operand::None);
+    });
+
+    let fault = result.fault.unwrap();
+    assert_eq!(fault.status, ExitStatus::StackAccessViolation);
+
+    match fault.detail {
+        FaultDetail::StackAccess { sp, bp, size } => {
+            // note: エントリポイントのフレームにはローカル変数がない
+            assert_eq!(sp, bp + size_of::<usize>() * 2);
+            assert_eq!(size, size_of::<u32>());
+        },
+        _ => panic!("unexpected fault detail {:?}", fault.detail),
+    }
+}
+
+#[test]
+fn omit_fault_without_instruction() {
+    let mut bytes = {
+        let mut asm = Assembler::new();
+        asm.add_func(0, 0);
+        asm.func(0).push(Opcode::Exit, Operand::None);
+        asm.assemble()
+    };
+
+    // note: エントリポイントの開始位置をバイトコード外に書き換える
+    let mut entry_addr_bytes = [0u8; size_of::<usize>()];
+    entry_addr_bytes.copy_from_slice(&bytes[*HEADER_SIZE..*HEADER_SIZE + size_of::<usize>()]);
+    let entry_addr = usize::from_ne_bytes(entry_addr_bytes);
+    bytes[entry_addr..entry_addr + size_of::<usize>()].copy_from_slice(&usize::MAX.to_ne_bytes());
+
+    let result = launch(bytes);
+    assert_eq!(result.exit_status, ExitStatus::BytecodeAccessViolation);
+    assert_eq!(result.fault, None);
+}
+
+#[test]
+fn report_uncaught_exception() {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let thrower_i = asm.add_func(0, 0);
+
+    asm.func(main_i)
+        .push(Opcode::Invoke, Operand::Index(thrower_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(thrower_i)
+        .push(Opcode::IPush, Operand::Int(0xbeef))
+        .push(Opcode::Throw, Operand::None);
+
+    let bytes = asm.assemble();
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+    let result = launch(bytes);
+
+    // note: 捕捉されなかった場合はフレームを巻き戻さずに送出位置で終了
+    let fault = result.fault.unwrap();
+    assert_eq!(fault.status, ExitStatus::UncaughtException);
+    assert_eq!(fault.pc, funcs[1].start_addr + 1 + size_of::<u32>());
+    assert_eq!(fault.detail, FaultDetail::Exception(0xbeef));
+    assert_eq!(result.backtrace.iter().map(|v| v.pool_i).collect::<Vec<Option<usize>>>(), vec![Some(1), Some(0)]);
+}
+
+#[test]
+fn report_field() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    let struct_i = asm.add_struct(&[FieldType::Int, FieldType::Ref]);
+    asm.func(0)
+        .push(Opcode::New, Operand::Short(struct_i))
+        .push(Opcode::GetField, Operand::Short(2))
+        .push(Opcode::Exit, Operand::None);
+
+    let bytes = asm.assemble();
+    let pc = Bytecode::read_pool_funcs(&bytes)[0].start_addr + 1 + size_of::<u16>();
+    let fault = launch(bytes).fault.unwrap();
+
+    assert_eq!(fault, Fault {
+        status: ExitStatus::ObjectAccessViolation,
+        pc: pc,
+        opcode: Opcode::GetField,
+        detail: FaultDetail::Field { field_i: 2, field_len: 2 },
+    });
+
+    assert_eq!(fault.to_string(), format!("OBJECT_ACCESS_VIOLATION at 0x{:x} (GETFIELD): field 2 / 2 fields", pc));
+}
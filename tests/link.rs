@@ -0,0 +1,232 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::linker::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn link(modules: Vec<Vec<u8>>) -> LinkResult<Vec<u8>> {
+    let mut linker = Linker::new();
+
+    for each_module in modules {
+        linker.add_module(each_module);
+    }
+
+    return linker.link();
+}
+
+// note: add(40, 2) の結果を出力する (add は別モジュールからインポート)
+fn main_module(import_name: &str) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_import(import_name);
+
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(Opcode::IPush, Operand::Int(40))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Invoke, Operand::Index(add_i));
+    emit_int(f);
+    f.push(Opcode::Exit, Operand::None);
+
+    return asm.assemble();
+}
+
+// note: add は内部関数 sum が送出した引数の和を捕捉して再送出する (ret は値を返さないため)
+fn std_module() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let file_i = asm.add_file("std.ches");
+    let sum_i = asm.add_func(2, 2);
+    let add_i = asm.add_func(2, 2);
+    asm.add_export(add_i, "add");
+
+    asm.func(sum_i).name = Some("sum".to_string());
+    asm.func(sum_i)
+        .set_location(file_i, 1, 1)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Throw, Operand::None);
+
+    asm.func(add_i).name = Some("add".to_string());
+    let f = asm.func(add_i);
+    let begin_label = f.new_label();
+    let end_label = f.new_label();
+    let handler_label = f.new_label();
+    f.set_location(file_i, 5, 1);
+    f.set_label(begin_label);
+    f.push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::Invoke, Operand::Index(sum_i));
+    f.set_label(end_label);
+    f.push(Opcode::Ret, Operand::None);
+    f.set_label(handler_label);
+    f.push(Opcode::Throw, Operand::None);
+    f.add_handler(begin_label, end_label, handler_label, CATCH_ALL_CODE);
+
+    return asm.assemble();
+}
+
+// note: 呼び出し元のモジュールで add が送出した値を捕捉して出力
+fn catching_main_module() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let add_i = asm.add_import("add");
+
+    let f = asm.func(main_i);
+    let begin_label = f.new_label();
+    let end_label = f.new_label();
+    let handler_label = f.new_label();
+    int_prologue(f);
+    f.set_label(begin_label);
+    f.push(Opcode::IPush, Operand::Int(40))
+        .push(Opcode::IPush, Operand::Int(2))
+        .push(Opcode::Invoke, Operand::Index(add_i));
+    f.set_label(end_label);
+    f.push(Opcode::BPush, Operand::Byte(0));
+    f.set_label(handler_label);
+    emit_int(f);
+    f.push(Opcode::Exit, Operand::None)
+        .add_handler(begin_label, end_label, handler_label, CATCH_ALL_CODE);
+
+    return asm.assemble();
+}
+
+#[test]
+fn invoke_across_modules() {
+    let bytes = link(vec![catching_main_module(), std_module()]).unwrap();
+    let result = launch(bytes.clone());
+
+    assert_eq!(result.exit_status, ExitStatus::Success);
+    assert_eq!(result.output, int_bytes(42));
+
+    // note: インポートはプールから取り除かれてエクスポートされた関数に置き換えられる
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+    assert_eq!(funcs.len(), 3);
+    assert_eq!(LinkInfo::read(&bytes).unwrap().exports, vec![("add".to_string(), 2)]);
+}
+
+#[test]
+fn relocate_debug_info() {
+    let bytes = link(vec![main_module("add"), std_module()]).unwrap();
+    let debug_info = DebugInfo::read(&bytes).unwrap();
+    assert_eq!(debug_info.files, vec!["std.ches".to_string()]);
+    assert_eq!(debug_info.func_name(1), Some("sum"));
+
+    // note: add が送出した値を main で捕捉しないため送出位置で終了
+    let result = launch(bytes);
+    assert_eq!(result.exit_status, ExitStatus::UncaughtException);
+    assert_eq!(result.fault.unwrap().detail, FaultDetail::Exception(42));
+
+    let names = result.backtrace.iter().map(|v| v.func_name.clone().unwrap_or_default()).collect::<Vec<String>>();
+    assert_eq!(names, vec!["add".to_string(), "".to_string()]);
+    assert_eq!(result.backtrace[0].source.as_ref().unwrap().0, "std.ches");
+}
+
+#[test]
+fn report_link_errors() {
+    assert_eq!(link(vec![main_module("sub"), std_module()]), Err(LinkError::UndefinedSymbol("sub".to_string())));
+    assert_eq!(link(vec![main_module("add"), std_module(), std_module()]), Err(LinkError::DuplicateSymbol("add".to_string())));
+    assert_eq!(link(vec![main_module("add")]), Err(LinkError::UndefinedSymbol("add".to_string())));
+
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Nop, Operand::None);
+    assert_eq!(link(vec![main_module("add"), asm.assemble()]), Err(LinkError::MissingLinkInfo(1)));
+    assert_eq!(link(vec![main_module("add"), vec![0u8; 4]]), Err(LinkError::InvalidModule(1)));
+}
+
+#[test]
+fn pass_through_single_module() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Nop, Operand::None);
+    let bytes = asm.assemble();
+
+    assert_eq!(link(vec![bytes.clone()]), Ok(bytes));
+
+    // note: 未解決のインポートを呼び出した場合はバイトコード外へのジャンプとなる
+    assert_eq!(launch(main_module("add")).exit_status, ExitStatus::BytecodeAccessViolation);
+}
+
+#[test]
+fn merge_constants() {
+    // note: 各モジュールの定数インデックス 0 が連結後の定数プールで別の定数を指すよう再配置される
+    let mut greet_asm = Assembler::new();
+    let greet_i = greet_asm.add_func(0, 0);
+    let const_i = greet_asm.add_string(", world");
+    greet_asm.add_export(greet_i, "greet");
+    greet_asm.func(greet_i)
+        .push(Opcode::BAConst, Operand::Short(const_i))
+        .push(Opcode::Call, Operand::Byte(0x01))
+        .push(Opcode::Ret, Operand::None);
+
+    let mut main_asm = Assembler::new();
+    let main_i = main_asm.add_func(0, 0);
+    let greet_i = main_asm.add_import("greet");
+    let const_i = main_asm.add_string("hello");
+    main_asm.func(main_i)
+        .push(Opcode::BAConst, Operand::Short(const_i))
+        .push(Opcode::Call, Operand::Byte(0x01))
+        .push(Opcode::Invoke, Operand::Index(greet_i))
+        .push(Opcode::Exit, Operand::None);
+
+    let bytes = link(vec![main_asm.assemble(), greet_asm.assemble()]).unwrap();
+    let constants = ConstPool::read(&bytes).unwrap().constants;
+    assert_eq!(constants, vec![Constant::Bytes(b"hello".to_vec()), Constant::Bytes(b", world".to_vec())]);
+
+    let result = launch(bytes);
+    assert_eq!(result.exit_status, ExitStatus::Success);
+    assert_eq!(result.output, b"hello, world".to_vec());
+}
+
+#[test]
+fn report_too_many_constants() {
+    let module = |offset: usize| -> Vec<u8> {
+        let mut asm = Assembler::new();
+        asm.add_func(0, 0);
+        asm.add_export(0, &format!("f{}", offset));
+        asm.func(0).push(Opcode::Ret, Operand::None);
+
+        for i in 0..MAX_CONSTANT_LEN / 2 + 1 {
+            asm.add_int((offset + i) as u32);
+        }
+
+        return asm.assemble();
+    };
+
+    assert_eq!(link(vec![module(0), module(MAX_CONSTANT_LEN)]), Err(LinkError::TooManyConstants(MAX_CONSTANT_LEN + 1)));
+}
+
+#[test]
+fn report_too_many_files() {
+    let module = |offset: usize| -> Vec<u8> {
+        let mut asm = Assembler::new();
+        asm.add_func(0, 0);
+        asm.add_export(0, &format!("f{}", offset));
+        asm.func(0).push(Opcode::Ret, Operand::None);
+
+        for i in 0..MAX_ENCODED_LEN / 2 + 1 {
+            asm.add_file(&format!("{}.ches", offset + i));
+        }
+
+        return asm.assemble();
+    };
+
+    assert_eq!(link(vec![module(0), module(MAX_ENCODED_LEN)]), Err(LinkError::TooManyFiles(MAX_ENCODED_LEN + 1)));
+}
+
+#[test]
+fn reject_out_of_range_file_index() {
+    let mut asm = Assembler::new();
+    let file_i = asm.add_file("lib.ches");
+    asm.add_func(0, 0);
+    asm.add_export(0, "f");
+    asm.func(0)
+        .set_location(file_i + 1, 1, 1)
+        .push(Opcode::Ret, Operand::None);
+
+    assert_eq!(link(vec![main_module("f"), asm.assemble()]), Err(LinkError::InvalidModule(1)));
+}
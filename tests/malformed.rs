@@ -0,0 +1,140 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::linker::*;
+use rustnut::program::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn launch_main(build: fn(&mut FuncDef)) -> ExitStatus {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    build(asm.func(main_i));
+    return launch(asm.assemble()).exit_status;
+}
+
+#[test]
+fn exit_on_short_header() {
+    assert_eq!(launch(MAGIC_NUMBER.to_vec()).exit_status, ExitStatus::InvalidHeader);
+    assert_eq!(launch(Vec::new()).exit_status, ExitStatus::InvalidHeader);
+}
+
+#[test]
+fn exit_on_invalid_magic_number() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    let mut bytes = asm.assemble();
+    bytes[0] = 0;
+
+    assert_eq!(launch(bytes).exit_status, ExitStatus::InvalidHeader);
+}
+
+#[test]
+fn exit_on_unsupported_version() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Exit, Operand::None);
+    let mut bytes = asm.assemble();
+    assert_eq!(launch(bytes.clone()).exit_status, ExitStatus::Success);
+
+    // note: プール要素にハンドラ表と種別を持たない 1.x のバイトコード
+    bytes[HeaderItem::ChesVersion.get_bytecode_range().begin] = 1;
+    assert_eq!(launch(bytes.clone()).exit_status, ExitStatus::InvalidHeader);
+    assert_eq!(Program::load(bytes.clone()).err(), Some(LoadError::InvalidHeader));
+
+    let mut linker = Linker::new();
+    linker.add_module(bytes.clone());
+    linker.add_module(bytes);
+    assert_eq!(linker.link(), Err(LinkError::InvalidModule(0)));
+}
+
+#[test]
+fn exit_on_missing_pool() {
+    let mut bytes = MAGIC_NUMBER.to_vec();
+    bytes.resize(*HEADER_SIZE, 0);
+    bytes[HeaderItem::ChesVersion.get_bytecode_range().begin] = CURRENT_CHES_VERSION.0 as u8;
+
+    assert_eq!(launch(bytes.clone()).exit_status, ExitStatus::BytecodeAccessViolation);
+
+    // note: プール要素のアドレスがバイトコード外を指す
+    bytes.extend_from_slice(&usize::MAX.to_ne_bytes());
+    assert_eq!(launch(bytes).exit_status, ExitStatus::BytecodeAccessViolation);
+}
+
+#[test]
+fn exit_on_forged_array_pointer() {
+    let status = launch_main(|f| {
+        f.push(Opcode::LPush, Operand::Long(0xdeadbeef))
+            .push(Opcode::LPush, Operand::Long(0))
+            .push(Opcode::BALoad, Operand::None);
+    });
+
+    assert_eq!(status, ExitStatus::ArrayAccessViolation);
+}
+
+#[test]
+fn exit_on_dropped_array_access() {
+    let status = launch_main(|f| {
+        f.push(Opcode::BAPush, Operand::Index(4))
+            .push(Opcode::Dup2, Operand::None)
+            .push(Opcode::Drop, Operand::None)
+            .push(Opcode::LPush, Operand::Long(0))
+            .push(Opcode::BALoad, Operand::None);
+    });
+
+    assert_eq!(status, ExitStatus::ArrayAccessViolation);
+}
+
+#[test]
+fn exit_on_double_drop() {
+    let status = launch_main(|f| {
+        f.push(Opcode::BAPush, Operand::Index(4))
+            .push(Opcode::Dup2, Operand::None)
+            .push(Opcode::Drop, Operand::None)
+            .push(Opcode::Drop, Operand::None);
+    });
+
+    assert_eq!(status, ExitStatus::ArrayAccessViolation);
+}
+
+#[test]
+fn exit_on_huge_array_index() {
+    let status = launch_main(|f| {
+        f.push(Opcode::LAPush, Operand::Index(1))
+            .push(Opcode::LPush, Operand::Long(u64::MAX))
+            .push(Opcode::LALoad, Operand::None);
+    });
+
+    assert_eq!(status, ExitStatus::ArrayAccessViolation);
+}
+
+#[test]
+fn exit_on_huge_array_allocation() {
+    assert_eq!(launch_main(|f| { f.push(Opcode::LAPush, Operand::Index(usize::MAX)); }), ExitStatus::OutOfMemory);
+    assert_eq!(launch_main(|f| { f.push(Opcode::BAPush, Operand::Index(usize::MAX / 2)); }), ExitStatus::OutOfMemory);
+}
+
+#[test]
+fn exit_on_huge_pool_index() {
+    assert_eq!(launch_main(|f| { f.push(Opcode::Invoke, Operand::Index(usize::MAX)); }), ExitStatus::BytecodeAccessViolation);
+}
+
+#[test]
+fn exit_on_truncated_switch_table() {
+    // note: オフセット数やキー数に対してバイトコードの残りが足りない
+    let status = launch_main(|func| {
+        func.push(Opcode::BPush, Operand::Byte(0))
+            .push(Opcode::TableSwitch, Operand::Short(0));
+    });
+
+    assert_eq!(status, ExitStatus::BytecodeAccessViolation);
+
+    let status = launch_main(|func| {
+        func.push(Opcode::BPush, Operand::Byte(0))
+            .push(Opcode::LookupSwitch, Operand::Long(0x0000ffff00000000));
+    });
+
+    assert_eq!(status, ExitStatus::BytecodeAccessViolation);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("new_object", |asm| {
+            // note: 変数 3-4 = (int, long) のオブジェクト (long フィールドへの書き込みが int フィールドを上書きしないことを確認)
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Int, FieldType::Long]);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Store2, Operand::Short(3))
+                .push(Opcode::IPush, Operand::Int(42))
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::SetField, Operand::Short(0))
+                .push(Opcode::LPush, Operand::Long(u64::MAX))
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::SetField, Operand::Short(1))
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::GetField, Operand::Short(0));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(42)),
+        Case::new("object_ref_field", |asm| {
+            // note: 変数 0-1 = オブジェクト (ref フィールドに格納した配列を取り出して出力)
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Ref]);
+            let const_i = asm.add_string("ref");
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Store2, Operand::Short(0))
+                .push(Opcode::SetField, Operand::Short(0))
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::GetField, Operand::Short(0))
+                .push(Opcode::Call, Operand::Byte(0x01))
+                .push(Opcode::Load2, Operand::Short(0))
+                .push(Opcode::Drop, Operand::None);
+        }, ExitStatus::Success, b"ref".to_vec()),
+        Case::new("getfield_on_array", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::IAPush, Operand::Index(1))
+                .push(Opcode::GetField, Operand::Short(0));
+        }, ExitStatus::ObjectAccessViolation, vec![]),
+        Case::new("getfield_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Int]);
+            asm.func(main_i)
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::GetField, Operand::Short(1));
+        }, ExitStatus::ObjectAccessViolation, vec![]),
+        Case::new("getfield_after_drop", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Long]);
+            asm.func(main_i)
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Drop, Operand::None)
+                .push(Opcode::GetField, Operand::Short(0));
+        }, ExitStatus::ObjectAccessViolation, vec![]),
+        Case::new("getfield_after_drop_and_realloc", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Long]);
+            let f = asm.func(main_i);
+            fill_free_list(f, 8);
+            f.push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::Drop, Operand::None)
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::Pop2, Operand::None)
+                .push(Opcode::GetField, Operand::Short(0));
+        }, ExitStatus::ObjectAccessViolation, vec![]),
+        Case::new("setfield_null_ref", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Ref]);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::SetField, Operand::Short(0));
+        }, ExitStatus::Success, vec![]),
+        Case::new("setfield_non_handle", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let struct_i = asm.add_struct(&[FieldType::Ref]);
+            asm.func(main_i)
+                .push(Opcode::LPush, Operand::Long(0x1234))
+                .push(Opcode::New, Operand::Short(struct_i))
+                .push(Opcode::SetField, Operand::Short(0));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("new_non_struct_constant", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_int(1);
+            asm.func(main_i).push(Opcode::New, Operand::Short(const_i));
+        }, ExitStatus::BytecodeAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_object_table() {
+    check_cases(cases());
+}
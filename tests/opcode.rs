@@ -0,0 +1,450 @@
+mod common;
+
+use std::mem::size_of;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: 2 つの int 値に演算を適用して出力
+fn int_binary(asm: &mut Assembler, left: u32, right: u32, opcode: Opcode) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(Opcode::IPush, Operand::Int(left))
+        .push(Opcode::IPush, Operand::Int(right))
+        .push(opcode, Operand::None);
+    emit_int(f);
+}
+
+// note: 2 つの long 値に演算を適用して出力
+fn long_binary(asm: &mut Assembler, left: u64, right: u64, opcode: Opcode) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    long_prologue(f);
+    f.push(Opcode::LPush, Operand::Long(left))
+        .push(Opcode::LPush, Operand::Long(right))
+        .push(opcode, Operand::None);
+    emit_long(f);
+}
+
+// note: 2 つの long 値を比較して int の結果を出力
+fn long_compare(asm: &mut Assembler, left: u64, right: u64, opcode: Opcode) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(Opcode::LPush, Operand::Long(left))
+        .push(Opcode::LPush, Operand::Long(right))
+        .push(opcode, Operand::None);
+    emit_int(f);
+}
+
+// note: 長さ 2 の配列の要素 1 に値を格納してから読み出して出力
+fn array_round_trip(asm: &mut Assembler, push_opcode: Opcode, load_opcode: Opcode, store_opcode: Opcode, value: u32) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(push_opcode, Operand::Index(2))
+        .push(Opcode::Dup2, Operand::None)
+        .push(Opcode::LPush, Operand::Long(1))
+        .push(Opcode::IPush, Operand::Int(value))
+        .push(store_opcode, Operand::None)
+        .push(Opcode::LPush, Operand::Long(1))
+        .push(load_opcode, Operand::None);
+    emit_int(f);
+}
+
+// note: 要素の最大値を格納してから読み出し, 1 を足して出力 (int へのゼロ拡張を確認)
+fn widened_load(asm: &mut Assembler, push_opcode: Opcode, load_opcode: Opcode, store_opcode: Opcode, value: u32) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(push_opcode, Operand::Index(1))
+        .push(Opcode::Dup2, Operand::None)
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(Opcode::IPush, Operand::Int(value))
+        .push(store_opcode, Operand::None)
+        .push(Opcode::LPush, Operand::Long(0))
+        .push(load_opcode, Operand::None)
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::IAdd, Operand::None);
+    emit_int(f);
+}
+
+// note: 条件値によって分岐し, 分岐した場合は 1 を出力
+fn branch(asm: &mut Assembler, cond: u8, opcode: Opcode) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    let jump_label = f.new_label();
+    let end_label = f.new_label();
+    int_prologue(f);
+    f.push(Opcode::BPush, Operand::Byte(cond))
+        .push(opcode, Operand::Jump(jump_label))
+        .push(Opcode::BPush, Operand::Byte(0))
+        .push(Opcode::Goto, Operand::Jump(end_label));
+    f.set_label(jump_label);
+    f.push(Opcode::BPush, Operand::Byte(1));
+    f.set_label(end_label);
+    emit_int(f);
+}
+
+// note: goto が 3 バイトであれば if のオフセットは i16::MAX - 1 となる
+const WIDE_BRANCH_NOP_LEN: usize = i16::MAX as usize - 4;
+
+// note: i16 に収まらないオフセットの goto を挟み, その伸びによって if のオフセットも i16 に収まらなくなる
+// note: 条件値が 0 以外であれば 1, それ以外は 2 を出力
+fn wide_branch(asm: &mut Assembler, cond: u8) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    let one_label = f.new_label();
+    let far_label = f.new_label();
+    let end_label = f.new_label();
+    int_prologue(f);
+    f.push(Opcode::BPush, Operand::Byte(cond))
+        .push(Opcode::If, Operand::Jump(one_label))
+        .push(Opcode::Goto, Operand::Jump(far_label));
+
+    for _ in 0..WIDE_BRANCH_NOP_LEN {
+        f.push(Opcode::Nop, Operand::None);
+    }
+
+    f.set_label(one_label);
+    f.push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::Goto, Operand::Jump(end_label));
+
+    for _ in 0..10 {
+        f.push(Opcode::Nop, Operand::None);
+    }
+
+    f.set_label(far_label);
+    f.push(Opcode::IPush, Operand::Int(2));
+    f.set_label(end_label);
+    emit_int(f);
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("nop", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Nop, Operand::None);
+        }, ExitStatus::Success, vec![]),
+        Case::new("exit", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::Exit, Operand::None)
+                .push(Opcode::Pop, Operand::None);
+        }, ExitStatus::Success, vec![]),
+        Case::new("ret_from_entry_point", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Ret, Operand::None);
+        }, ExitStatus::Success, vec![]),
+        Case::new("call_console_output", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::BAPush, Operand::Index(2))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::BPush, Operand::Byte(b'o'))
+                .push(Opcode::BAStore, Operand::None)
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::BPush, Operand::Byte(b'k'))
+                .push(Opcode::BAStore, Operand::None)
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"ok".to_vec()),
+        Case::new("invoke_and_ret", |asm| {
+            let main_i = asm.add_func(0, 0);
+            // note: 引数 2 つを加算して出力する関数
+            let add_i = asm.add_func(5, 2);
+            asm.func(main_i)
+                .push(Opcode::IPush, Operand::Int(40))
+                .push(Opcode::IPush, Operand::Int(2))
+                .push(Opcode::Invoke, Operand::Index(add_i))
+                .push(Opcode::Ret, Operand::None);
+            let f = asm.func(add_i);
+            f.push(Opcode::IAPush, Operand::Index(1))
+                .push(Opcode::Store2, Operand::Short(3))
+                .push(Opcode::Load, Operand::Short(0))
+                .push(Opcode::Load, Operand::Short(1))
+                .push(Opcode::IAdd, Operand::None)
+                .push(Opcode::Store, Operand::Short(2))
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::Load, Operand::Short(2))
+                .push(Opcode::IAStore, Operand::None)
+                .push(Opcode::Load2, Operand::Short(3))
+                .push(Opcode::Call, Operand::Byte(0x01))
+                .push(Opcode::Ret, Operand::None);
+        }, ExitStatus::Success, int_bytes(42)),
+        Case::new("bapush", |asm| array_round_trip(asm, Opcode::BAPush, Opcode::BALoad, Opcode::BAStore, 0xab), ExitStatus::Success, int_bytes(0xab)),
+        Case::new("sapush", |asm| array_round_trip(asm, Opcode::SAPush, Opcode::SALoad, Opcode::SAStore, 0xabcd), ExitStatus::Success, int_bytes(0xabcd)),
+        Case::new("iapush", |asm| array_round_trip(asm, Opcode::IAPush, Opcode::IALoad, Opcode::IAStore, 0xabcdef01), ExitStatus::Success, int_bytes(0xabcdef01)),
+        Case::new("bastore_truncates", |asm| array_round_trip(asm, Opcode::BAPush, Opcode::BALoad, Opcode::BAStore, 0x1ff), ExitStatus::Success, int_bytes(0xff)),
+        Case::new("baload_zero_extends", |asm| widened_load(asm, Opcode::BAPush, Opcode::BALoad, Opcode::BAStore, 0xff), ExitStatus::Success, int_bytes(0x100)),
+        Case::new("saload_zero_extends", |asm| widened_load(asm, Opcode::SAPush, Opcode::SALoad, Opcode::SAStore, 0xffff), ExitStatus::Success, int_bytes(0x10000)),
+        Case::new("lapush", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::LAPush, Operand::Index(2))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(0x0123456789abcdef))
+                .push(Opcode::LAStore, Operand::None)
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LALoad, Operand::None);
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(0x0123456789abcdef)),
+        Case::new("bpush", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::BPush, Operand::Byte(0xfe));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(0xfe)),
+        Case::new("spush", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::SPush, Operand::Short(0xfedc));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(0xfedc)),
+        Case::new("ipush", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::IPush, Operand::Int(0xfedcba98));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(0xfedcba98)),
+        Case::new("lpush", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(0xfedcba9876543210));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(0xfedcba9876543210)),
+        Case::new("dup", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::BPush, Operand::Byte(21))
+                .push(Opcode::Dup, Operand::None)
+                .push(Opcode::IAdd, Operand::None);
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(42)),
+        Case::new("dup2", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(0x100000000))
+                .push(Opcode::Dup2, Operand::None)
+                .push(Opcode::LAdd, Operand::None);
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(0x200000000)),
+        Case::new("pop", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::BPush, Operand::Byte(1))
+                .push(Opcode::BPush, Operand::Byte(2))
+                .push(Opcode::Pop, Operand::None);
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(1)),
+        Case::new("pop2", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::Pop2, Operand::None);
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(1)),
+        Case::new("load_and_store", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            int_prologue(f);
+            // note: 変数 3 を確保してから値を書き換え
+            f.push(Opcode::BPush, Operand::Byte(0))
+                .push(Opcode::BPush, Operand::Byte(7))
+                .push(Opcode::Store, Operand::Short(3))
+                .push(Opcode::Load, Operand::Short(3));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(7)),
+        Case::new("load2_and_store2", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LPush, Operand::Long(0x700000007))
+                .push(Opcode::Store2, Operand::Short(4))
+                .push(Opcode::Load2, Operand::Short(4));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(0x700000007)),
+        Case::new("drop", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::IAPush, Operand::Index(4))
+                .push(Opcode::Drop, Operand::None);
+        }, ExitStatus::Success, vec![]),
+        Case::new("iadd", |asm| int_binary(asm, 40, 2, Opcode::IAdd), ExitStatus::Success, int_bytes(42)),
+        Case::new("ladd", |asm| long_binary(asm, 0x100000000, 2, Opcode::LAdd), ExitStatus::Success, long_bytes(0x100000002)),
+        Case::new("isub", |asm| int_binary(asm, 44, 2, Opcode::ISub), ExitStatus::Success, int_bytes(42)),
+        Case::new("lsub", |asm| long_binary(asm, 0x100000000, 1, Opcode::LSub), ExitStatus::Success, long_bytes(0xffffffff)),
+        Case::new("imul", |asm| int_binary(asm, 6, 7, Opcode::IMul), ExitStatus::Success, int_bytes(42)),
+        Case::new("lmul", |asm| long_binary(asm, 0x100000000, 3, Opcode::LMul), ExitStatus::Success, long_bytes(0x300000000)),
+        Case::new("idiv", |asm| int_binary(asm, 85, 2, Opcode::IDiv), ExitStatus::Success, int_bytes(42)),
+        Case::new("ldiv", |asm| long_binary(asm, 0x300000000, 3, Opcode::LDiv), ExitStatus::Success, long_bytes(0x100000000)),
+        Case::new("ieq", |asm| int_binary(asm, 3, 3, Opcode::IEq), ExitStatus::Success, int_bytes(1)),
+        Case::new("ieq_false", |asm| int_binary(asm, 3, 4, Opcode::IEq), ExitStatus::Success, int_bytes(0)),
+        Case::new("leq", |asm| long_compare(asm, 0x100000003, 0x100000003, Opcode::LEq), ExitStatus::Success, int_bytes(1)),
+        Case::new("iord", |asm| int_binary(asm, 3, 4, Opcode::IOrd), ExitStatus::Success, int_bytes(1)),
+        Case::new("iord_equal", |asm| int_binary(asm, 4, 4, Opcode::IOrd), ExitStatus::Success, int_bytes(0)),
+        Case::new("lord", |asm| long_compare(asm, 3, 0x100000000, Opcode::LOrd), ExitStatus::Success, int_bytes(1)),
+        Case::new("irevord", |asm| int_binary(asm, 4, 3, Opcode::IRevOrd), ExitStatus::Success, int_bytes(1)),
+        Case::new("lrevord", |asm| long_compare(asm, 3, 0x100000000, Opcode::LRevOrd), ExitStatus::Success, int_bytes(0)),
+        Case::new("ieqord", |asm| int_binary(asm, 4, 4, Opcode::IEqOrd), ExitStatus::Success, int_bytes(1)),
+        Case::new("leqord", |asm| long_compare(asm, 0x100000001, 0x100000000, Opcode::LEqOrd), ExitStatus::Success, int_bytes(0)),
+        Case::new("goto", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            let skip_label = f.new_label();
+            int_prologue(f);
+            f.push(Opcode::BPush, Operand::Byte(1))
+                .push(Opcode::Goto, Operand::Jump(skip_label))
+                .push(Opcode::BPush, Operand::Byte(2))
+                .push(Opcode::IAdd, Operand::None);
+            f.set_label(skip_label);
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(1)),
+        Case::new("goto_backward", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            let loop_label = f.new_label();
+            let end_label = f.new_label();
+            int_prologue(f);
+            // note: 値が 5 になるまで 1 を加算し続ける
+            f.push(Opcode::BPush, Operand::Byte(0));
+            f.set_label(loop_label);
+            f.push(Opcode::Dup, Operand::None)
+                .push(Opcode::BPush, Operand::Byte(5))
+                .push(Opcode::IEq, Operand::None)
+                .push(Opcode::If, Operand::Jump(end_label))
+                .push(Opcode::BPush, Operand::Byte(1))
+                .push(Opcode::IAdd, Operand::None)
+                .push(Opcode::Goto, Operand::Jump(loop_label));
+            f.set_label(end_label);
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(5)),
+        Case::new("if_taken", |asm| branch(asm, 1, Opcode::If), ExitStatus::Success, int_bytes(1)),
+        Case::new("if_not_taken", |asm| branch(asm, 0, Opcode::If), ExitStatus::Success, int_bytes(0)),
+        Case::new("ifnot_taken", |asm| branch(asm, 0, Opcode::IfNot), ExitStatus::Success, int_bytes(1)),
+        Case::new("ifnot_not_taken", |asm| branch(asm, 1, Opcode::IfNot), ExitStatus::Success, int_bytes(0)),
+        Case::new("unknown_call_number", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Call, Operand::Byte(0x7f));
+        }, ExitStatus::UnknownCallNumber, vec![]),
+        Case::new("invoke_unknown_pool_index", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Invoke, Operand::Index(0x1000));
+        }, ExitStatus::BytecodeAccessViolation, vec![]),
+        Case::new("stack_overflow", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let f = asm.func(main_i);
+            let loop_label = f.new_label();
+            f.set_label(loop_label);
+            f.push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::Goto, Operand::Jump(loop_label));
+        }, ExitStatus::StackOverflow, vec![]),
+        Case::new("pop_empty_stack", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Pop, Operand::None);
+        }, ExitStatus::StackAccessViolation, vec![]),
+        Case::new("load_beyond_stack", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i).push(Opcode::Load, Operand::Short(0));
+        }, ExitStatus::StackAccessViolation, vec![]),
+        Case::new("invoke_missing_args", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let func_i = asm.add_func(2, 2);
+            asm.func(main_i)
+                .push(Opcode::BPush, Operand::Byte(1))
+                .push(Opcode::Invoke, Operand::Index(func_i));
+            asm.func(func_i).push(Opcode::Ret, Operand::None);
+        }, ExitStatus::StackAccessViolation, vec![]),
+        Case::new("array_index_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::IAPush, Operand::Index(2))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::IALoad, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("array_store_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            asm.func(main_i)
+                .push(Opcode::LAPush, Operand::Index(1))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(0))
+                .push(Opcode::LAStore, Operand::None);
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+        Case::new("iadd_overflow", |asm| int_binary(asm, u32::MAX, 1, Opcode::IAdd), ExitStatus::ArithmeticOverflow, vec![]),
+        Case::new("lmul_overflow", |asm| long_binary(asm, u64::MAX, 2, Opcode::LMul), ExitStatus::ArithmeticOverflow, vec![]),
+        Case::new("isub_underflow", |asm| int_binary(asm, 0, 1, Opcode::ISub), ExitStatus::ArithmeticOverflow, vec![]),
+        Case::new("idiv_by_zero", |asm| int_binary(asm, 1, 0, Opcode::IDiv), ExitStatus::DivideByZero, vec![]),
+        Case::new("ldiv_by_zero", |asm| long_binary(asm, 1, 0, Opcode::LDiv), ExitStatus::DivideByZero, vec![]),
+        Case::new("wide_branch", |asm| wide_branch(asm, 1), ExitStatus::Success, int_bytes(1)),
+        Case::new("wide_branch_not_taken", |asm| wide_branch(asm, 0), ExitStatus::Success, int_bytes(2)),
+    ];
+}
+
+#[test]
+fn conform_to_opcode_table() {
+    check_cases(cases());
+}
+
+#[test]
+fn exit_on_unknown_opcode() {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    asm.func(main_i).push(Opcode::Nop, Operand::None);
+
+    // note: 末尾の exit 直前にある nop を未定義のオペコードに置き換え
+    let mut bytes = asm.assemble();
+    let nop_i = bytes.len() - 2;
+    bytes[nop_i] = 0xff;
+
+    assert_eq!(launch(bytes).exit_status, ExitStatus::UnknownOpcode);
+}
+
+#[test]
+fn exit_on_truncated_operand() {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    asm.func(main_i).push(Opcode::Nop, Operand::None);
+
+    // note: 末尾の exit を lpush に置き換えてオペランドを欠落させる
+    let mut bytes = asm.assemble();
+    let last_i = bytes.len() - 1;
+    bytes[last_i] = Opcode::LPush.into();
+
+    assert_eq!(launch(bytes).exit_status, ExitStatus::BytecodeAccessViolation);
+}
+
+#[test]
+fn select_wide_jumps() {
+    let mut asm = Assembler::new();
+    wide_branch(&mut asm, 1);
+    let bytes = asm.assemble();
+    let start_addr = Bytecode::read_pool_funcs(&bytes)[0].start_addr;
+
+    // note: int_prologue と bpush の後に if_w と goto_w が続き, 関数末尾の近い goto はそのまま
+    let if_addr = start_addr + 1 + size_of::<usize>() + 2 + 2;
+    let goto_addr = if_addr + 1 + size_of::<i32>();
+    let near_goto_addr = goto_addr + 1 + size_of::<i32>() + WIDE_BRANCH_NOP_LEN + 1 + size_of::<u32>();
+
+    assert_eq!(Opcode::from(bytes[if_addr]), Opcode::IfW);
+    assert_eq!(Opcode::from(bytes[goto_addr]), Opcode::GotoW);
+    assert_eq!(Opcode::from(bytes[near_goto_addr]), Opcode::Goto);
+    assert_eq!(i32::from_ne_bytes([bytes[if_addr + 1], bytes[if_addr + 2], bytes[if_addr + 3], bytes[if_addr + 4]]) as usize, 1 + size_of::<i32>() + WIDE_BRANCH_NOP_LEN);
+}
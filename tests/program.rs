@@ -0,0 +1,132 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+
+use rustnut::*;
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::linker::*;
+use rustnut::program::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+fn config() -> InterpreterConfig {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+    return config;
+}
+
+// note: 引数の 2 乗を返す square と 42 を出力する main
+fn square_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let square_i = asm.add_func(1, 1);
+
+    let f = asm.func(main_i);
+    int_prologue(f);
+    f.push(Opcode::IPush, Operand::Int(42));
+    emit_int(f);
+    f.push(Opcode::Exit, Operand::None);
+
+    asm.func(square_i)
+        .set_symbol("square", "(i)i")
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::IMul, Operand::None)
+        .push(Opcode::Ret, Operand::None);
+
+    return asm.assemble();
+}
+
+#[test]
+fn validate_on_load() {
+    assert_eq!(Program::load(vec![0u8; 4]).err(), Some(LoadError::InvalidHeader));
+
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.add_import("add");
+    assert_eq!(Program::load(asm.assemble()).err(), Some(LoadError::Link(LinkError::UndefinedSymbol("add".to_string()))));
+
+    // note: プール要素のないバイトコード
+    let mut bytes = square_program();
+    bytes.truncate(*HEADER_SIZE);
+    assert_eq!(Program::load(bytes.clone()).err(), Some(LoadError::InvalidEntryPoint));
+    assert_eq!(Program::load(bytes).err().unwrap().exit_status(), ExitStatus::BytecodeAccessViolation);
+
+    let program = Program::load(square_program()).unwrap();
+    assert_eq!(program.funcs().len(), 2);
+    assert_eq!(program.find_func("square").map(|v| v.pool_i), Some(1));
+    assert_eq!(program.find_func(1usize).map(|v| v.arg_len), Some(1));
+    assert!(program.find_func("cube").is_none());
+}
+
+#[test]
+fn run_repeatedly() {
+    let program = Program::load(square_program()).unwrap();
+    let config = config();
+
+    for _ in 0..3 {
+        let result = unsafe { Interpreter::launch_program(&program, &config, None) };
+        assert_eq!(result.exit_status, ExitStatus::Success);
+        assert_eq!(result.output, int_bytes(42));
+
+        let result = unsafe { Interpreter::call_program(&program, &config, 1, &[Value::Int(7)]) };
+        assert_eq!(result.ret_value, Some(Value::Int(49)));
+    }
+
+    let vm = ChesVM::with_config(config);
+    assert_eq!(vm.run_program(&program), ExitStatus::Success);
+    assert_eq!(vm.call_program(&program, "square", &[Value::Int(9)]), Ok(Value::Int(81)));
+}
+
+#[test]
+fn run_concurrently() {
+    let program = Arc::new(Program::load(square_program()).unwrap());
+
+    let handles = (0..4u32).map(|i| {
+        let program = Arc::clone(&program);
+
+        return thread::spawn(move || {
+            let vm = ChesVM::with_config(config());
+            assert_eq!(vm.run_program(&program), ExitStatus::Success);
+            return vm.call_program(&program, "square", &[Value::Int(i)]);
+        });
+    }).collect::<Vec<thread::JoinHandle<CallResult<Value>>>>();
+
+    let results = handles.into_iter().map(|v| v.join().unwrap()).collect::<Vec<CallResult<Value>>>();
+    assert_eq!(results, vec![Ok(Value::Int(0)), Ok(Value::Int(1)), Ok(Value::Int(4)), Ok(Value::Int(9))]);
+}
+
+#[test]
+fn collect_backtrace_from_loaded_program() {
+    let mut asm = Assembler::new();
+    let file_i = asm.add_file("fail.ches");
+    let main_i = asm.add_func(0, 0);
+    let fail_i = asm.add_func(0, 0);
+
+    asm.func(main_i)
+        .set_symbol("main", "()")
+        .push(Opcode::Invoke, Operand::Index(fail_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(fail_i).name = Some("fail".to_string());
+    asm.func(fail_i)
+        .set_location(file_i, 4, 2)
+        .push(Opcode::IPush, Operand::Int(7))
+        .push(Opcode::Throw, Operand::None);
+
+    let bytes = asm.assemble();
+    let program = Program::load(bytes.clone()).unwrap();
+    let config = config();
+
+    // note: 読み込み済みのプール要素とデバッグ情報からバイトコードを直接実行した場合と同じバックトレースを得る
+    let expected = unsafe { Interpreter::launch(bytes, &config) };
+    let actual = unsafe { Interpreter::launch_program(&program, &config, None) };
+    assert_eq!(actual.exit_status, ExitStatus::UncaughtException);
+    assert_eq!(actual.backtrace, expected.backtrace);
+    assert_eq!(actual.backtrace.iter().map(|v| v.func_name.as_deref()).collect::<Vec<Option<&str>>>(), vec![Some("fail"), Some("main")]);
+    assert_eq!(actual.backtrace[0].source, Some(("fail.ches".to_string(), 4, 2)));
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "safe-interpreter")]
+
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::runtime::*;
+use rustnut::safe_runtime::*;
+
+use crate::common::*;
+
+#[test]
+fn match_on_calls() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.add_func(3, 2);
+    asm.func(0).push(Opcode::Exit, Operand::None);
+    asm.func(1)
+        .push(Opcode::Load, Operand::Short(0))
+        .push(Opcode::Load, Operand::Short(1))
+        .push(Opcode::IAdd, Operand::None)
+        .push(Opcode::Dup, Operand::None)
+        .push(Opcode::Store, Operand::Short(2))
+        .push(Opcode::Ret, Operand::None);
+
+    let bytes = asm.assemble();
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+
+    let calls: Vec<(usize, Vec<Value>)> = vec![
+        (1, vec![Value::Int(40), Value::Int(2)]),
+        (1, vec![Value::Long(u64::MAX)]),
+        (1, vec![Value::Int(1)]),
+        (0, vec![]),
+        (9, vec![]),
+        (1, vec![Value::Int(1); 300]),
+    ];
+
+    for (pool_i, args) in calls {
+        let expected = unsafe { Interpreter::launch_call(bytes.clone(), &config, pool_i, &args) };
+        let actual = SafeInterpreter::launch_call(bytes.clone(), &config, pool_i, &args);
+        let name = format!("call of func {} with {} arguments", pool_i, args.len());
+
+        assert_eq!(actual.exit_status, expected.exit_status, "exit status of `{}`", name);
+        assert_eq!(actual.inst_count, expected.inst_count, "instruction count of `{}`", name);
+        assert_eq!(actual.fault, expected.fault, "fault of `{}`", name);
+        assert_eq!(actual.ret_value, expected.ret_value, "return value of `{}`", name);
+    }
+}
+
+#[test]
+fn match_on_malformed_header() {
+    let mut bytes = MAGIC_NUMBER.to_vec();
+    assert_same_result(bytes.clone(), "short header");
+
+    bytes.resize(*HEADER_SIZE, 0);
+    assert_same_result(bytes.clone(), "missing pool");
+
+    bytes.extend_from_slice(&usize::MAX.to_ne_bytes());
+    assert_same_result(bytes, "pool address out of range");
+}
+
+#[test]
+fn match_on_random_instructions() {
+    // note: xorshift による再現可能な乱数
+    let mut seed = 0x2545f4914f6cdd1du64;
+    let mut next_rand = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let excluded_opcodes: Vec<u8> = vec![
+        // note: 標準入力を待つ call 0x00 や無限ループとなる分岐を除外
+        Opcode::Call.into(),
+        Opcode::Goto.into(),
+        Opcode::If.into(),
+        Opcode::IfNot.into(),
+    ];
+
+    for i in 0..20000 {
+        let mut asm = Assembler::new();
+        asm.add_func(2, 0);
+        asm.add_func(3, 1);
+
+        let mut bytes = asm.assemble();
+        // note: 末尾の exit を除いて乱数列を追加
+        bytes.pop();
+
+        for _ in 0..next_rand() % 48 {
+            let rand = next_rand();
+            // note: オペコードの範囲に収まる値とオペランドになりやすい小さな値を混ぜる
+            let mut byte = if rand % 3 == 0 { (rand >> 8) as u8 % (Opcode::IfNot as u8) } else { (rand >> 8) as u8 % 4 };
+
+            if excluded_opcodes.contains(&byte) {
+                byte = Opcode::Nop.into();
+            }
+
+            bytes.push(byte);
+        }
+
+        bytes.push(Opcode::Exit.into());
+        assert_same_result(bytes, &format!("random program {}", i));
+    }
+}
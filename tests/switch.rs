@@ -0,0 +1,85 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: キーで分岐して 100 + ジャンプ先のインデックス, デフォルトの場合は 0xff を出力
+fn switch(asm: &mut Assembler, key: i32, is_lookup: bool) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    let default_label = f.new_label();
+    let end_label = f.new_label();
+    let target_labels = vec![f.new_label(), f.new_label(), f.new_label()];
+    int_prologue(f);
+    f.push(Opcode::IPush, Operand::Int(key as u32));
+
+    if is_lookup {
+        let targets = vec![(1000, target_labels[0]), (-7, target_labels[1]), (3, target_labels[2])];
+        f.push(Opcode::LookupSwitch, Operand::Lookup { default: default_label, targets: targets });
+    } else {
+        f.push(Opcode::TableSwitch, Operand::Table { default: default_label, low: -1, targets: target_labels.clone() });
+    }
+
+    f.set_label(default_label);
+    f.push(Opcode::IPush, Operand::Int(0xff))
+        .push(Opcode::Goto, Operand::Jump(end_label));
+
+    for (i, each_label) in target_labels.into_iter().enumerate() {
+        f.set_label(each_label);
+        f.push(Opcode::IPush, Operand::Int(100 + i as u32))
+            .push(Opcode::Goto, Operand::Jump(end_label));
+    }
+
+    f.set_label(end_label);
+    emit_int(f);
+}
+
+// note: i16 に収まらない位置のジャンプ先へ分岐して 1, デフォルトの場合は 0xff を出力
+fn far_switch(asm: &mut Assembler, key: i32, is_lookup: bool) {
+    let main_i = asm.add_func(0, 0);
+    let f = asm.func(main_i);
+    let default_label = f.new_label();
+    let far_label = f.new_label();
+    let end_label = f.new_label();
+    int_prologue(f);
+    f.push(Opcode::IPush, Operand::Int(key as u32));
+
+    if is_lookup {
+        f.push(Opcode::LookupSwitch, Operand::Lookup { default: default_label, targets: vec![(0, far_label)] });
+    } else {
+        f.push(Opcode::TableSwitch, Operand::Table { default: default_label, low: 0, targets: vec![far_label] });
+    }
+
+    f.set_label(default_label);
+    f.push(Opcode::IPush, Operand::Int(0xff))
+        .push(Opcode::Goto, Operand::Jump(end_label));
+
+    for _ in 0..i16::MAX as usize + 1 {
+        f.push(Opcode::Nop, Operand::None);
+    }
+
+    f.set_label(far_label);
+    f.push(Opcode::IPush, Operand::Int(1));
+    f.set_label(end_label);
+    emit_int(f);
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("tableswitch", |asm| switch(asm, 1, false), ExitStatus::Success, int_bytes(102)),
+        Case::new("tableswitch_lowest_key", |asm| switch(asm, -1, false), ExitStatus::Success, int_bytes(100)),
+        Case::new("tableswitch_default", |asm| switch(asm, 2, false), ExitStatus::Success, int_bytes(0xff)),
+        Case::new("lookupswitch", |asm| switch(asm, -7, true), ExitStatus::Success, int_bytes(101)),
+        Case::new("lookupswitch_default", |asm| switch(asm, 4, true), ExitStatus::Success, int_bytes(0xff)),
+        Case::new("tableswitch_far_target", |asm| far_switch(asm, 0, false), ExitStatus::Success, int_bytes(1)),
+        Case::new("tableswitch_far_default", |asm| far_switch(asm, 1, false), ExitStatus::Success, int_bytes(0xff)),
+        Case::new("lookupswitch_far_target", |asm| far_switch(asm, 0, true), ExitStatus::Success, int_bytes(1)),
+    ];
+}
+
+#[test]
+fn conform_to_switch_table() {
+    check_cases(cases());
+}
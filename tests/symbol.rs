@@ -0,0 +1,113 @@
+mod common;
+
+use std::mem::size_of;
+
+use rustnut::assembler::*;
+use rustnut::bytecode::*;
+use rustnut::linker::*;
+use rustnut::runtime::*;
+
+use crate::common::*;
+
+// note: main から名前付きの関数 fail を呼び出して例外を送出する
+fn named_program() -> Assembler {
+    let mut asm = Assembler::new();
+    let main_i = asm.add_func(0, 0);
+    let fail_i = asm.add_func(0, 0);
+
+    asm.func(main_i)
+        .push(Opcode::Invoke, Operand::Index(fail_i))
+        .push(Opcode::Exit, Operand::None);
+
+    asm.func(fail_i)
+        .set_symbol("fail", "()")
+        .push(Opcode::IPush, Operand::Int(1))
+        .push(Opcode::Throw, Operand::None);
+
+    return asm;
+}
+
+#[test]
+fn read_named_pool_entries() {
+    let bytes = named_program().assemble();
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+
+    assert_eq!(funcs[0].symbol, None);
+    assert_eq!(funcs[1].symbol, Some(FuncSymbol { name: "fail".to_string(), signature: "()".to_string() }));
+    assert_eq!(Bytecode::find_pool_func_by_name(&funcs, "fail").map(|v| v.pool_i), Some(1));
+    assert!(Bytecode::find_pool_func_by_name(&funcs, "main").is_none());
+
+    // note: 名前付きの要素も実行時には名前なしの要素と同様に扱われる
+    assert_eq!(launch(bytes).exit_status, ExitStatus::UncaughtException);
+}
+
+#[test]
+fn name_backtrace_frames_by_symbol() {
+    let result = launch(named_program().assemble());
+    assert_eq!(result.backtrace[0].func_name, Some("fail".to_string()));
+    assert_eq!(result.backtrace[1].func_name, None);
+
+    // note: デバッグ情報の関数名が優先される
+    let mut asm = named_program();
+    asm.func(1).name = Some("debug_fail".to_string());
+    let result = launch(asm.assemble());
+    assert_eq!(result.backtrace[0].func_name, Some("debug_fail".to_string()));
+}
+
+#[test]
+fn read_unknown_entry_kind() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0).push(Opcode::Exit, Operand::None);
+    let bytes = asm.assemble();
+
+    let mut entry_addr_bytes = [0u8; size_of::<usize>()];
+    entry_addr_bytes.copy_from_slice(&bytes[*HEADER_SIZE..*HEADER_SIZE + size_of::<usize>()]);
+    let entry_addr = usize::from_ne_bytes(entry_addr_bytes);
+
+    // note: ハンドラ数の直後の種別を未知の値に書き換える
+    let mut unknown_bytes = bytes.clone();
+    unknown_bytes[entry_addr + size_of::<usize>() + size_of::<u16>() + size_of::<u8>() + size_of::<u16>()] = 0xff;
+
+    let funcs = Bytecode::read_pool_funcs(&unknown_bytes);
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(funcs[0].symbol, None);
+}
+
+#[test]
+fn keep_symbols_on_link() {
+    let mut main_asm = named_program();
+    main_asm.add_export(1, "fail");
+
+    let mut lib_asm = Assembler::new();
+    let helper_i = lib_asm.add_func(0, 0);
+    lib_asm.add_export(helper_i, "helper");
+    lib_asm.func(helper_i)
+        .set_symbol("helper", "()")
+        .push(Opcode::Ret, Operand::None);
+
+    let mut linker = Linker::new();
+    linker.add_module(main_asm.assemble());
+    linker.add_module(lib_asm.assemble());
+    let bytes = linker.link().unwrap();
+
+    let funcs = Bytecode::read_pool_funcs(&bytes);
+    assert_eq!(funcs.iter().map(|v| v.symbol.as_ref().map(|v| v.name.clone())).collect::<Vec<Option<String>>>(), vec![None, Some("fail".to_string()), Some("helper".to_string())]);
+}
+
+#[test]
+fn reject_oversized_symbol() {
+    let func = PoolFunc {
+        pool_i: 0,
+        start_addr: 0,
+        var_len: 0,
+        arg_len: 0,
+        handlers: Vec::new(),
+        symbol: Some(FuncSymbol {
+            name: "f".to_string(),
+            signature: "i".repeat(MAX_ENCODED_LEN + 1),
+        }),
+    };
+
+    assert_eq!(func.encode(), Err(EncodeError::StringTooLong(MAX_ENCODED_LEN + 1)));
+}
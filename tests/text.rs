@@ -0,0 +1,183 @@
+mod common;
+
+use rustnut::assembler::*;
+use rustnut::runtime::*;
+use rustnut::text::*;
+
+use crate::common::*;
+
+fn apply(text_call: TextCall, args: Vec<TextValue>) -> Result<Vec<TextValue>, TextError> {
+    return text_call.apply(&args);
+}
+
+fn cases() -> Vec<Case> {
+    return vec![
+        Case::new("text_format_and_concat", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let sep_i = asm.add_string(" / ");
+            asm.func(main_i)
+                .push(Opcode::IPush, Operand::Int(-42i32 as u32))
+                .push(Opcode::Call, Operand::Byte(TextCall::FormatInt as u8))
+                .push(Opcode::BAConst, Operand::Short(sep_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::Concat as u8))
+                .push(Opcode::LPush, Operand::Long(1.5f64.to_bits()))
+                .push(Opcode::Call, Operand::Byte(TextCall::FormatDouble as u8))
+                .push(Opcode::Call, Operand::Byte(TextCall::Concat as u8))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, b"-42 / 1.5".to_vec()),
+        Case::new("text_substring", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("h\u{e9}llo\u{4e16}\u{754c}");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(5))
+                .push(Opcode::Call, Operand::Byte(TextCall::Substring as u8))
+                .push(Opcode::Call, Operand::Byte(0x01));
+        }, ExitStatus::Success, "\u{e9}llo\u{4e16}".as_bytes().to_vec()),
+        Case::new("text_char_len", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("\u{4e16}\u{754c}!");
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::CharLen as u8));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(3)),
+        Case::new("text_find", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let text_i = asm.add_string("h\u{e9}llo\u{4e16}\u{754c}");
+            let pattern_i = asm.add_string("\u{754c}");
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::BAConst, Operand::Short(text_i))
+                .push(Opcode::BAConst, Operand::Short(pattern_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::Find as u8));
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(6)),
+        Case::new("text_compare", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let left_i = asm.add_string("abc");
+            let right_i = asm.add_string("abd");
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::BAConst, Operand::Short(left_i))
+                .push(Opcode::BAConst, Operand::Short(right_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::Compare as u8));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(-1i32 as u32)),
+        Case::new("text_parse_long", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("-1234");
+            let f = asm.func(main_i);
+            long_prologue(f);
+            f.push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::ParseLong as u8))
+                .push(Opcode::Pop, Operand::None);
+            emit_long(f);
+        }, ExitStatus::Success, long_bytes(-1234i64 as u64)),
+        Case::new("text_parse_int_failure", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("12x");
+            let f = asm.func(main_i);
+            int_prologue(f);
+            f.push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::ParseInt as u8));
+            emit_int(f);
+        }, ExitStatus::Success, int_bytes(0)),
+        Case::new("text_invalid_utf8", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_bytes(&[0x61, 0xff]);
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::Call, Operand::Byte(TextCall::CharLen as u8));
+        }, ExitStatus::InvalidEncoding, vec![]),
+        Case::new("text_substring_out_of_range", |asm| {
+            let main_i = asm.add_func(0, 0);
+            let const_i = asm.add_string("\u{4e16}\u{754c}");
+            asm.func(main_i)
+                .push(Opcode::BAConst, Operand::Short(const_i))
+                .push(Opcode::LPush, Operand::Long(1))
+                .push(Opcode::LPush, Operand::Long(2))
+                .push(Opcode::Call, Operand::Byte(TextCall::Substring as u8));
+        }, ExitStatus::ArrayAccessViolation, vec![]),
+    ];
+}
+
+#[test]
+fn conform_to_text_table() {
+    check_cases(cases());
+}
+
+#[test]
+fn resolve_call_numbers() {
+    assert_eq!(TextCall::from_code(0x10), Some(TextCall::Validate));
+    assert_eq!(TextCall::from_code(0x1b), Some(TextCall::ParseDouble));
+    assert_eq!(TextCall::from_code(0x01), None);
+    assert_eq!(TextCall::from_code(0x1c), None);
+}
+
+#[test]
+fn count_chars_instead_of_bytes() {
+    let text = "a\u{e9}\u{4e16}\u{1f600}".as_bytes().to_vec();
+    assert_eq!(apply(TextCall::CharLen, vec![TextValue::Array(text.clone())]), Ok(vec![TextValue::Usize(4)]));
+    assert_eq!(apply(TextCall::Substring, vec![TextValue::Array(text.clone()), TextValue::Usize(3), TextValue::Usize(1)]), Ok(vec![TextValue::Array("\u{1f600}".as_bytes().to_vec())]));
+    assert_eq!(apply(TextCall::Find, vec![TextValue::Array(text), TextValue::Array(b"".to_vec())]), Ok(vec![TextValue::Usize(0)]));
+}
+
+#[test]
+fn report_missing_pattern() {
+    let values = apply(TextCall::Find, vec![TextValue::Array(b"abc".to_vec()), TextValue::Array(b"abcd".to_vec())]);
+    assert_eq!(values, Ok(vec![TextValue::Usize(usize::MAX)]));
+}
+
+#[test]
+fn round_trip_double() {
+    for each_value in [1.0, -0.25, 1e100, f64::INFINITY] {
+        let text = match apply(TextCall::FormatDouble, vec![TextValue::Long(each_value.to_bits())]).unwrap().remove(0) {
+            TextValue::Array(v) => v,
+            v => panic!("unexpected value {:?}", v),
+        };
+
+        assert_eq!(apply(TextCall::ParseDouble, vec![TextValue::Array(text)]), Ok(vec![TextValue::Long(each_value.to_bits()), TextValue::Int(1)]));
+    }
+}
+
+#[test]
+fn validate_without_fault() {
+    assert_eq!(apply(TextCall::Validate, vec![TextValue::Array(vec![0xe4, 0xb8])]), Ok(vec![TextValue::Int(0)]));
+    assert_eq!(apply(TextCall::CharLen, vec![TextValue::Array(vec![0x61, 0xe4, 0xb8])]), Err(TextError::InvalidUtf8 { valid_len: 1 }));
+}
+
+#[test]
+fn reject_invalid_utf8_operands() {
+    let (valid, invalid) = (TextValue::Array(b"a".to_vec()), TextValue::Array(vec![0x61, 0xff]));
+    assert_eq!(apply(TextCall::Concat, vec![valid.clone(), invalid.clone()]), Err(TextError::InvalidUtf8 { valid_len: 1 }));
+    assert_eq!(apply(TextCall::Compare, vec![invalid, valid]), Err(TextError::InvalidUtf8 { valid_len: 1 }));
+}
+
+#[test]
+fn reject_mismatched_arguments() {
+    let text = TextValue::Array(b"abc".to_vec());
+    assert_eq!(apply(TextCall::CharLen, vec![]), Err(TextError::Argument { index: 0 }));
+    assert_eq!(apply(TextCall::CharLen, vec![TextValue::Usize(3)]), Err(TextError::Argument { index: 0 }));
+    assert_eq!(apply(TextCall::CharLen, vec![text.clone(), text.clone()]), Err(TextError::Argument { index: 1 }));
+    assert_eq!(apply(TextCall::Substring, vec![text.clone(), TextValue::Usize(0), TextValue::Int(1)]), Err(TextError::Argument { index: 2 }));
+    assert_eq!(apply(TextCall::FormatInt, vec![TextValue::Long(1)]), Err(TextError::Argument { index: 0 }));
+    assert_eq!(TextError::Argument { index: 2 }.to_fault(), (ExitStatus::StackAccessViolation, FaultDetail::TextArgument(2)));
+}
+
+#[test]
+fn report_invalid_encoding() {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    let const_i = asm.add_bytes(&[0x61, 0x62, 0xff]);
+    asm.func(0)
+        .push(Opcode::BAConst, Operand::Short(const_i))
+        .push(Opcode::Call, Operand::Byte(TextCall::CharLen as u8));
+
+    let fault = launch(asm.assemble()).fault.unwrap();
+    assert_eq!((fault.status, fault.opcode), (ExitStatus::InvalidEncoding, Opcode::Call));
+    assert_eq!(fault.detail, FaultDetail::Encoding { valid_len: 2 });
+    assert!(fault.to_string().ends_with("invalid utf-8 sequence after 2 bytes"));
+}
@@ -0,0 +1,68 @@
+use std::io::{Cursor, Error, ErrorKind, Read};
+
+use rustnut::*;
+use rustnut::assembler::*;
+use rustnut::linker::*;
+use rustnut::program::*;
+use rustnut::runtime::*;
+
+fn vm() -> ChesVM {
+    let mut config = InterpreterConfig::new();
+    config.is_traced = false;
+    config.is_output_captured = true;
+    return ChesVM::with_config(config);
+}
+
+fn divide_program(divisor: u32) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.func(0)
+        .push(Opcode::IPush, Operand::Int(42))
+        .push(Opcode::IPush, Operand::Int(divisor))
+        .push(Opcode::IDiv, Operand::None)
+        .push(Opcode::Exit, Operand::None);
+
+    return asm.assemble();
+}
+
+struct FailingReader {}
+
+impl Read for FailingReader {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        return Err(Error::new(ErrorKind::Other, "unreadable"));
+    }
+}
+
+#[test]
+fn run_from_memory() {
+    assert_eq!(vm().run_bytes(&divide_program(2)), Ok(ExitStatus::Success));
+    assert_eq!(vm().run_bytes(&divide_program(0)), Ok(ExitStatus::DivideByZero));
+    assert_eq!(vm().run_bytes(&[0u8; 4]), Err(LoadError::InvalidHeader));
+
+    // note: 未解決のインポートを持つモジュールはリンクに失敗する
+    let mut asm = Assembler::new();
+    asm.add_func(0, 0);
+    asm.add_import("add");
+    asm.func(0).push(Opcode::Exit, Operand::None);
+    let err = vm().run_bytes(&asm.assemble()).unwrap_err();
+    assert_eq!(err, LoadError::Link(LinkError::UndefinedSymbol("add".to_string())));
+    assert_eq!(err.exit_status(), ExitStatus::LinkError);
+}
+
+#[test]
+fn run_from_reader() {
+    assert_eq!(vm().run_reader(Cursor::new(divide_program(2))).unwrap(), Ok(ExitStatus::Success));
+    assert_eq!(vm().run_reader(&divide_program(0)[..]).unwrap(), Ok(ExitStatus::DivideByZero));
+    assert_eq!(vm().run_reader(FailingReader {}).unwrap_err().kind(), ErrorKind::Other);
+}
+
+#[test]
+fn load_from_memory() {
+    let mut vm = vm();
+    assert_eq!(vm.load_bytes(&divide_program(2)), Ok(()));
+    assert_eq!(vm.call(0usize, &[]), Ok(Value::Void));
+
+    // note: 読み込みに失敗しても読み込み済みのプログラムは保持される
+    assert_eq!(vm.load_bytes(&[0u8; 4]), Err(LoadError::InvalidHeader));
+    assert_eq!(vm.call(0usize, &[]), Ok(Value::Void));
+}